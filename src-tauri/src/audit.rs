@@ -0,0 +1,114 @@
+use crate::error::{AppError, ErrorKind};
+use crate::types::*;
+use crate::{i18n, lock, storage};
+use std::fs;
+use std::path::Path;
+
+// Oldest calls are dropped beyond this; every entry carries the whole prompt.
+const KEEP_ENTRIES: usize = 500;
+
+fn index_item(entry: &LlmAuditEntry) -> LlmAuditIndexItem {
+    LlmAuditIndexItem {
+        id: entry.id.clone(),
+        created_at: entry.created_at.clone(),
+        action: entry.action.clone(),
+        endpoint_name: entry.endpoint_name.clone(),
+        model: entry.model.clone(),
+        latency_ms: entry.latency_ms,
+        total_tokens: entry.usage.as_ref().map(|u| u.total_tokens),
+        ok: entry.error.is_none(),
+        replay_of: entry.replay_of.clone(),
+    }
+}
+
+fn read_index(root: &Path) -> Result<Vec<LlmAuditIndexItem>, AppError> {
+    let file = storage::llm_log_index_file(root);
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let raw = storage::read_tracked(&file, i18n::t("what.llmLog"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.llmLog"), e))
+}
+
+// Adds a call to the project's log, dropping the oldest ones past the limit.
+pub fn append(project_dir: &str, entry: &LlmAuditEntry) -> Result<(), AppError> {
+    append_keeping(project_dir, entry, KEEP_ENTRIES)
+}
+
+fn append_keeping(project_dir: &str, entry: &LlmAuditEntry, keep: usize) -> Result<(), AppError> {
+    let root = Path::new(project_dir);
+    let _write = lock::write_guard(root);
+    storage::atomic_write_json(&storage::llm_log_entry_file(root, &entry.id), entry)?;
+
+    let mut index = read_index(root)?;
+    index.push(index_item(entry));
+    let excess = index.len().saturating_sub(keep);
+    for old in index.drain(..excess) {
+        let file = storage::llm_log_entry_file(root, &old.id);
+        let _ = fs::remove_file(&file);
+        lock::forget(&file);
+    }
+    storage::atomic_write_json(&storage::llm_log_index_file(root), &index)
+}
+
+// Newest first.
+pub fn list(project_dir: &str) -> Result<Vec<LlmAuditIndexItem>, AppError> {
+    let mut index = read_index(Path::new(project_dir))?;
+    index.reverse();
+    Ok(index)
+}
+
+pub fn load(project_dir: &str, id: &str) -> Result<LlmAuditEntry, AppError> {
+    let file = storage::llm_log_entry_file(Path::new(project_dir), id);
+    // ids are uuids; anything else could point outside the log
+    if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') || !file.exists() {
+        return Err(AppError::new(ErrorKind::NotFound, "llm.auditNotFound"));
+    }
+    let raw = storage::read_tracked(&file, i18n::t("what.llmLogEntry"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.llmLogEntry"), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> LlmAuditEntry {
+        LlmAuditEntry {
+            id: id.to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            action: "continue".to_string(),
+            endpoint_id: "ep".to_string(),
+            endpoint_name: "OpenAI".to_string(),
+            model: "gpt-4o".to_string(),
+            parameters: ModelParameters::default_for_writing(),
+            messages: vec![serde_json::json!({ "role": "user", "content": "写下去" })],
+            response_schema: None,
+            latency_ms: 1200,
+            usage: Some(TokenUsage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+            }),
+            response: Some("{}".to_string()),
+            error: None,
+            replay_of: None,
+        }
+    }
+
+    #[test]
+    fn appends_lists_newest_first_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        for i in 0..5 {
+            append_keeping(&root, &entry(&i.to_string()), 3).unwrap();
+        }
+
+        let items = list(&root).unwrap();
+        assert_eq!(items.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), ["4", "3", "2"]);
+        assert_eq!(items[0].total_tokens, Some(15));
+        assert!(items[0].ok);
+        assert!(load(&root, "0").is_err());
+        assert!(!storage::llm_log_entry_file(dir.path(), "1").exists());
+        assert_eq!(load(&root, "2").unwrap().messages[0]["content"], "写下去");
+    }
+}
@@ -0,0 +1,443 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, lock, prompt, storage, types::*};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST: &str = "manifest.json";
+const FORMAT: &str = "creatorai-backup";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: String,
+    version: u32,
+    app_version: String,
+    project_name: String,
+    created_at: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn zip_err(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Io, "backup.archive").caused_by(e)
+}
+
+// API keys live in the system keychain, but strip anything credential-like in case
+// a config was hand-edited or written by a future version.
+fn scrub_secrets(v: &mut serde_json::Value) {
+    match v {
+        serde_json::Value::Object(map) => {
+            map.retain(|k, _| {
+                let k = k.to_lowercase().replace(['_', '-'], "");
+                !matches!(k.as_str(), "apikey" | "secret" | "password" | "accesstoken" | "authorization")
+            });
+            map.values_mut().for_each(scrub_secrets);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(scrub_secrets),
+        _ => {}
+    }
+}
+
+fn timestamp_for_file_name() -> String {
+    let t = time::OffsetDateTime::now_utc();
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        t.year(),
+        t.month() as u8,
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
+
+// The bytes a project file is archived as.
+fn archive_data(rel: &str, path: &Path) -> Result<Vec<u8>, AppError> {
+    let mut data = fs::read(path).map_err(|e| {
+        AppError::new(ErrorKind::Io, "file.read")
+            .with_param("file", path.display())
+            .caused_by(e)
+    })?;
+    if rel == "llm_config.json" {
+        if let Ok(mut v) = serde_json::from_slice::<serde_json::Value>(&data) {
+            scrub_secrets(&mut v);
+            data =
+                serde_json::to_vec_pretty(&v).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?;
+        }
+    }
+    Ok(data)
+}
+
+fn write_archive(root: &Path, output: &Path) -> Result<(), AppError> {
+    // no save may land between listing the files and reading them
+    let _write = lock::write_guard(root);
+    let files = storage::project_files(root)?;
+
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+    }
+    let tmp = durable::temp_path(output);
+    let file = fs::File::create(&tmp).map_err(|e| AppError::io("backup.create", &tmp, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut entries = vec![];
+    for (rel, path) in files {
+        let data = archive_data(&rel, &path)?;
+        zip.start_file(rel.as_str(), options).map_err(zip_err)?;
+        zip.write_all(&data).map_err(zip_err)?;
+        entries.push(ManifestEntry {
+            path: rel,
+            size: data.len() as u64,
+            sha256: sha256_hex(&data),
+        });
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        project_name: root
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        created_at: prompt::now_iso(),
+        files: entries,
+    };
+    let raw =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?;
+    zip.start_file(MANIFEST, options).map_err(zip_err)?;
+    zip.write_all(&raw).map_err(zip_err)?;
+    zip.finish().map_err(zip_err)?;
+
+    durable::persist(&tmp, output).map_err(|e| AppError::io("backup.save", output, e))
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let meta = fs::metadata(path).ok()?;
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let created_at = meta
+        .modified()
+        .ok()
+        .map(time::OffsetDateTime::from)
+        .and_then(|t| t.format(&time::format_description::well_known::Rfc3339).ok())
+        .unwrap_or_default();
+    Some(BackupInfo {
+        path: path.to_string_lossy().to_string(),
+        automatic: file_name.starts_with("auto_"),
+        file_name,
+        created_at,
+        size: meta.len(),
+    })
+}
+
+fn create_in_backups_dir(root: &Path, prefix: &str) -> Result<BackupInfo, AppError> {
+    let dir = storage::backups_dir(root);
+    let mut path = dir.join(format!("{prefix}_{}.zip", timestamp_for_file_name()));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{prefix}_{}_{n}.zip", timestamp_for_file_name()));
+    }
+    write_archive(root, &path)?;
+    backup_info(&path).ok_or_else(|| AppError::new(ErrorKind::Io, "backup.info"))
+}
+
+// Writes to `output_path` when given, otherwise into the project's backup folder.
+pub fn backup_project(project_dir: String, output_path: Option<String>) -> Result<BackupInfo, AppError> {
+    let root = PathBuf::from(&project_dir);
+    if !root.is_dir() {
+        return Err(AppError::new(ErrorKind::NotFound, "storage.projectMissing"));
+    }
+    match output_path.filter(|p| !p.trim().is_empty()) {
+        Some(out) => {
+            let out = PathBuf::from(out);
+            write_archive(&root, &out)?;
+            backup_info(&out).ok_or_else(|| AppError::new(ErrorKind::Io, "backup.info"))
+        }
+        None => create_in_backups_dir(&root, "manual"),
+    }
+}
+
+// Taken by init_project before it upgrades an older project layout; never pruned.
+pub(crate) fn backup_before_migration(root: &Path, from_version: u32) -> Result<BackupInfo, AppError> {
+    create_in_backups_dir(root, &format!("premigration_v{from_version}"))
+}
+
+// Swaps the backups holding any file `exposes` flags for one fresh backup of the project as it
+// is now, which is taken first so there is always something to restore. Archives that can't be
+// read are left alone. Returns how many were removed.
+pub(crate) fn replace_backups_exposing(root: &Path, exposes: impl Fn(&Path, &[u8]) -> bool) -> Result<u32, AppError> {
+    let exposing = list_backups(root.to_string_lossy().to_string())?
+        .into_iter()
+        .filter(|b| {
+            validate_archive(&b.path).is_ok_and(|files| files.iter().any(|(rel, data)| exposes(&root.join(rel), data)))
+        })
+        .collect::<Vec<_>>();
+    if exposing.is_empty() {
+        return Ok(0);
+    }
+    create_in_backups_dir(root, "encrypted")?;
+    for old in &exposing {
+        fs::remove_file(&old.path).map_err(|e| AppError::io("io.remove", Path::new(&old.path), e))?;
+    }
+    Ok(exposing.len() as u32)
+}
+
+pub fn list_backups(project_dir: String) -> Result<Vec<BackupInfo>, AppError> {
+    let dir = storage::backups_dir(Path::new(&project_dir));
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut out = fs::read_dir(&dir)
+        .map_err(|e| AppError::io("backup.readDir", &dir, e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "zip"))
+        .filter_map(|p| backup_info(&p))
+        .collect::<Vec<_>>();
+    out.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.file_name.cmp(&a.file_name)));
+    Ok(out)
+}
+
+fn prune_automatic(project_dir: &str, keep: u32) -> Result<(), AppError> {
+    let autos = list_backups(project_dir.to_string())?
+        .into_iter()
+        .filter(|b| b.automatic)
+        .collect::<Vec<_>>();
+    for old in autos.into_iter().skip(keep.max(1) as usize) {
+        fs::remove_file(&old.path).map_err(|e| AppError::io("backup.removeOld", Path::new(&old.path), e))?;
+    }
+    Ok(())
+}
+
+// Called periodically by the scheduler; creates an automatic backup when one is due and the
+// project changed since the newest backup.
+pub fn run_scheduled(project_dir: &str) -> Result<Option<BackupInfo>, AppError> {
+    let settings = storage::load_backup_settings(project_dir.to_string())?;
+    if !settings.enabled || !Path::new(project_dir).is_dir() {
+        return Ok(None);
+    }
+    let interval = Duration::from_secs(u64::from(settings.interval_minutes.max(1)) * 60);
+    let last = list_backups(project_dir.to_string())?
+        .into_iter()
+        .find(|b| b.automatic)
+        .and_then(|b| fs::metadata(b.path).ok()?.modified().ok());
+    let due = match last {
+        Some(t) => SystemTime::now().duration_since(t).unwrap_or_default() >= interval,
+        None => true,
+    };
+    if !due {
+        return Ok(None);
+    }
+    let newest = list_backups(project_dir.to_string())?.into_iter().next();
+    if newest.is_some_and(|b| unchanged_since(Path::new(project_dir), Path::new(&b.path))) {
+        return Ok(None);
+    }
+    let info = create_in_backups_dir(Path::new(project_dir), "auto")?;
+    prune_automatic(project_dir, settings.keep)?;
+    Ok(Some(info))
+}
+
+fn read_manifest<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>) -> Result<Manifest, AppError> {
+    let mut entry = zip
+        .by_name(MANIFEST)
+        .map_err(|_| AppError::new(ErrorKind::Invalid, "backup.missingManifest"))?;
+    let mut raw = String::new();
+    entry.read_to_string(&mut raw).map_err(zip_err)?;
+    serde_json::from_str(&raw).map_err(|e| AppError::new(ErrorKind::Invalid, "backup.badManifest").caused_by(e))
+}
+
+// True when the project holds exactly the files in `archive`, with the same contents.
+fn unchanged_since(root: &Path, archive: &Path) -> bool {
+    let _write = lock::write_guard(root);
+    let manifest = fs::File::open(archive)
+        .map_err(zip_err)
+        .and_then(|f| ZipArchive::new(f).map_err(zip_err))
+        .and_then(|mut zip| read_manifest(&mut zip));
+    let (Ok(manifest), Ok(files)) = (manifest, storage::project_files(root)) else {
+        return false;
+    };
+    files.len() == manifest.files.len()
+        && files.iter().zip(&manifest.files).all(|((rel, path), entry)| {
+            *rel == entry.path && archive_data(rel, path).is_ok_and(|data| sha256_hex(&data) == entry.sha256)
+        })
+}
+
+// Every entry must be listed in the manifest with a matching checksum, and no path may
+// escape the target directory. Returns the validated file contents.
+fn validate_archive(archive_path: &str) -> Result<Vec<(PathBuf, Vec<u8>)>, AppError> {
+    let file = fs::File::open(archive_path).map_err(|e| AppError::io("backup.open", Path::new(archive_path), e))?;
+    let mut zip =
+        ZipArchive::new(file).map_err(|e| AppError::new(ErrorKind::Invalid, "backup.corrupt").caused_by(e))?;
+
+    let manifest = read_manifest(&mut zip)?;
+    if manifest.format != FORMAT {
+        return Err(AppError::new(ErrorKind::Invalid, "backup.notBackup"));
+    }
+    if manifest.version > FORMAT_VERSION {
+        return Err(AppError::new(ErrorKind::Invalid, "backup.tooNew").with_param("version", manifest.version));
+    }
+
+    let expected = manifest
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f))
+        .collect::<HashMap<_, _>>();
+    let mut files = vec![];
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(zip_err)?;
+        if entry.is_dir() || entry.name() == MANIFEST {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let rel = entry
+            .enclosed_name()
+            .ok_or_else(|| AppError::new(ErrorKind::Invalid, "backup.unsafePath").with_param("name", &name))?;
+        let meta = expected
+            .get(name.as_str())
+            .ok_or_else(|| AppError::new(ErrorKind::Invalid, "backup.unlistedFile").with_param("name", &name))?;
+        let mut data = Vec::with_capacity(meta.size as usize);
+        entry.read_to_end(&mut data).map_err(zip_err)?;
+        if data.len() as u64 != meta.size || sha256_hex(&data) != meta.sha256 {
+            return Err(AppError::new(ErrorKind::Invalid, "backup.checksum").with_param("name", name));
+        }
+        files.push((rel, data));
+    }
+    if files.len() != manifest.files.len() {
+        return Err(AppError::new(ErrorKind::Invalid, "backup.incomplete"));
+    }
+    Ok(files)
+}
+
+pub fn restore_project(archive_path: String, target_dir: String) -> Result<ProjectInfo, AppError> {
+    let target = PathBuf::from(&target_dir);
+    if target.exists() {
+        let empty = fs::read_dir(&target)
+            .map_err(|e| AppError::io("backup.readTarget", &target, e))?
+            .next()
+            .is_none();
+        if !empty {
+            return Err(AppError::new(ErrorKind::Invalid, "backup.targetNotEmpty"));
+        }
+    }
+
+    let files = validate_archive(&archive_path)?;
+    for (rel, data) in files {
+        let path = target.join(rel);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+        }
+        durable::write(&path, &data).map_err(|e| {
+            AppError::new(ErrorKind::Io, "file.write")
+                .with_param("file", path.display())
+                .caused_by(e)
+        })?;
+    }
+    storage::init_project(target_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_and_restore_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("novel").to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let mut ch = storage::load_chapter(root.clone(), 1).unwrap();
+        ch.content = "正文".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+
+        let info = backup_project(root.clone(), None).unwrap();
+        assert!(!info.automatic);
+        assert_eq!(list_backups(root.clone()).unwrap().len(), 1);
+
+        // a second backup must not contain the first one
+        let again = backup_project(root.clone(), None).unwrap();
+        let zip = ZipArchive::new(fs::File::open(&again.path).unwrap()).unwrap();
+        assert!(zip.file_names().all(|n| !n.contains("backups")));
+
+        let restored = dir.path().join("restored").to_string_lossy().to_string();
+        restore_project(info.path.clone(), restored.clone()).unwrap();
+        assert_eq!(storage::load_chapter(restored.clone(), 1).unwrap().content, "正文");
+        assert!(restore_project(info.path, restored).is_err());
+    }
+
+    #[test]
+    fn scheduled_backup_skips_unchanged_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("novel").to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let settings = BackupSettings {
+            enabled: true,
+            interval_minutes: 1,
+            keep: 5,
+        };
+        storage::save_backup_settings(root.clone(), &settings).unwrap();
+        let first = run_scheduled(&root).unwrap().unwrap();
+
+        // make the last backup look old enough for the next one to be due
+        let age = |path: &str| {
+            let earlier = SystemTime::now() - Duration::from_secs(3600);
+            fs::File::options().write(true).open(path).unwrap().set_modified(earlier).unwrap();
+        };
+        age(&first.path);
+        assert!(run_scheduled(&root).unwrap().is_none());
+
+        let mut ch = storage::load_chapter(root.clone(), 1).unwrap();
+        ch.content = "新的正文".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+        assert!(run_scheduled(&root).unwrap().is_some());
+    }
+
+    #[test]
+    fn restore_rejects_tampered_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        let manifest = Manifest {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            app_version: String::new(),
+            project_name: "x".to_string(),
+            created_at: String::new(),
+            files: vec![ManifestEntry {
+                path: "config.json".to_string(),
+                size: 2,
+                sha256: sha256_hex(b"{}"),
+            }],
+        };
+        zip.start_file("config.json", options).unwrap();
+        zip.write_all(b"[]").unwrap();
+        zip.start_file(MANIFEST, options).unwrap();
+        zip.write_all(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+        zip.finish().unwrap();
+
+        let target = dir.path().join("out").to_string_lossy().to_string();
+        let err = restore_project(path.to_string_lossy().to_string(), target.clone()).unwrap_err();
+        assert_eq!((err.kind, err.key.as_str()), (ErrorKind::Invalid, "backup.checksum"));
+        assert!(!Path::new(&target).exists());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{backup, durable, i18n, lock, secure, storage, types::*};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// Encrypted files start with this header, followed by a 24-byte nonce and the ciphertext.
+// Anything without it is read as plain text, so half-converted projects stay readable.
+const MAGIC: &[u8] = b"CREATORAI-ENC1\n";
+const NONCE_LEN: usize = 24;
+const CHECK_PLAINTEXT: &[u8] = b"creatorai";
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+type Key = [u8; 32];
+
+fn settings_file(project_dir: &Path) -> PathBuf {
+    project_dir.join(".creatorai").join("encryption.json")
+}
+
+// Keys of unlocked projects; they never touch the disk unless the user asked to remember them.
+fn keys() -> &'static Mutex<HashMap<PathBuf, Key>> {
+    static KEYS: OnceLock<Mutex<HashMap<PathBuf, Key>>> = OnceLock::new();
+    KEYS.get_or_init(Default::default)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn load_settings(root: &Path) -> Result<Option<EncryptionSettings>, AppError> {
+    let file = settings_file(root);
+    if !file.exists() {
+        return Ok(None);
+    }
+    let what = i18n::t("what.encryptionSettings");
+    let raw = fs::read_to_string(&file).map_err(|e| AppError::read(what, e))?;
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| AppError::malformed(what, e))
+}
+
+// Chapters, summaries, chat sessions and logged model calls are encrypted; indexes, presets
+// and model settings stay readable. Returns the project root the file belongs to.
+fn protected_root(path: &Path) -> Option<&Path> {
+    let name = path.file_name()?.to_str()?;
+    let parent = path.parent()?;
+    if name == "summaries.json" {
+        return Some(parent);
+    }
+    match parent.file_name()?.to_str()? {
+        "chapters" if name.starts_with("chapter_") => parent.parent(),
+        "chat_sessions" if name.starts_with("session_") => parent.parent(),
+        "llm_log" if name.starts_with("entry_") => parent.parent()?.parent(),
+        _ => None,
+    }
+}
+
+fn seal(key: &Key, aad: &[u8], plain: &[u8]) -> Result<Vec<u8>, AppError> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .map_err(|_| AppError::new(ErrorKind::Other, "crypto.sealFailed"))?;
+    Ok([MAGIC, nonce.as_slice(), &sealed].concat())
+}
+
+fn open(key: &Key, aad: &[u8], raw: &[u8]) -> Option<Vec<u8>> {
+    let body = raw.strip_prefix(MAGIC)?;
+    if body.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, sealed) = body.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.into());
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: sealed, aad })
+        .ok()
+}
+
+// Binds each file's ciphertext to its name, so chapters can't be swapped around on disk.
+fn aad(path: &Path) -> Vec<u8> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, AppError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| AppError::new(ErrorKind::Other, "crypto.deriveKey").caused_by(e))?;
+    Ok(key)
+}
+
+fn verify_key(settings: &EncryptionSettings, key: &Key) -> bool {
+    unhex(&settings.check)
+        .and_then(|raw| open(key, b"check", &raw))
+        .is_some_and(|p| p == CHECK_PLAINTEXT)
+}
+
+fn key_for_passphrase(settings: &EncryptionSettings, passphrase: &str) -> Result<Key, AppError> {
+    let what = i18n::t("what.encryptionSettings");
+    let salt = unhex(&settings.salt).ok_or_else(|| AppError::malformed(what, "salt"))?;
+    let key = derive_key(passphrase, &salt)?;
+    if !verify_key(settings, &key) {
+        return Err(AppError::new(ErrorKind::Auth, "crypto.wrongPassphrase"));
+    }
+    Ok(key)
+}
+
+// The unlocked key of an encrypted project, falling back to one remembered in the keychain.
+fn key(root: &Path, settings: &EncryptionSettings) -> Result<Key, AppError> {
+    if let Some(key) = keys().lock().unwrap_or_else(|e| e.into_inner()).get(root) {
+        return Ok(*key);
+    }
+    let remembered = settings
+        .remember_key
+        .then(|| secure::get_project_key(&settings.key_id).ok().flatten())
+        .flatten()
+        .and_then(|k| unhex(&k))
+        .and_then(|k| Key::try_from(k).ok())
+        .filter(|k| verify_key(settings, k));
+    let key = remembered.ok_or_else(|| AppError::new(ErrorKind::Locked, "crypto.locked"))?;
+    keys()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.to_path_buf(), key);
+    Ok(key)
+}
+
+// What to write to disk for `plain`: ciphertext for protected files of encrypted projects.
+pub(crate) fn encode<'a>(path: &Path, plain: &'a [u8]) -> Result<Cow<'a, [u8]>, AppError> {
+    let Some(root) = protected_root(path) else {
+        return Ok(Cow::Borrowed(plain));
+    };
+    let Some(settings) = load_settings(root)? else {
+        return Ok(Cow::Borrowed(plain));
+    };
+    seal(&key(root, &settings)?, &aad(path), plain).map(Cow::Owned)
+}
+
+// The inverse of encode for bytes read from `path`; plain files pass through unchanged.
+pub(crate) fn decode(path: &Path, raw: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    if !raw.starts_with(MAGIC) {
+        return Ok(raw);
+    }
+    let root = protected_root(path).ok_or_else(|| AppError::new(ErrorKind::Invalid, "crypto.notProtected"))?;
+    let settings = load_settings(root)?.ok_or_else(|| AppError::new(ErrorKind::Invalid, "crypto.noSettings"))?;
+    open(&key(root, &settings)?, &aad(path), &raw)
+        .ok_or_else(|| AppError::new(ErrorKind::Invalid, "crypto.decryptFailed").with_param("path", path.display()))
+}
+
+// Whether `data`, found at `path` outside the project (in a backup), is a protected file in
+// plain text.
+fn exposes(path: &Path, data: &[u8]) -> bool {
+    protected_root(path).is_some() && !data.is_empty() && !data.starts_with(MAGIC)
+}
+
+// For tools that look at raw files (integrity checks) and would misread ciphertext.
+pub(crate) fn ensure_unlocked(root: &Path) -> Result<(), AppError> {
+    match load_settings(root)? {
+        Some(settings) => key(root, &settings).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+fn protected_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = vec![storage::summaries_file(root)];
+    for dir in [
+        storage::chapters_dir(root),
+        storage::chat_sessions_dir(root),
+        storage::llm_log_dir(root),
+    ] {
+        files.extend(fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()));
+    }
+    files.retain(|f| f.is_file() && protected_root(f).is_some() && f.extension().is_some_and(|e| e != "tmp"));
+    files.sort();
+    files
+}
+
+// Rewrites every protected file through encode/decode, skipping files already converted.
+fn convert_files(root: &Path, encrypt: bool) -> Result<(), AppError> {
+    for file in protected_files(root) {
+        let raw = fs::read(&file).map_err(|e| AppError::read(&file.display().to_string(), e))?;
+        if raw.starts_with(MAGIC) == encrypt {
+            continue;
+        }
+        let plain = decode(&file, raw)?;
+        let out = if encrypt {
+            encode(&file, &plain)?
+        } else {
+            Cow::Borrowed(plain.as_slice())
+        };
+        durable::write(&file, &out).map_err(|e| {
+            AppError::new(ErrorKind::Io, "file.write")
+                .with_param("file", file.display())
+                .caused_by(e)
+        })?;
+        lock::remember(&file, &out);
+    }
+    Ok(())
+}
+
+pub fn status(project_dir: String) -> Result<EncryptionStatus, AppError> {
+    let root = PathBuf::from(project_dir);
+    let Some(settings) = load_settings(&root)? else {
+        return Ok(EncryptionStatus::default());
+    };
+    Ok(EncryptionStatus {
+        enabled: true,
+        unlocked: key(&root, &settings).is_ok(),
+        remember_key: settings.remember_key,
+    })
+}
+
+fn remember_in_keychain(
+    root: &Path,
+    settings: &mut EncryptionSettings,
+    key: &Key,
+    remember: bool,
+) -> Result<(), AppError> {
+    if remember {
+        secure::set_project_key(&settings.key_id, &hex(key))?;
+    } else if settings.remember_key {
+        secure::delete_project_key(&settings.key_id)?;
+    }
+    if settings.remember_key != remember {
+        settings.remember_key = remember;
+        storage::atomic_write_json(&settings_file(root), settings)?;
+    }
+    Ok(())
+}
+
+pub fn unlock(project_dir: String, passphrase: String, remember: bool) -> Result<(), AppError> {
+    let root = PathBuf::from(project_dir);
+    let mut settings = load_settings(&root)?.ok_or_else(|| AppError::new(ErrorKind::Invalid, "crypto.notEncrypted"))?;
+    let key = key_for_passphrase(&settings, &passphrase)?;
+    keys()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.clone(), key);
+    remember_in_keychain(&root, &mut settings, &key, remember)
+}
+
+// Forgets the key in memory and in the keychain; the next access asks for the passphrase.
+pub fn lock_project(project_dir: String) -> Result<(), AppError> {
+    let root = PathBuf::from(project_dir);
+    keys().lock().unwrap_or_else(|e| e.into_inner()).remove(&root);
+    if let Some(mut settings) = load_settings(&root)? {
+        remember_in_keychain(&root, &mut settings, &Key::default(), false)?;
+    }
+    Ok(())
+}
+
+// Turns on encryption for an existing project. Running it again after an interruption
+// finishes the files that were not converted yet. Backups still holding plain text are
+// replaced; git history can't be rewritten here, so the report says when it is exposed.
+pub fn encrypt_project(project_dir: String, passphrase: String, remember: bool) -> Result<EncryptionReport, AppError> {
+    let root = PathBuf::from(project_dir);
+    let _write = lock::write_guard(&root);
+    let (mut settings, key) = match load_settings(&root)? {
+        Some(settings) => {
+            let key = key_for_passphrase(&settings, &passphrase)?;
+            (settings, key)
+        }
+        None => {
+            if storage::project_backend(&root) == StorageBackend::Sqlite {
+                return Err(AppError::new(ErrorKind::Invalid, "crypto.sqliteUnsupported"));
+            }
+            if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+                return Err(AppError::new(ErrorKind::Invalid, "crypto.passphraseTooShort")
+                    .with_param("min", MIN_PASSPHRASE_CHARS));
+            }
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = derive_key(&passphrase, &salt)?;
+            let settings = EncryptionSettings {
+                key_id: uuid::Uuid::new_v4().to_string(),
+                salt: hex(&salt),
+                check: hex(&seal(&key, b"check", CHECK_PLAINTEXT)?),
+                remember_key: false,
+            };
+            storage::atomic_write_json(&settings_file(&root), &settings)?;
+            (settings, key)
+        }
+    };
+    keys()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.clone(), key);
+    convert_files(&root, true)?;
+    let removed_backups = backup::replace_backups_exposing(&root, exposes)?;
+    remember_in_keychain(&root, &mut settings, &key, remember)?;
+    Ok(EncryptionReport {
+        removed_backups,
+        history_exposed: root.join(".git").exists(),
+    })
+}
+
+// Writes everything back as plain text and removes the encryption settings last, so an
+// interrupted run can simply be repeated.
+pub fn decrypt_project(project_dir: String, passphrase: String) -> Result<(), AppError> {
+    let root = PathBuf::from(project_dir);
+    let _write = lock::write_guard(&root);
+    let mut settings = load_settings(&root)?.ok_or_else(|| AppError::new(ErrorKind::Invalid, "crypto.notEncrypted"))?;
+    let key = key_for_passphrase(&settings, &passphrase)?;
+    keys()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.clone(), key);
+    convert_files(&root, false)?;
+    remember_in_keychain(&root, &mut settings, &key, false)?;
+    let file = settings_file(&root);
+    fs::remove_file(&file).map_err(|e| AppError::io("io.remove", &file, e))?;
+    lock::remember_removed(&file);
+    keys().lock().unwrap_or_else(|e| e.into_inner()).remove(&root);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_protected_files_transparently() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let item = storage::create_chapter(root.clone(), "第一章".to_string()).unwrap();
+        let chapter = Chapter {
+            id: item.id,
+            title: item.title.clone(),
+            content: "秘密的开头".to_string(),
+            summary: String::new(),
+        };
+        storage::save_chapter(root.clone(), &chapter).unwrap();
+        backup::backup_project(root.clone(), None).unwrap();
+
+        let short = encrypt_project(root.clone(), "short".to_string(), false).unwrap_err();
+        assert_eq!(short.key, "crypto.passphraseTooShort");
+        let report = encrypt_project(root.clone(), "correct horse".to_string(), false).unwrap();
+        assert_eq!(report.removed_backups, 1);
+        assert!(!report.history_exposed);
+        // the plain-text backup was swapped for an encrypted one
+        let backups = backup::list_backups(root.clone()).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].file_name.starts_with("encrypted_"));
+        let txt = storage::chapter_txt(dir.path(), item.id);
+        assert!(fs::read(&txt).unwrap().starts_with(MAGIC));
+        assert_eq!(
+            storage::load_chapter(root.clone(), item.id).unwrap().content,
+            "秘密的开头"
+        );
+        // the index stays readable without the key
+        assert!(!fs::read(storage::chapters_index_file(dir.path()))
+            .unwrap()
+            .starts_with(MAGIC));
+
+        lock_project(root.clone()).unwrap();
+        assert!(!status(root.clone()).unwrap().unlocked);
+        let locked = storage::load_chapter(root.clone(), item.id).unwrap_err();
+        assert_eq!(locked.kind, ErrorKind::Locked);
+        assert_eq!(locked.key, "crypto.locked");
+        let wrong = unlock(root.clone(), "wrong".to_string(), false).unwrap_err();
+        assert_eq!(wrong.kind, ErrorKind::Auth);
+        assert_eq!(wrong.key, "crypto.wrongPassphrase");
+        unlock(root.clone(), "correct horse".to_string(), false).unwrap();
+
+        decrypt_project(root.clone(), "correct horse".to_string()).unwrap();
+        assert_eq!(fs::read_to_string(&txt).unwrap(), "秘密的开头");
+        assert!(!status(root).unwrap().enabled);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, integrity, logging, prompt, storage};
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// how much of each recent log goes into a bundle; the end is the interesting part
+const LOG_TAIL_BYTES: u64 = 2 * 1024 * 1024;
+const LOG_FILES: usize = 3;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleInfo {
+    app_version: String,
+    os: String,
+    arch: String,
+    created_at: String,
+    project: Option<ProjectSummary>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectSummary {
+    name: String,
+    schema_version: Option<u32>,
+    backend: crate::types::StorageBackend,
+    chapters: Option<usize>,
+}
+
+fn zip_err(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Io, "diagnostics.write").caused_by(e)
+}
+
+fn tail(path: &Path, max: u64) -> Result<Vec<u8>, AppError> {
+    let mut file = fs::File::open(path).map_err(|e| AppError::io("diagnostics.readLog", path, e))?;
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if len > max {
+        file.seek(SeekFrom::Start(len - max))
+            .map_err(|e| AppError::io("diagnostics.readLog", path, e))?;
+    }
+    let mut out = vec![];
+    file.read_to_end(&mut out)
+        .map_err(|e| AppError::io("diagnostics.readLog", path, e))?;
+    Ok(out)
+}
+
+fn project_summary(root: &Path) -> ProjectSummary {
+    ProjectSummary {
+        name: root
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        schema_version: storage::load_manifest(root.to_string_lossy().to_string())
+            .ok()
+            .flatten()
+            .map(|m| m.schema_version),
+        backend: storage::project_backend(root),
+        chapters: crate::store::open(&root.to_string_lossy())
+            .and_then(|s| s.list_chapters())
+            .ok()
+            .map(|c| c.len()),
+    }
+}
+
+// Packs what a bug report needs into a zip: app and OS versions, the ends of the latest log
+// files and, with a project open, its integrity report. Chapter text is never included and
+// the logs were redacted when written.
+pub fn write_bundle(log_dir: &Path, project_dir: Option<String>, output: &Path) -> Result<(), AppError> {
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+    }
+    let tmp = durable::temp_path(output);
+    let file = fs::File::create(&tmp).map_err(|e| AppError::new(ErrorKind::Io, "diagnostics.create").caused_by(e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, data: &[u8]| -> Result<(), AppError> {
+        zip.start_file(name, options).map_err(zip_err)?;
+        zip.write_all(data).map_err(zip_err)
+    };
+
+    let root = project_dir.map(PathBuf::from);
+    let info = BundleInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: prompt::now_iso(),
+        project: root.as_deref().map(project_summary),
+    };
+    add(
+        "info.json",
+        &serde_json::to_vec_pretty(&info).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?,
+    )?;
+
+    if let Some(root) = &root {
+        // a locked or broken project still gets a bundle; the failure is the report
+        let health = match integrity::verify_project(root.to_string_lossy().to_string()) {
+            Ok(report) => {
+                serde_json::to_value(report).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?
+            }
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        add(
+            "health.json",
+            &serde_json::to_vec_pretty(&health)
+                .map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?,
+        )?;
+    }
+
+    for path in logging::log_files(log_dir).into_iter().take(LOG_FILES) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        add(&format!("logs/{name}"), &tail(&path, LOG_TAIL_BYTES)?)?;
+    }
+
+    zip.finish().map_err(zip_err)?;
+    durable::persist(&tmp, output).map_err(|e| AppError::new(ErrorKind::Io, "diagnostics.save").caused_by(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::ZipArchive;
+
+    #[test]
+    fn bundles_info_health_and_recent_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("novel");
+        let root = project.to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let logs = dir.path().join("logs");
+        fs::create_dir_all(&logs).unwrap();
+        for day in 1..=5 {
+            fs::write(
+                logs.join(format!("creatorai.2024-01-0{day}.log")),
+                format!("day {day}\n"),
+            )
+            .unwrap();
+        }
+        fs::write(logs.join("notes.txt"), "not a log").unwrap();
+
+        let output = dir.path().join("out").join("diagnostics.zip");
+        write_bundle(&logs, Some(root), &output).unwrap();
+
+        let mut zip = ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+        let mut names = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "health.json",
+                "info.json",
+                "logs/creatorai.2024-01-03.log",
+                "logs/creatorai.2024-01-04.log",
+                "logs/creatorai.2024-01-05.log",
+            ]
+        );
+        let mut info = String::new();
+        zip.by_name("info.json").unwrap().read_to_string(&mut info).unwrap();
+        let info: serde_json::Value = serde_json::from_str(&info).unwrap();
+        assert_eq!(info["project"]["name"], "novel");
+        assert_eq!(info["project"]["chapters"], 1);
+        let mut health = String::new();
+        zip.by_name("health.json").unwrap().read_to_string(&mut health).unwrap();
+        assert!(health.contains("\"issues\": []"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Temp files are named "<file>.<pid>.<n>.tmp" next to their target, so two writers never
+// share one and every filter that skips "*.tmp" keeps working.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{name}.{}.{n}.tmp", std::process::id()))
+}
+
+// The file a temp was meant to replace and the pid of its writer; also understands the older
+// "<file>.tmp" naming, which carries no pid.
+fn temp_target(tmp: &Path) -> Option<(PathBuf, Option<u32>)> {
+    let name = tmp.file_name()?.to_str()?.strip_suffix(".tmp")?;
+    let mut parts = name.rsplitn(3, '.');
+    let (n, pid, base) = (parts.next()?, parts.next(), parts.next());
+    let numeric = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    match (pid, base) {
+        (Some(pid), Some(base)) if numeric(n) && numeric(pid) => Some((tmp.with_file_name(base), pid.parse().ok())),
+        _ => Some((tmp.with_file_name(name), None)),
+    }
+}
+
+// Whether the process that made a temp may still be writing it. Linux can be asked through
+// /proc; elsewhere a temp touched in the last few minutes is taken to be in flight.
+fn writer_alive(pid: u32, tmp: &Path) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+    fs::metadata(tmp)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age < Duration::from_secs(300))
+}
+
+pub(crate) fn sync_dir(dir: &Path) -> io::Result<()> {
+    // directories can't be opened for syncing on Windows; NTFS journals the rename itself
+    if cfg!(unix) {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// Antivirus scanners and indexers briefly hold files open on Windows, which makes replacing
+// them fail with "access denied"; those clear up after a moment.
+fn replace(tmp: &Path, path: &Path) -> io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match fs::rename(tmp, path) {
+            Err(e) if cfg!(windows) && e.kind() == io::ErrorKind::PermissionDenied && attempt < 10 => {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(20 * attempt));
+            }
+            result => return result,
+        }
+    }
+}
+
+// Moves a fully written temp file over `path`: the data is flushed to disk before the rename
+// and the directory entry after it, so a crash leaves either the old or the new file.
+pub(crate) fn persist(tmp: &Path, path: &Path) -> io::Result<()> {
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .open(tmp)
+        .and_then(|f| f.sync_all())
+        .and_then(|_| replace(tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(tmp);
+    }
+    result?;
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = temp_path(path);
+    let written = fs::File::create(&tmp).and_then(|mut f| f.write_all(contents));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    persist(&tmp, path)
+}
+
+// Cleans up temp files left by a crash in `dir` (not its subfolders) for the targets `owned`
+// accepts. A temp is only promoted when its target is missing and its content is usable;
+// otherwise it was an interrupted overwrite of a file that is still intact, and gets deleted.
+// Temps of a writer that may still be running are left alone. Returns the restored files.
+pub(crate) fn recover(dir: &Path, owned: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut restored = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return restored;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some((target, pid)) = temp_target(&path) else {
+            continue;
+        };
+        if path.is_dir() || !owned(&target) || pid.is_some_and(|pid| writer_alive(pid, &path)) {
+            continue;
+        }
+        // a possibly cut-off chapter text still beats an empty one; archives are rebuilt instead
+        let complete = || match target.extension().and_then(|e| e.to_str()) {
+            Some("json") => fs::read(&path).is_ok_and(|raw| serde_json::from_slice::<serde_json::Value>(&raw).is_ok()),
+            Some("txt") => true,
+            _ => false,
+        };
+        if !target.exists() && complete() && persist(&path, &target).is_ok() {
+            restored.push(target);
+        } else {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_and_recovers_leftover_temps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.json");
+        write(&path, b"[1]").unwrap();
+        write(&path, b"[2]").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[2]");
+        assert_ne!(temp_path(&path), temp_path(&path));
+        assert_eq!(
+            temp_target(&temp_path(&path)).unwrap(),
+            (path.clone(), Some(std::process::id()))
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // crash while overwriting: the old file wins
+        fs::write(dir.path().join("index.json.999999999.0.tmp"), "[3").unwrap();
+        // crash while creating: a complete temp is kept, a truncated one dropped
+        fs::write(dir.path().join("sessions.json.tmp"), "[]").unwrap();
+        fs::write(dir.path().join("summaries.json.999999999.1.tmp"), "[{").unwrap();
+        // a write still in progress, and a file that isn't ours
+        let in_flight = temp_path(&dir.path().join("config.json"));
+        fs::write(&in_flight, "{").unwrap();
+        fs::write(dir.path().join("notes.json.999999999.0.tmp"), "{}").unwrap();
+
+        let restored = recover(dir.path(), |target| !target.ends_with("notes.json"));
+        assert_eq!(restored, vec![dir.path().join("sessions.json")]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[2]");
+        assert!(!dir.path().join("summaries.json").exists());
+        assert!(!dir.path().join("summaries.json.999999999.1.tmp").exists());
+        assert!(in_flight.exists() && dir.path().join("notes.json.999999999.0.tmp").exists());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::i18n;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    // the project or a file in it is missing
+    NotFound,
+    // the file changed on disk since it was loaded
+    Conflict,
+    // the project is encrypted and not unlocked
+    Locked,
+    // reading or writing a project file, or the system keychain, failed
+    Io,
+    // settings are incomplete, e.g. no endpoint or API key
+    Config,
+    // the provider rejected the API key, or the project passphrase is wrong
+    Auth,
+    // the account is out of credit
+    Quota,
+    RateLimit,
+    // the prompt doesn't fit the model's context window
+    ContextLength,
+    // the provider failed (5xx) or answered with something unusable
+    Server,
+    // the request never got an answer
+    Network,
+    // a usage budget set in the app is used up
+    Budget,
+    // input that was rejected, e.g. a prompt template that doesn't parse
+    Invalid,
+    Other,
+}
+
+// What every command returns on failure. `key` and `params` let the frontend render its own
+// text; `message` is the same text already rendered in the interface language. `sources` lists
+// the underlying causes, outermost first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub key: String,
+    pub params: BTreeMap<String, String>,
+    pub message: String,
+    pub sources: Vec<String>,
+}
+
+impl AppError {
+    // An error whose message is `key` from the catalog in the interface language.
+    pub fn new(kind: ErrorKind, key: &'static str) -> Self {
+        AppError {
+            kind,
+            key: key.to_string(),
+            params: BTreeMap::new(),
+            message: i18n::t(key).to_string(),
+            sources: vec![],
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: impl ToString) -> Self {
+        let value = value.to_string();
+        self.message = self.message.replace(&format!("{{{name}}}"), &value);
+        self.params.insert(name.to_string(), value);
+        self
+    }
+
+    // A failed file operation: `key` says what was attempted, the path and OS error follow.
+    pub fn io(key: &'static str, path: &Path, source: impl fmt::Display) -> Self {
+        AppError::new(ErrorKind::Io, key).caused_by(format!("{}: {source}", path.display()))
+    }
+
+    // `what` (a `what.*` label) could not be read.
+    pub fn read(what: &str, source: impl fmt::Display) -> Self {
+        AppError::new(ErrorKind::Io, "file.read")
+            .with_param("file", what)
+            .caused_by(source)
+    }
+
+    // `what` was read but doesn't parse.
+    pub fn malformed(what: &str, source: impl fmt::Display) -> Self {
+        AppError::new(ErrorKind::Invalid, "file.malformed")
+            .with_param("file", what)
+            .caused_by(source)
+    }
+
+    pub fn caused_by(mut self, source: impl fmt::Display) -> Self {
+        let source = source.to_string();
+        if !source.trim().is_empty() {
+            self.sources.push(source);
+        }
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        for source in &self.sources {
+            write!(f, ": {source}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_for_the_frontend() {
+        let err = AppError::new(ErrorKind::RateLimit, "llm.rateLimited")
+            .with_param("status", 429)
+            .caused_by("slow down");
+        let v = serde_json::to_value(&err).unwrap();
+        assert_eq!(v["kind"], "rateLimit");
+        assert_eq!(v["key"], "llm.rateLimited");
+        assert_eq!(v["params"]["status"], "429");
+        assert_eq!(v["sources"][0], "slow down");
+        assert_eq!(err.to_string(), format!("{}: slow down", err.message));
+
+        let typed = AppError::new(ErrorKind::Conflict, "storage.conflict").with_param("path", "a.txt");
+        assert!(typed.message.contains("a.txt") && !typed.message.contains("{path}"));
+    }
+}
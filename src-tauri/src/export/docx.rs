@@ -0,0 +1,217 @@
+use super::{escape_xml, load_manuscript, now_utc_seconds, zip_err, Block, Manuscript};
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, types::*};
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+<Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+<Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+<Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+</Types>
+"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+</Relationships>
+"#;
+
+// Body text uses a two-character first-line indent, the usual convention for Chinese manuscripts.
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults>
+<w:rPrDefault><w:rPr><w:rFonts w:ascii="Times New Roman" w:hAnsi="Times New Roman" w:eastAsia="SimSun" w:cs="Times New Roman"/><w:sz w:val="24"/><w:szCs w:val="24"/><w:lang w:val="en-US" w:eastAsia="zh-CN"/></w:rPr></w:rPrDefault>
+<w:pPrDefault><w:pPr><w:spacing w:after="0" w:line="360" w:lineRule="auto"/></w:pPr></w:pPrDefault>
+</w:docDefaults>
+<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/><w:pPr><w:ind w:firstLineChars="200" w:firstLine="480"/><w:jc w:val="both"/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:next w:val="Subtitle"/><w:qFormat/><w:pPr><w:spacing w:before="2400" w:after="600"/><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/></w:pPr><w:rPr><w:rFonts w:eastAsia="SimHei"/><w:b/><w:sz w:val="52"/><w:szCs w:val="52"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Subtitle"><w:name w:val="Subtitle"/><w:basedOn w:val="Normal"/><w:qFormat/><w:pPr><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/></w:pPr><w:rPr><w:sz w:val="28"/><w:szCs w:val="28"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="480" w:after="360"/><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:rFonts w:eastAsia="SimHei"/><w:b/><w:sz w:val="36"/><w:szCs w:val="36"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="360" w:after="240"/><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:rFonts w:eastAsia="SimHei"/><w:b/><w:sz w:val="30"/><w:szCs w:val="30"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:customStyle="1" w:styleId="SceneBreak"><w:name w:val="Scene Break"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:before="240" w:after="240"/><w:ind w:firstLineChars="0" w:firstLine="0"/><w:jc w:val="center"/></w:pPr></w:style>
+</w:styles>
+"#;
+
+fn paragraph(style: Option<&str>, page_break_before: bool, text: &str) -> String {
+    let mut ppr = String::new();
+    if let Some(style) = style {
+        ppr.push_str(&format!("<w:pStyle w:val=\"{style}\"/>"));
+    }
+    if page_break_before {
+        ppr.push_str("<w:pageBreakBefore/>");
+    }
+    let ppr = if ppr.is_empty() {
+        ppr
+    } else {
+        format!("<w:pPr>{ppr}</w:pPr>")
+    };
+    format!(
+        "<w:p>{ppr}<w:r><w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>\n",
+        escape_xml(text)
+    )
+}
+
+fn document_xml(m: &Manuscript) -> String {
+    let mut body = String::new();
+    body.push_str(&paragraph(Some("Title"), false, &m.title));
+    if !m.author.is_empty() {
+        body.push_str(&paragraph(Some("Subtitle"), false, &m.author));
+    }
+
+    // With volumes: volume = Heading 1, chapter = Heading 2. Otherwise chapters are Heading 1.
+    let chapter_style = if m.has_volumes() { "Heading2" } else { "Heading1" };
+    for volume in &m.volumes {
+        let mut after_volume_heading = false;
+        if let Some(title) = &volume.title {
+            body.push_str(&paragraph(Some("Heading1"), true, title));
+            after_volume_heading = true;
+        }
+        for ch in &volume.chapters {
+            body.push_str(&paragraph(Some(chapter_style), !after_volume_heading, &ch.title));
+            after_volume_heading = false;
+            for block in &ch.blocks {
+                match block {
+                    Block::Paragraph(text) => body.push_str(&paragraph(None, false, text)),
+                    Block::SceneBreak => body.push_str(&paragraph(Some("SceneBreak"), false, "* * *")),
+                }
+            }
+        }
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:body>
+{body}<w:sectPr><w:pgSz w:w="11906" w:h="16838"/><w:pgMar w:top="1440" w:right="1800" w:bottom="1440" w:left="1800" w:header="851" w:footer="992" w:gutter="0"/></w:sectPr>
+</w:body>
+</w:document>
+"#
+    )
+}
+
+fn core_xml(m: &Manuscript) -> String {
+    let created = now_utc_seconds();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+<dc:title>{}</dc:title>
+<dc:creator>{}</dc:creator>
+<dc:language>{}</dc:language>
+<dcterms:created xsi:type="dcterms:W3CDTF">{created}</dcterms:created>
+</cp:coreProperties>
+"#,
+        escape_xml(&m.title),
+        escape_xml(&m.author),
+        m.language(),
+    )
+}
+
+pub fn export_docx(project_dir: String, options: &DocxExportOptions) -> Result<(), AppError> {
+    let m = load_manuscript(
+        &project_dir,
+        options.title.as_deref(),
+        options.author.as_deref(),
+        options.range.as_ref(),
+    )?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", ROOT_RELS.to_string()),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/styles.xml", STYLES.to_string()),
+        ("word/document.xml", document_xml(&m)),
+        ("docProps/core.xml", core_xml(&m)),
+    ];
+    for (name, data) in parts {
+        zip.start_file(name, deflated).map_err(zip_err)?;
+        zip.write_all(data.as_bytes()).map_err(zip_err)?;
+    }
+
+    let data = zip.finish().map_err(zip_err)?.into_inner();
+    durable::write(Path::new(&options.output_path), &data).map_err(|e| {
+        AppError::new(ErrorKind::Io, "export.write")
+            .with_param("format", "DOCX")
+            .caused_by(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ManuscriptChapter, Volume};
+
+    fn chapter(id: u32, title: &str, blocks: Vec<Block>) -> ManuscriptChapter {
+        ManuscriptChapter {
+            id,
+            title: title.to_string(),
+            blocks,
+        }
+    }
+
+    #[test]
+    fn document_escapes_text_and_breaks_pages_at_headings() {
+        let m = Manuscript {
+            title: "雾 & <港>".to_string(),
+            author: "\"佚名\"".to_string(),
+            locale: Locale::En,
+            volumes: vec![
+                Volume {
+                    title: Some("第一卷".to_string()),
+                    chapters: vec![
+                        chapter(
+                            1,
+                            "第一章",
+                            vec![Block::Paragraph("a < b".to_string()), Block::SceneBreak],
+                        ),
+                        chapter(2, "第二章", vec![]),
+                    ],
+                },
+                Volume {
+                    title: Some("第二卷".to_string()),
+                    chapters: vec![chapter(3, "第三章", vec![])],
+                },
+            ],
+        };
+        let doc = document_xml(&m);
+        assert!(doc.contains(">雾 &amp; &lt;港&gt;</w:t>"));
+        assert!(doc.contains(">&quot;佚名&quot;</w:t>"));
+        assert!(doc.contains(">a &lt; b</w:t>"));
+        assert!(doc.contains("<w:pStyle w:val=\"SceneBreak\"/>"));
+
+        // a volume starts a page; its first chapter stays on it, later chapters start their own
+        let headings = doc
+            .lines()
+            .filter(|l| l.contains("Heading"))
+            .map(|l| (l.contains("Heading1"), l.contains("<w:pageBreakBefore/>")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            vec![
+                (true, true),
+                (false, false),
+                (false, true),
+                (true, true),
+                (false, false)
+            ]
+        );
+
+        let core = core_xml(&m);
+        assert!(core.contains("<dc:title>雾 &amp; &lt;港&gt;</dc:title>"));
+        assert!(core.contains("<dc:language>en</dc:language>"));
+    }
+}
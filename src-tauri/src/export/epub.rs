@@ -0,0 +1,408 @@
+use super::{escape_xml, load_manuscript, now_utc_seconds, zip_err, Block, Manuscript};
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, i18n, types::*};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const EPUB_CSS: &str = r#"body { margin: 0 5%; line-height: 1.8; font-family: serif; }
+h1 { text-align: center; font-size: 1.4em; margin: 2em 0 1.5em; }
+p { text-indent: 2em; margin: 0 0 0.4em; text-align: justify; }
+.title-page { text-align: center; margin-top: 30%; }
+.title-page h1 { font-size: 2em; margin-bottom: 1em; }
+.title-page p { text-indent: 0; }
+.cover { text-align: center; margin: 0; padding: 0; }
+.cover img { max-width: 100%; max-height: 100%; }
+.volume { text-align: center; margin-top: 30%; }
+hr.scene-break { border: none; margin: 1.2em 0; text-align: center; }
+hr.scene-break::after { content: "* * *"; }
+"#;
+
+struct EpubPage {
+    file_name: String,
+    title: String,
+    body: String,
+}
+
+// Volume pages are listed at the top level of the TOC with their chapters nested below.
+struct TocEntry {
+    page: usize,
+    children: Vec<usize>,
+}
+
+fn cover_media_type(path: &Path) -> Result<&'static str, AppError> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" => Ok("image/jpeg"),
+        "png" => Ok("image/png"),
+        "gif" => Ok("image/gif"),
+        "webp" => Ok("image/webp"),
+        _ => Err(AppError::new(ErrorKind::Invalid, "export.coverFormat").with_param("ext", ext)),
+    }
+}
+
+fn xhtml_page(lang: &str, title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+<meta charset="utf-8" />
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css" />
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        lang = escape_xml(lang),
+        title = escape_xml(title),
+    )
+}
+
+fn chapter_body(title: &str, blocks: &[Block]) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape_xml(title));
+    for block in blocks {
+        match block {
+            Block::Paragraph(text) => body.push_str(&format!("<p>{}</p>\n", escape_xml(text))),
+            Block::SceneBreak => body.push_str("<hr class=\"scene-break\" />\n"),
+        }
+    }
+    body
+}
+
+fn volume_body(title: &str) -> String {
+    format!("<div class=\"volume\">\n<h1>{}</h1>\n</div>", escape_xml(title))
+}
+
+fn title_xhtml(lang: &str, title: &str, author: &str) -> String {
+    let mut body = format!("<div class=\"title-page\">\n<h1>{}</h1>\n", escape_xml(title));
+    if !author.is_empty() {
+        body.push_str(&format!("<p>{}</p>\n", escape_xml(author)));
+    }
+    body.push_str("</div>");
+    xhtml_page(lang, title, &body)
+}
+
+fn cover_xhtml(lang: &str, title: &str, image_href: &str) -> String {
+    let body = format!(
+        "<div class=\"cover\"><img src=\"{}\" alt=\"{}\" /></div>",
+        escape_xml(image_href),
+        escape_xml(title)
+    );
+    xhtml_page(lang, title, &body)
+}
+
+fn nav_xhtml(lang: &str, heading: &str, pages: &[EpubPage], toc: &[TocEntry]) -> String {
+    let link = |i: usize| format!("<a href=\"{}\">{}</a>", pages[i].file_name, escape_xml(&pages[i].title));
+    let mut body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n",
+        escape_xml(heading)
+    );
+    for entry in toc {
+        if entry.children.is_empty() {
+            body.push_str(&format!("<li>{}</li>\n", link(entry.page)));
+            continue;
+        }
+        body.push_str(&format!("<li>{}\n<ol>\n", link(entry.page)));
+        for &child in &entry.children {
+            body.push_str(&format!("<li>{}</li>\n", link(child)));
+        }
+        body.push_str("</ol>\n</li>\n");
+    }
+    body.push_str("</ol>\n</nav>");
+    xhtml_page(lang, heading, &body)
+}
+
+// EPUB 2 NCX for older readers that ignore nav.xhtml
+fn toc_ncx(uid: &str, title: &str, pages: &[EpubPage], toc: &[TocEntry]) -> String {
+    let nav_point = |i: usize| {
+        format!(
+            "<navPoint id=\"nav{n}\" playOrder=\"{n}\"><navLabel><text>{}</text></navLabel><content src=\"{}\" />",
+            escape_xml(&pages[i].title),
+            pages[i].file_name,
+            n = i + 1
+        )
+    };
+    let mut points = String::new();
+    for entry in toc {
+        points.push_str(&nav_point(entry.page));
+        points.push('\n');
+        for &child in &entry.children {
+            points.push_str(&nav_point(child));
+            points.push_str("</navPoint>\n");
+        }
+        points.push_str("</navPoint>\n");
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+<head><meta name="dtb:uid" content="{uid}" /></head>
+<docTitle><text>{title}</text></docTitle>
+<navMap>
+{points}</navMap>
+</ncx>
+"#,
+        uid = escape_xml(uid),
+        title = escape_xml(title),
+    )
+}
+
+struct OpfInput<'a> {
+    uid: &'a str,
+    title: &'a str,
+    author: &'a str,
+    lang: &'a str,
+    modified: &'a str,
+    cover: Option<(&'a str, &'a str)>,
+    pages: &'a [EpubPage],
+}
+
+fn content_opf(input: &OpfInput) -> String {
+    let mut manifest = String::new();
+    let mut spine = String::new();
+
+    manifest
+        .push_str("<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\" />\n");
+    manifest.push_str("<item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\" />\n");
+    manifest.push_str("<item id=\"css\" href=\"style.css\" media-type=\"text/css\" />\n");
+
+    if let Some((href, media_type)) = input.cover {
+        manifest.push_str(&format!(
+            "<item id=\"cover-image\" href=\"{href}\" media-type=\"{media_type}\" properties=\"cover-image\" />\n"
+        ));
+        manifest.push_str("<item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\" />\n");
+        spine.push_str("<itemref idref=\"cover\" linear=\"no\" />\n");
+    }
+
+    manifest.push_str("<item id=\"title\" href=\"title.xhtml\" media-type=\"application/xhtml+xml\" />\n");
+    spine.push_str("<itemref idref=\"title\" />\n");
+    spine.push_str("<itemref idref=\"nav\" />\n");
+
+    for (i, page) in input.pages.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"p{}\" href=\"{}\" media-type=\"application/xhtml+xml\" />\n",
+            i + 1,
+            page.file_name
+        ));
+        spine.push_str(&format!("<itemref idref=\"p{}\" />\n", i + 1));
+    }
+
+    let creator = if input.author.is_empty() {
+        String::new()
+    } else {
+        format!("<dc:creator>{}</dc:creator>\n", escape_xml(input.author))
+    };
+    let cover_meta = if input.cover.is_some() {
+        "<meta name=\"cover\" content=\"cover-image\" />\n"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="bookid" xml:lang="{lang}">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="bookid">{uid}</dc:identifier>
+<dc:title>{title}</dc:title>
+{creator}<dc:language>{lang}</dc:language>
+<meta property="dcterms:modified">{modified}</meta>
+{cover_meta}</metadata>
+<manifest>
+{manifest}</manifest>
+<spine toc="ncx">
+{spine}</spine>
+</package>
+"#,
+        uid = escape_xml(input.uid),
+        title = escape_xml(input.title),
+        lang = escape_xml(input.lang),
+        modified = input.modified,
+    )
+}
+
+fn push_page(pages: &mut Vec<EpubPage>, title: &str, body: String) -> usize {
+    pages.push(EpubPage {
+        file_name: format!("text_{:03}.xhtml", pages.len() + 1),
+        title: title.to_string(),
+        body,
+    });
+    pages.len() - 1
+}
+
+fn build_pages(m: &Manuscript) -> (Vec<EpubPage>, Vec<TocEntry>) {
+    let mut pages = vec![];
+    let mut toc = vec![];
+    for volume in &m.volumes {
+        let volume_page = volume
+            .title
+            .as_deref()
+            .map(|title| push_page(&mut pages, title, volume_body(title)));
+        let chapters = volume
+            .chapters
+            .iter()
+            .map(|ch| push_page(&mut pages, &ch.title, chapter_body(&ch.title, &ch.blocks)))
+            .collect::<Vec<_>>();
+        match volume_page {
+            Some(page) => toc.push(TocEntry {
+                page,
+                children: chapters,
+            }),
+            None => toc.extend(chapters.into_iter().map(|page| TocEntry { page, children: vec![] })),
+        }
+    }
+    (pages, toc)
+}
+
+pub fn export_epub(project_dir: String, options: &EpubExportOptions) -> Result<(), AppError> {
+    let m = load_manuscript(
+        &project_dir,
+        options.title.as_deref(),
+        options.author.as_deref(),
+        options.range.as_ref(),
+    )?;
+    let (title, author) = (&m.title, &m.author);
+    let lang = options
+        .language
+        .clone()
+        .filter(|l| !l.trim().is_empty())
+        .unwrap_or_else(|| m.language().to_string());
+    let (pages, toc) = build_pages(&m);
+
+    let cover = match options.cover_image_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(path) => {
+            let path = Path::new(path);
+            let media_type = cover_media_type(path)?;
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let bytes = fs::read(path).map_err(|e| AppError::io("export.readCover", path, e))?;
+            Some((format!("cover.{ext}"), media_type, bytes))
+        }
+        None => None,
+    };
+
+    let uid = format!("urn:uuid:{}", Uuid::new_v4());
+    let modified = now_utc_seconds();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // mimetype must be the first entry and stored uncompressed
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("mimetype", stored).map_err(zip_err)?;
+    zip.write_all(b"application/epub+zip").map_err(zip_err)?;
+
+    let mut add = |name: &str, data: &[u8]| -> Result<(), AppError> {
+        zip.start_file(name, deflated).map_err(zip_err)?;
+        zip.write_all(data).map_err(zip_err)
+    };
+
+    add(
+        "META-INF/container.xml",
+        br#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml" />
+</rootfiles>
+</container>
+"#,
+    )?;
+
+    let opf = content_opf(&OpfInput {
+        uid: &uid,
+        title,
+        author,
+        lang: &lang,
+        modified: &modified,
+        cover: cover.as_ref().map(|(href, media_type, _)| (href.as_str(), *media_type)),
+        pages: &pages,
+    });
+    add("OEBPS/content.opf", opf.as_bytes())?;
+    add("OEBPS/style.css", EPUB_CSS.as_bytes())?;
+    let nav = nav_xhtml(&lang, i18n::text(m.locale, "export.toc"), &pages, &toc);
+    add("OEBPS/nav.xhtml", nav.as_bytes())?;
+    add("OEBPS/toc.ncx", toc_ncx(&uid, title, &pages, &toc).as_bytes())?;
+    add("OEBPS/title.xhtml", title_xhtml(&lang, title, author).as_bytes())?;
+
+    if let Some((href, _, bytes)) = &cover {
+        add(&format!("OEBPS/{href}"), bytes)?;
+        add("OEBPS/cover.xhtml", cover_xhtml(&lang, title, href).as_bytes())?;
+    }
+
+    for page in &pages {
+        add(
+            &format!("OEBPS/{}", page.file_name),
+            xhtml_page(&lang, &page.title, &page.body).as_bytes(),
+        )?;
+    }
+
+    let data = zip.finish().map_err(zip_err)?.into_inner();
+    durable::write(Path::new(&options.output_path), &data).map_err(|e| {
+        AppError::new(ErrorKind::Io, "export.write")
+            .with_param("format", "EPUB")
+            .caused_by(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage;
+    use std::io::Read;
+
+    #[test]
+    fn export_epub_writes_valid_container() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("novel").to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let mut ch = storage::load_chapter(root.clone(), 1).unwrap();
+        ch.content = "　　第一段。\n\n  第二段 <b> & 引号\"。".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+        storage::create_chapter(root.clone(), "第二章".to_string()).unwrap();
+
+        let out = dir.path().join("novel.epub");
+        let options = EpubExportOptions {
+            output_path: out.to_string_lossy().to_string(),
+            title: Some("测试小说".to_string()),
+            author: Some("佚名".to_string()),
+            language: None,
+            cover_image_path: None,
+            range: None,
+        };
+        export_epub(root, &options).unwrap();
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&out).unwrap()).unwrap();
+        {
+            let first = archive.by_index(0).unwrap();
+            assert_eq!(first.name(), "mimetype");
+            assert_eq!(first.compression(), CompressionMethod::Stored);
+        }
+        let mut opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains("<dc:language>zh-CN</dc:language>"));
+        assert!(opf.contains("<dc:title>测试小说</dc:title>"));
+
+        let mut xhtml = String::new();
+        archive
+            .by_name("OEBPS/text_001.xhtml")
+            .unwrap()
+            .read_to_string(&mut xhtml)
+            .unwrap();
+        assert!(xhtml.contains("<p>第一段。</p>"));
+        assert!(xhtml.contains("<p>第二段 &lt;b&gt; &amp; 引号&quot;。</p>"));
+        assert!(archive.by_name("OEBPS/text_002.xhtml").is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+use super::{load_manuscript, Block, Manuscript, ManuscriptChapter};
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, i18n, types::*};
+use std::fs;
+use std::path::Path;
+
+// Escape characters that would otherwise turn a prose line into Markdown syntax.
+fn escape_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    for c in line.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if let Some((n, _)) = out.split_once(". ") {
+        if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) {
+            out.insert(n.len(), '\\');
+            return out;
+        }
+    }
+    if out.starts_with('#') || out.starts_with("- ") || out.starts_with("+ ") || out.starts_with('=') {
+        out.insert(0, '\\');
+    }
+    out
+}
+
+fn render_chapter(out: &mut String, heading: &str, ch: &ManuscriptChapter) {
+    out.push_str(&format!("{heading} {}\n\n", escape_line(&ch.title)));
+    for block in &ch.blocks {
+        match block {
+            Block::Paragraph(text) => out.push_str(&escape_line(text)),
+            Block::SceneBreak => out.push_str("* * *"),
+        }
+        out.push_str("\n\n");
+    }
+}
+
+fn render_single(m: &Manuscript) -> String {
+    let mut out = format!("# {}\n\n", escape_line(&m.title));
+    if !m.author.is_empty() {
+        out.push_str(&i18n::text_with(
+            m.locale,
+            "export.author",
+            &[("author", &escape_line(&m.author))],
+        ));
+        out.push_str("\n\n");
+    }
+    let chapter_heading = if m.has_volumes() { "###" } else { "##" };
+    for volume in &m.volumes {
+        if let Some(title) = &volume.title {
+            out.push_str(&format!("## {}\n\n", escape_line(title)));
+        }
+        for ch in &volume.chapters {
+            render_chapter(&mut out, chapter_heading, ch);
+        }
+    }
+    out.trim_end().to_string() + "\n"
+}
+
+fn file_stem(title: &str) -> String {
+    let cleaned = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    cleaned.trim().trim_matches('.').chars().take(60).collect()
+}
+
+fn write_error(e: std::io::Error) -> AppError {
+    AppError::new(ErrorKind::Io, "export.write")
+        .with_param("format", "Markdown")
+        .caused_by(e)
+}
+
+pub fn export_markdown(project_dir: String, options: &MarkdownExportOptions) -> Result<(), AppError> {
+    let m = load_manuscript(
+        &project_dir,
+        options.title.as_deref(),
+        options.author.as_deref(),
+        options.range.as_ref(),
+    )?;
+
+    if !options.split_chapters {
+        return durable::write(Path::new(&options.output_path), render_single(&m).as_bytes()).map_err(write_error);
+    }
+
+    let dir = Path::new(&options.output_path);
+    fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+    for (i, ch) in m.chapters().enumerate() {
+        let mut out = String::new();
+        render_chapter(&mut out, "#", ch);
+        let name = format!("{:03}_{}.md", i + 1, file_stem(&ch.title));
+        durable::write(&dir.join(name), (out.trim_end().to_string() + "\n").as_bytes()).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage;
+
+    #[test]
+    fn split_export_writes_one_numbered_file_per_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("novel").to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let mut ch = storage::load_chapter(root.clone(), 1).unwrap();
+        ch.content = "　　# 不是标题\n\n***\n\n1. 也不是列表".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+        storage::create_chapter(root.clone(), "第二章：归/来".to_string()).unwrap();
+
+        let out = dir.path().join("md");
+        let options = MarkdownExportOptions {
+            output_path: out.to_string_lossy().to_string(),
+            title: None,
+            author: None,
+            split_chapters: true,
+            range: None,
+        };
+        export_markdown(root, &options).unwrap();
+
+        let mut names = fs::read_dir(&out)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![format!("001_{}.md", ch.title), "002_第二章：归_来.md".to_string()]
+        );
+
+        let first = fs::read_to_string(out.join(&names[0])).unwrap();
+        assert_eq!(
+            first,
+            format!("# {}\n\n\\# 不是标题\n\n* * *\n\n1\\. 也不是列表\n", ch.title)
+        );
+        let second = fs::read_to_string(out.join(&names[1])).unwrap();
+        assert_eq!(second, "# 第二章：归/来\n");
+    }
+}
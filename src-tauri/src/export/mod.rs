@@ -0,0 +1,241 @@
+mod docx;
+mod epub;
+mod markdown;
+
+pub use docx::export_docx;
+pub use epub::export_epub;
+pub use markdown::export_markdown;
+
+use crate::error::{AppError, ErrorKind};
+use crate::{i18n, prompt, storage, store, types::*};
+use std::path::Path;
+
+// Format-independent view of the project that every renderer consumes.
+pub struct Manuscript {
+    pub title: String,
+    pub author: String,
+    pub locale: Locale,
+    pub volumes: Vec<Volume>,
+}
+
+// Chapters without a volume in the index end up in a volume with `title: None`.
+pub struct Volume {
+    pub title: Option<String>,
+    pub chapters: Vec<ManuscriptChapter>,
+}
+
+pub struct ManuscriptChapter {
+    pub id: u32,
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Block {
+    Paragraph(String),
+    SceneBreak,
+}
+
+impl Manuscript {
+    pub fn chapters(&self) -> impl Iterator<Item = &ManuscriptChapter> {
+        self.volumes.iter().flat_map(|v| v.chapters.iter())
+    }
+
+    pub fn has_volumes(&self) -> bool {
+        self.volumes.iter().any(|v| v.title.is_some())
+    }
+
+    // BCP 47 tag for the language metadata of EPUB and DOCX
+    pub fn language(&self) -> &'static str {
+        match self.locale {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+}
+
+const SCENE_BREAK_CHARS: &str = "*＊-—–=~～·•◇◆○●☆★#＃";
+
+// A line made only of separator symbols ("***", "* * *", "◇◇◇", "§" ...) marks a scene break.
+fn is_scene_break(line: &str) -> bool {
+    if line == "§" {
+        return true;
+    }
+    let symbols = line.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+    symbols.len() >= 3 && symbols.iter().all(|c| SCENE_BREAK_CHARS.contains(*c))
+}
+
+// One paragraph per non-empty line. Chinese first-line indentation is applied by each renderer,
+// so manual full-width/ASCII indentation is stripped to avoid doubling it.
+pub fn parse_blocks(content: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    for line in content.lines() {
+        let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\u{3000}');
+        if line.is_empty() {
+            continue;
+        }
+        if is_scene_break(line) {
+            if !blocks.is_empty() && blocks.last() != Some(&Block::SceneBreak) {
+                blocks.push(Block::SceneBreak);
+            }
+            continue;
+        }
+        blocks.push(Block::Paragraph(line.to_string()));
+    }
+    if blocks.last() == Some(&Block::SceneBreak) {
+        blocks.pop();
+    }
+    blocks
+}
+
+fn select_range(index: Vec<ChapterIndexItem>, range: Option<&ChapterRange>) -> Result<Vec<ChapterIndexItem>, AppError> {
+    let Some(range) = range else {
+        return Ok(index);
+    };
+    let position = |id: u32| {
+        index
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, "storage.chapterNotFound").with_param("id", id))
+    };
+    let start = match range.start_id {
+        Some(id) => position(id)?,
+        None => 0,
+    };
+    let end = match range.end_id {
+        Some(id) => position(id)?,
+        None => index.len().saturating_sub(1),
+    };
+    if start > end {
+        return Err(AppError::new(ErrorKind::Invalid, "export.badRange"));
+    }
+    Ok(index.into_iter().skip(start).take(end - start + 1).collect())
+}
+
+pub fn load_manuscript(
+    project_dir: &str,
+    title: Option<&str>,
+    author: Option<&str>,
+    range: Option<&ChapterRange>,
+) -> Result<Manuscript, AppError> {
+    let locale = storage::project_locale(Path::new(project_dir));
+    let title = title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .or_else(|| {
+            Path::new(project_dir)
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| i18n::text(locale, "export.untitled").to_string());
+    let author = author.unwrap_or_default().trim().to_string();
+
+    let store = store::open(project_dir)?;
+    let index = select_range(store.list_chapters()?, range)?;
+    if index.is_empty() {
+        return Err(AppError::new(ErrorKind::Invalid, "export.noChapters"));
+    }
+
+    let mut volumes: Vec<Volume> = vec![];
+    for item in index {
+        let ch = store.load_chapter(item.id)?;
+        let chapter = ManuscriptChapter {
+            id: ch.id,
+            title: ch.title,
+            blocks: parse_blocks(&ch.content),
+        };
+        match volumes.last_mut() {
+            Some(v) if v.title == item.volume => v.chapters.push(chapter),
+            _ => volumes.push(Volume {
+                title: item.volume,
+                chapters: vec![chapter],
+            }),
+        }
+    }
+
+    Ok(Manuscript {
+        title,
+        author,
+        locale,
+        volumes,
+    })
+}
+
+pub fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // XML 1.0 forbids most control characters
+            c if (c as u32) < 0x20 && c != '\t' && c != '\n' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// EPUB dcterms:modified and DOCX core properties want UTC without fractional seconds
+pub fn now_utc_seconds() -> String {
+    let now = time::OffsetDateTime::now_utc();
+    now.replace_nanosecond(0)
+        .unwrap_or(now)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_else(|_| prompt::now_iso())
+}
+
+pub fn zip_err(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Io, "export.package").caused_by(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage;
+
+    #[test]
+    fn parse_blocks_detects_scene_breaks() {
+        let blocks = parse_blocks("　　他推开门。\n\n* * *\n\n　　三天后。\n◇◇◇\n");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Paragraph("他推开门。".to_string()),
+                Block::SceneBreak,
+                Block::Paragraph("三天后。".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_manuscript_groups_volumes_and_applies_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        storage::create_chapter(root.clone(), "第二章".to_string()).unwrap();
+        storage::create_chapter(root.clone(), "第三章".to_string()).unwrap();
+        storage::set_chapter_volume(root.clone(), 1, Some("第一卷".to_string())).unwrap();
+        storage::set_chapter_volume(root.clone(), 2, Some("第一卷".to_string())).unwrap();
+        storage::set_chapter_volume(root.clone(), 3, Some("第二卷".to_string())).unwrap();
+
+        let m = load_manuscript(&root, Some("书名"), None, None).unwrap();
+        assert_eq!(m.volumes.len(), 2);
+        assert_eq!(m.volumes[0].chapters.len(), 2);
+        assert!(m.has_volumes());
+
+        let range = ChapterRange {
+            start_id: Some(2),
+            end_id: Some(3),
+        };
+        let m = load_manuscript(&root, None, None, Some(&range)).unwrap();
+        assert_eq!(m.chapters().map(|c| c.id).collect::<Vec<_>>(), vec![2, 3]);
+
+        let bad = ChapterRange {
+            start_id: Some(3),
+            end_id: Some(1),
+        };
+        assert!(load_manuscript(&root, None, None, Some(&bad)).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::types::GenerationResponse;
+use serde_json::Value;
+
+// The JSON schema continuation output is asked to follow where the endpoint enforces schemas.
+pub fn schema() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "content": { "type": "string" },
+            "summary": { "type": "string" }
+        },
+        "required": ["content", "summary"],
+        "additionalProperties": false
+    })
+}
+
+// The part of a reply that should be JSON: the inside of a code fence (closed or not), else
+// everything from the first brace, which drops any prose the model put in front.
+fn json_candidate(raw: &str) -> Option<&str> {
+    if let Some(start) = raw.find("```") {
+        let after = &raw[start + 3..];
+        let after = after.strip_prefix("json").unwrap_or(after);
+        let inner = after.find("```").map_or(after, |end| &after[..end]);
+        if inner.contains('{') {
+            return inner.find('{').map(|i| inner[i..].trim_end());
+        }
+    }
+    raw.find('{').map(|i| raw[i..].trim_end())
+}
+
+// Appends a follow-up to a reply that was cut off. Models tend to reopen the code fence
+// they were in, which would land in the middle of the JSON.
+pub fn join(so_far: &str, next: &str) -> String {
+    let rest = next.trim_start();
+    let rest = match rest.strip_prefix("```") {
+        Some(after) => after.strip_prefix("json").unwrap_or(after).trim_start_matches(['\r', '\n']),
+        None => next,
+    };
+    format!("{so_far}{rest}")
+}
+
+fn next_significant(chars: &[char], from: usize) -> Option<char> {
+    chars[from..].iter().copied().find(|c| !c.is_whitespace())
+}
+
+// Fixes what models get wrong in JSON: quotes inside strings left unescaped (a quote only
+// ends a string when a delimiter follows), raw line breaks in strings, and output cut off
+// mid-way, whose open string and brackets are closed. Text after the top-level object is
+// dropped. Returns the repaired text and the closers that were appended.
+fn repair(candidate: &str) -> (String, String) {
+    let chars = candidate.chars().collect::<Vec<_>>();
+    let mut out = String::with_capacity(candidate.len());
+    let mut open = vec![];
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => {
+                    out.push(c);
+                    escaped = false;
+                }
+                '\\' => {
+                    out.push(c);
+                    escaped = true;
+                }
+                '"' => match next_significant(&chars, i + 1) {
+                    None | Some(',' | '}' | ']' | ':') => {
+                        out.push('"');
+                        in_string = false;
+                    }
+                    Some(_) => out.push_str("\\\""),
+                },
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' if open.last() == Some(&c) => {
+                open.pop();
+            }
+            _ => {}
+        }
+        out.push(c);
+        if open.is_empty() && matches!(c, '}' | ']') {
+            break;
+        }
+    }
+    if escaped {
+        out.pop();
+    }
+    if in_string {
+        out.push('"');
+    }
+    let trimmed = out.trim_end().trim_end_matches(',').len();
+    out.truncate(trimmed);
+    if out.ends_with(':') {
+        out.push_str(" null");
+    }
+    (out, open.into_iter().rev().collect())
+}
+
+fn parse_object(candidate: &str) -> Option<serde_json::Map<String, Value>> {
+    if let Ok(Value::Object(map)) = serde_json::from_str(candidate) {
+        return Some(map);
+    }
+    let (body, closers) = repair(candidate);
+    // a cut that fell right after a key leaves the key without a value
+    [format!("{body}{closers}"), format!("{body}: null{closers}")]
+        .iter()
+        .find_map(|text| match serde_json::from_str(text) {
+            Ok(Value::Object(map)) => Some(map),
+            _ => None,
+        })
+}
+
+fn text_field(map: &serde_json::Map<String, Value>, key: &str) -> String {
+    map.get(key).and_then(Value::as_str).unwrap_or("").to_string()
+}
+
+// Reads a continuation reply into content and summary, repairing broken JSON where it can.
+// `raw` keeps the original reply whenever it wasn't clean JSON. A reply that isn't JSON at
+// all is taken as the content itself.
+pub fn parse(raw: &str) -> GenerationResponse {
+    let clean = json_candidate(raw).and_then(|c| match serde_json::from_str(c) {
+        Ok(Value::Object(map)) => Some(map),
+        _ => None,
+    });
+    if let Some(map) = &clean {
+        return GenerationResponse {
+            content: text_field(map, "content"),
+            summary: text_field(map, "summary"),
+            raw: None,
+            ..Default::default()
+        };
+    }
+    match json_candidate(raw).and_then(parse_object) {
+        Some(map) if map.contains_key("content") => GenerationResponse {
+            content: text_field(&map, "content"),
+            summary: text_field(&map, "summary"),
+            raw: Some(raw.to_string()),
+            ..Default::default()
+        },
+        _ => GenerationResponse {
+            content: raw.to_string(),
+            summary: "".to_string(),
+            raw: Some(raw.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repairs_what_models_get_wrong() {
+        let clean = parse("```json\n{\"content\": \"雾散了。\", \"summary\": \"雾散\"}\n```");
+        assert_eq!((clean.content.as_str(), clean.summary.as_str()), ("雾散了。", "雾散"));
+        assert!(clean.raw.is_none());
+
+        let prose = parse("好的，以下是续写：\n{\"content\": \"灯亮了。\", \"summary\": \"灯亮\"} 希望有帮助");
+        assert_eq!(prose.content, "灯亮了。");
+
+        let quotes = parse("{\"content\": \"他说\"别走\"，然后\n转身。\", \"summary\": \"告别\"}");
+        assert_eq!(quotes.content, "他说\"别走\"，然后\n转身。");
+        assert_eq!(quotes.summary, "告别");
+        assert!(quotes.raw.is_some());
+
+        let cut = parse("```json\n{\"content\": \"海面上起了风，林舟");
+        assert_eq!(cut.content, "海面上起了风，林舟");
+        assert_eq!(cut.summary, "");
+        let cut_at_key = parse("{\"content\": \"浪声。\", \"summary\"");
+        assert_eq!(cut_at_key.content, "浪声。");
+        let cut_after_escape = parse("{\"content\": \"第一行\\");
+        assert_eq!(cut_after_escape.content, "第一行");
+
+        let joined = join("{\"content\": \"海面上起了风，", "```json\n林舟收起帆。\", \"summary\": \"起风\"}\n```");
+        assert_eq!(parse(&joined).content, "海面上起了风，林舟收起帆。");
+        assert_eq!(join("第一段", "第二段"), "第一段第二段");
+
+        let plain = parse("只是一段普通的正文。");
+        assert_eq!(plain.content, "只是一段普通的正文。");
+        assert_eq!(plain.summary, "");
+    }
+}
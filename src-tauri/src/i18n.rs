@@ -0,0 +1,435 @@
+use crate::types::Locale;
+use std::fmt::Display;
+use std::sync::RwLock;
+
+// The interface language, which backend messages follow. Set whenever the app state is
+// loaded or saved; the language of prompts and default content comes from the project.
+static LOCALE: RwLock<Locale> = RwLock::new(Locale::ZhCn);
+
+const ZH_CN: &[(&str, &str)] = &[
+    ("io.createDir", "无法创建目录"),
+    ("io.readDir", "无法读取目录"),
+    ("io.save", "保存失败"),
+    ("io.invalidPath", "无效路径"),
+    ("io.serialize", "序列化失败"),
+    ("io.remove", "删除失败"),
+    ("io.removeDir", "删除目录失败"),
+    ("file.read", "无法读取 {file}"),
+    ("file.write", "无法写入 {file}"),
+    ("file.malformed", "{file} 格式错误"),
+    ("what.chapterIndex", "章节索引"),
+    ("what.chapterText", "章节正文"),
+    ("what.chapterMeta", "章节元数据"),
+    ("what.presetFile", "预设文件"),
+    ("what.backupSettings", "备份设置"),
+    ("what.versioningSettings", "版本管理设置"),
+    ("what.sessionIndex", "会话索引"),
+    ("what.session", "会话"),
+    ("what.llmLog", "模型调用记录"),
+    ("what.llmLogEntry", "模型调用记录条目"),
+    ("what.encryptionSettings", "加密设置"),
+    ("storage.missingManifest", "项目缺少 project.json"),
+    (
+        "storage.schemaTooNew",
+        "项目格式版本 {version} 高于当前应用支持的版本 {supported}，请升级应用",
+    ),
+    ("storage.migrationFailed", "项目从版本 {version} 升级失败"),
+    ("storage.chapterNotFound", "章节不存在"),
+    ("storage.deleteChapterFile", "删除章节文件失败"),
+    ("storage.conflict", "文件已被外部修改：{path}，请重新加载后再保存"),
+    ("storage.projectMissing", "项目目录不存在"),
+    ("lock.write", "写入项目锁失败"),
+    ("lock.release", "释放项目锁失败"),
+    ("store.convertEncrypted", "加密项目暂不支持切换存储方式，请先取消加密"),
+    ("store.database", "数据库错误"),
+    ("store.databaseTooNew", "数据库版本 {version} 高于当前应用支持的版本 {supported}，请升级应用"),
+    ("crypto.locked", "项目已加密，请先输入密码解锁"),
+    ("crypto.wrongPassphrase", "密码错误"),
+    ("crypto.notEncrypted", "项目未加密"),
+    ("crypto.passphraseTooShort", "密码至少需要 {min} 个字符"),
+    ("crypto.sqliteUnsupported", "SQLite 存储的项目暂不支持加密"),
+    ("crypto.sealFailed", "加密失败"),
+    ("crypto.deriveKey", "密钥派生失败"),
+    ("crypto.notProtected", "无法解密：文件不属于加密项目"),
+    ("crypto.noSettings", "无法解密：项目缺少加密设置"),
+    ("crypto.decryptFailed", "解密失败，文件可能已损坏：{path}"),
+    ("secure.readApiKey", "读取 API Key 失败"),
+    ("secure.saveApiKey", "保存 API Key 失败"),
+    ("secure.deleteApiKey", "删除 API Key 失败"),
+    ("secure.readProjectKey", "读取项目密钥失败"),
+    ("secure.saveProjectKey", "保存项目密钥失败"),
+    ("secure.deleteProjectKey", "删除项目密钥失败"),
+    ("secure.keychain", "无法访问系统钥匙串"),
+    ("state.appDataDir", "无法获取应用数据目录"),
+    ("state.createAppDataDir", "无法创建应用数据目录"),
+    ("llm.invalidApiKey", "无效的 API Key"),
+    ("llm.missingApiKey", "当前端点未设置 API Key（请到“模型设置”里设置）"),
+    ("llm.noEndpoint", "请先在“模型设置”中添加一个 API 端点"),
+    ("llm.network", "无法连接到模型服务"),
+    ("llm.badResponse", "解析响应失败"),
+    ("llm.missingContent", "响应缺少 choices[0].message.content"),
+    ("llm.contextLength", "输入超出了模型的上下文长度"),
+    ("llm.quotaExceeded", "账户额度不足"),
+    ("llm.unauthorized", "API Key 无效或无权访问该模型"),
+    ("llm.rateLimited", "请求过于频繁，请稍后再试"),
+    ("llm.serverError", "模型服务暂时不可用"),
+    ("llm.requestFailed", "请求失败"),
+    ("llm.auditNotFound", "调用记录不存在或已被清理"),
+    ("llm.dailyBudgetExceeded", "已达到今日用量预算，可在“模型设置”中调整"),
+    ("llm.monthlyBudgetExceeded", "已达到本月用量预算，可在“模型设置”中调整"),
+    ("prompt.invalidTemplate", "提示词模板有误"),
+    ("prompt.previewUnsupported", "只能预览续写和讨论的提示词"),
+    ("template.unclosedTag", "第 {line} 行的 {{ 缺少对应的 }}"),
+    ("template.unclosedBlock", "第 {line} 行的 {tag} 缺少对应的结束标记"),
+    ("template.unexpectedClose", "第 {line} 行的 {tag} 没有对应的开始标记"),
+    ("template.badTag", "第 {line} 行无法识别的标记 {tag}"),
+    ("template.unknownVariable", "第 {line} 行的变量 {name} 在此模板中不可用"),
+    ("template.listAsText", "第 {line} 行的 {name} 是列表，需要用 {{#each {name}}} 展开"),
+    ("template.notAList", "第 {line} 行的 {name} 不是列表，不能用于 #each"),
+    ("export.noChapters", "项目中没有章节可导出"),
+    ("export.badRange", "导出范围无效：起始章节在结束章节之后"),
+    ("export.coverFormat", "不支持的封面图片格式: {ext}"),
+    ("export.readCover", "无法读取封面图片"),
+    ("export.package", "生成导出文件失败"),
+    ("export.write", "写入 {format} 文件失败"),
+    ("export.untitled", "未命名作品"),
+    ("export.author", "作者：{author}"),
+    ("export.toc", "目录"),
+    ("backup.archive", "备份文件读写失败"),
+    ("backup.create", "无法创建备份文件"),
+    ("backup.save", "保存备份失败"),
+    ("backup.info", "无法读取备份文件信息"),
+    ("backup.readDir", "无法读取备份目录"),
+    ("backup.removeOld", "删除旧备份失败"),
+    ("backup.missingManifest", "备份文件缺少 manifest.json"),
+    ("backup.badManifest", "manifest.json 格式错误"),
+    ("backup.open", "无法打开备份文件"),
+    ("backup.corrupt", "备份文件已损坏"),
+    ("backup.notBackup", "不是 CreatorAI 项目备份"),
+    ("backup.tooNew", "备份格式版本 {version} 过新，请升级应用"),
+    ("backup.unsafePath", "备份包含不安全的路径: {name}"),
+    ("backup.unlistedFile", "备份包含未登记的文件: {name}"),
+    ("backup.checksum", "文件校验失败: {name}"),
+    ("backup.incomplete", "备份文件不完整：部分文件缺失"),
+    ("backup.readTarget", "无法读取目标目录"),
+    ("backup.targetNotEmpty", "目标目录已存在且不为空，请选择一个新目录"),
+    ("diagnostics.write", "诊断包写入失败"),
+    ("diagnostics.readLog", "无法读取日志"),
+    ("diagnostics.create", "无法创建诊断包"),
+    ("diagnostics.save", "保存诊断包失败"),
+    ("import.noChapters", "没有识别到任何章节"),
+    ("import.badPattern", "章节标题规则无效 {pattern}"),
+    ("import.unknownEncoding", "不支持的编码: {encoding}"),
+    ("import.decode", "无法以 {encoding} 解码文件"),
+    ("import.detectEncoding", "无法识别文件编码，请手动指定（如 UTF-8、GBK）"),
+    ("import.readFile", "无法读取文件"),
+    ("import.readScrivener", "无法读取 Scrivener 项目"),
+    ("import.noScrivx", "未找到 .scrivx 文件，请选择 Scrivener 项目（.scriv）"),
+    ("import.readScrivx", "无法读取 .scrivx"),
+    ("import.badScrivx", ".scrivx 格式错误"),
+    ("import.noBinder", ".scrivx 缺少 Binder"),
+    ("import.body", "正文"),
+    ("import.preface", "序"),
+    ("watcher.start", "无法监听项目目录"),
+    ("chapter.first", "第一章"),
+    ("chapter.numbered", "第{id}章"),
+    ("session.defaultTitle", "新对话"),
+    ("logging.createDir", "无法创建日志目录"),
+    ("logging.createFile", "无法创建日志文件"),
+    ("logging.init", "初始化日志失败"),
+    ("vcs.failed", "版本库操作失败"),
+    ("vcs.writeIgnore", "写入 .gitignore 失败"),
+    ("vcs.notEnabled", "项目未开启版本管理"),
+    ("vcs.emptyBranch", "分支名不能为空"),
+    ("vcs.branchExists", "分支 {branch} 已存在"),
+    ("vcs.badCommitId", "无效的提交 ID: {id}"),
+    ("vcs.commitNotFound", "找不到提交 {id}"),
+    ("vcs.createBranch", "无法创建分支 {branch}"),
+    ("vcs.titleSeparator", "、"),
+    ("vcs.savedChapters", "保存章节：{titles}"),
+    ("vcs.savedManyChapters", "保存章节：{titles} 等 {count} 章"),
+    ("vcs.aiAccepted", "采纳 AI 续写：{title}"),
+    ("vcs.enabled", "开启版本管理"),
+    ("vcs.presetUpdated", "更新文风预设"),
+    ("vcs.beforeCheckout", "切换到分支 {branch} 前自动保存"),
+    ("vcs.autoCommitFailed", "章节已保存，但没能记入版本历史"),
+    ("integrity.leftoverTemp", "未完成的写入留下的临时文件"),
+    ("integrity.duplicateChapter", "章节 {id} 在索引中出现多次"),
+    ("integrity.missingText", "索引中的章节《{title}》缺少正文文件"),
+    ("integrity.missingMeta", "章节《{title}》缺少元数据文件"),
+    ("integrity.orphanedChapterFile", "文件未登记在章节索引中"),
+    ("integrity.duplicateSession", "会话「{title}」在索引中出现多次"),
+    ("integrity.missingSession", "索引中的会话「{title}」缺少文件"),
+    ("integrity.orphanedSessionFile", "会话文件未登记在会话索引中"),
+    ("integrity.databaseMissing", "项目数据库 project.db 不存在"),
+    ("integrity.databaseUnreadable", "无法打开项目数据库"),
+    ("repair.databaseCorrupt", "项目数据库已损坏，无法自动修复，请从备份恢复"),
+    ("repair.removedDuplicateChapter", "移除重复的章节索引项 {id}"),
+    ("repair.createdText", "为章节《{title}》补建空白正文"),
+    ("repair.createdMeta", "为章节《{title}》补建元数据"),
+    ("repair.adoptedChapter", "将未登记的 {path} 加入章节索引"),
+    ("repair.removedMeta", "删除孤立的章节元数据 {path}"),
+    ("repair.removedDuplicateSession", "移除重复的会话索引项「{title}」"),
+    ("repair.removedMissingSession", "从索引中移除缺失的会话「{title}」"),
+    ("repair.adoptedSession", "将未登记的会话「{title}」加入索引"),
+    ("repair.removedTemp", "删除临时文件 {path}"),
+    ("repair.movedAside", "格式错误的 {path} 已另存为 {aside}"),
+    ("io.rename", "移动失败"),
+];
+
+const EN: &[(&str, &str)] = &[
+    ("io.createDir", "Could not create directory"),
+    ("io.readDir", "Could not read directory"),
+    ("io.save", "Could not save"),
+    ("io.invalidPath", "Invalid path"),
+    ("io.serialize", "Could not serialize"),
+    ("io.remove", "Could not delete"),
+    ("io.removeDir", "Could not delete directory"),
+    ("file.read", "Could not read {file}"),
+    ("file.write", "Could not write {file}"),
+    ("file.malformed", "{file} is malformed"),
+    ("what.chapterIndex", "the chapter index"),
+    ("what.chapterText", "the chapter text"),
+    ("what.chapterMeta", "the chapter metadata"),
+    ("what.presetFile", "the preset file"),
+    ("what.backupSettings", "the backup settings"),
+    ("what.versioningSettings", "the versioning settings"),
+    ("what.sessionIndex", "the chat session index"),
+    ("what.session", "the chat session"),
+    ("what.llmLog", "the model call log"),
+    ("what.llmLogEntry", "the model call log entry"),
+    ("what.encryptionSettings", "the encryption settings"),
+    ("storage.missingManifest", "The project has no project.json"),
+    (
+        "storage.schemaTooNew",
+        "The project format version {version} is newer than this app supports ({supported}); please update the app",
+    ),
+    (
+        "storage.migrationFailed",
+        "Upgrading the project from version {version} failed",
+    ),
+    ("storage.chapterNotFound", "Chapter not found"),
+    ("storage.deleteChapterFile", "Could not delete chapter file"),
+    (
+        "storage.conflict",
+        "The file was changed outside the app: {path}. Reload it before saving",
+    ),
+    ("storage.projectMissing", "The project folder does not exist"),
+    ("lock.write", "Could not write the project lock"),
+    ("lock.release", "Could not release the project lock"),
+    ("store.convertEncrypted", "Encrypted projects can't switch storage backends yet; turn off encryption first"),
+    ("store.database", "Database error"),
+    (
+        "store.databaseTooNew",
+        "Database version {version} is newer than this app supports ({supported}); please update the app",
+    ),
+    (
+        "crypto.locked",
+        "The project is encrypted; enter its passphrase to unlock it",
+    ),
+    ("crypto.wrongPassphrase", "Wrong passphrase"),
+    ("crypto.notEncrypted", "The project is not encrypted"),
+    ("crypto.passphraseTooShort", "The passphrase needs at least {min} characters"),
+    ("crypto.sqliteUnsupported", "Projects stored in SQLite can't be encrypted yet"),
+    ("crypto.sealFailed", "Encryption failed"),
+    ("crypto.deriveKey", "Could not derive the key"),
+    ("crypto.notProtected", "Can't decrypt: the file doesn't belong to an encrypted project"),
+    ("crypto.noSettings", "Can't decrypt: the project has no encryption settings"),
+    ("crypto.decryptFailed", "Decryption failed, the file may be damaged: {path}"),
+    ("secure.readApiKey", "Could not read the API key"),
+    ("secure.saveApiKey", "Could not save the API key"),
+    ("secure.deleteApiKey", "Could not delete the API key"),
+    ("secure.readProjectKey", "Could not read the project key"),
+    ("secure.saveProjectKey", "Could not save the project key"),
+    ("secure.deleteProjectKey", "Could not delete the project key"),
+    ("secure.keychain", "Could not access the system keychain"),
+    ("state.appDataDir", "Could not locate the app data directory"),
+    ("state.createAppDataDir", "Could not create the app data directory"),
+    ("llm.invalidApiKey", "Invalid API key"),
+    (
+        "llm.missingApiKey",
+        "No API key is set for this endpoint (set one under Models)",
+    ),
+    ("llm.noEndpoint", "Add an API endpoint under Models first"),
+    ("llm.network", "Could not reach the model service"),
+    ("llm.badResponse", "Could not parse the response"),
+    ("llm.missingContent", "The response has no choices[0].message.content"),
+    (
+        "llm.contextLength",
+        "The input is longer than the model's context window",
+    ),
+    ("llm.quotaExceeded", "The account has run out of credit"),
+    (
+        "llm.unauthorized",
+        "The API key is invalid or has no access to this model",
+    ),
+    ("llm.rateLimited", "Too many requests; try again shortly"),
+    ("llm.serverError", "The model service is temporarily unavailable"),
+    ("llm.requestFailed", "Request failed"),
+    (
+        "llm.auditNotFound",
+        "The logged call doesn't exist or has been cleaned up",
+    ),
+    (
+        "llm.dailyBudgetExceeded",
+        "Today's usage budget has been reached; adjust it under Models",
+    ),
+    (
+        "llm.monthlyBudgetExceeded",
+        "This month's usage budget has been reached; adjust it under Models",
+    ),
+    ("prompt.invalidTemplate", "The prompt template has an error"),
+    ("prompt.previewUnsupported", "Only the continue and discuss prompts can be previewed"),
+    ("template.unclosedTag", "The {{ on line {line} has no matching }}"),
+    ("template.unclosedBlock", "The {tag} on line {line} is never closed"),
+    ("template.unexpectedClose", "The {tag} on line {line} has no matching opening tag"),
+    ("template.badTag", "Unrecognised tag {tag} on line {line}"),
+    ("template.unknownVariable", "The variable {name} on line {line} is not available in this template"),
+    ("template.listAsText", "{name} on line {line} is a list; expand it with {{#each {name}}}"),
+    ("template.notAList", "{name} on line {line} is not a list and cannot be used with #each"),
+    ("export.noChapters", "The project has no chapters to export"),
+    ("export.badRange", "Invalid export range: the first chapter comes after the last"),
+    ("export.coverFormat", "Unsupported cover image format: {ext}"),
+    ("export.readCover", "Could not read the cover image"),
+    ("export.package", "Could not build the export file"),
+    ("export.write", "Could not write the {format} file"),
+    ("export.untitled", "Untitled"),
+    ("export.author", "Author: {author}"),
+    ("export.toc", "Contents"),
+    ("backup.archive", "Could not read or write the backup archive"),
+    ("backup.create", "Could not create the backup file"),
+    ("backup.save", "Could not save the backup"),
+    ("backup.info", "Could not read the backup file's details"),
+    ("backup.readDir", "Could not read the backups folder"),
+    ("backup.removeOld", "Could not delete an old backup"),
+    ("backup.missingManifest", "The backup has no manifest.json"),
+    ("backup.badManifest", "The backup's manifest.json is malformed"),
+    ("backup.open", "Could not open the backup file"),
+    ("backup.corrupt", "The backup file is damaged"),
+    ("backup.notBackup", "Not a CreatorAI project backup"),
+    ("backup.tooNew", "Backup format version {version} is too new; please update the app"),
+    ("backup.unsafePath", "The backup contains an unsafe path: {name}"),
+    ("backup.unlistedFile", "The backup contains a file missing from its manifest: {name}"),
+    ("backup.checksum", "Checksum mismatch: {name}"),
+    ("backup.incomplete", "The backup is incomplete: some files are missing"),
+    ("backup.readTarget", "Could not read the target folder"),
+    ("backup.targetNotEmpty", "The target folder exists and is not empty; choose a new folder"),
+    ("diagnostics.write", "Could not write the diagnostics bundle"),
+    ("diagnostics.readLog", "Could not read the log"),
+    ("diagnostics.create", "Could not create the diagnostics bundle"),
+    ("diagnostics.save", "Could not save the diagnostics bundle"),
+    ("import.noChapters", "No chapters were found"),
+    ("import.badPattern", "Invalid chapter heading pattern {pattern}"),
+    ("import.unknownEncoding", "Unsupported encoding: {encoding}"),
+    ("import.decode", "Could not decode the file as {encoding}"),
+    ("import.detectEncoding", "Could not detect the file's encoding; choose one (such as UTF-8 or GBK)"),
+    ("import.readFile", "Could not read the file"),
+    ("import.readScrivener", "Could not read the Scrivener project"),
+    ("import.noScrivx", "No .scrivx file found; choose a Scrivener project (.scriv)"),
+    ("import.readScrivx", "Could not read the .scrivx file"),
+    ("import.badScrivx", "The .scrivx file is malformed"),
+    ("import.noBinder", "The .scrivx file has no Binder"),
+    ("import.body", "Text"),
+    ("import.preface", "Preface"),
+    ("watcher.start", "Could not watch the project folder"),
+    ("chapter.first", "Chapter 1"),
+    ("chapter.numbered", "Chapter {id}"),
+    ("session.defaultTitle", "New chat"),
+    ("logging.createDir", "Could not create the log directory"),
+    ("logging.createFile", "Could not create the log file"),
+    ("logging.init", "Could not initialize logging"),
+    ("vcs.failed", "Version control operation failed"),
+    ("vcs.writeIgnore", "Could not write .gitignore"),
+    ("vcs.notEnabled", "Versioning is off for this project"),
+    ("vcs.emptyBranch", "The branch name can't be empty"),
+    ("vcs.branchExists", "Branch {branch} already exists"),
+    ("vcs.badCommitId", "Invalid commit ID: {id}"),
+    ("vcs.commitNotFound", "Commit {id} not found"),
+    ("vcs.createBranch", "Could not create branch {branch}"),
+    ("vcs.titleSeparator", ", "),
+    ("vcs.savedChapters", "Save chapters: {titles}"),
+    ("vcs.savedManyChapters", "Save chapters: {titles} and others, {count} in all"),
+    ("vcs.aiAccepted", "Accept AI continuation: {title}"),
+    ("vcs.enabled", "Turn on versioning"),
+    ("vcs.presetUpdated", "Update style preset"),
+    ("vcs.beforeCheckout", "Save before switching to branch {branch}"),
+    ("vcs.autoCommitFailed", "The chapters were saved but could not be added to the version history"),
+    ("integrity.leftoverTemp", "Temporary file left by an unfinished write"),
+    ("integrity.duplicateChapter", "Chapter {id} is listed more than once in the index"),
+    ("integrity.missingText", "Chapter \"{title}\" is in the index but its text file is missing"),
+    ("integrity.missingMeta", "Chapter \"{title}\" has no metadata file"),
+    ("integrity.orphanedChapterFile", "The file is not listed in the chapter index"),
+    ("integrity.duplicateSession", "Chat session \"{title}\" is listed more than once in the index"),
+    ("integrity.missingSession", "Chat session \"{title}\" is in the index but its file is missing"),
+    ("integrity.orphanedSessionFile", "The chat session file is not listed in the session index"),
+    ("integrity.databaseMissing", "The project database project.db is missing"),
+    ("integrity.databaseUnreadable", "Could not open the project database"),
+    (
+        "repair.databaseCorrupt",
+        "The project database is damaged and can't be repaired automatically; restore it from a backup",
+    ),
+    ("repair.removedDuplicateChapter", "Removed duplicate chapter index entry {id}"),
+    ("repair.createdText", "Created empty text for chapter \"{title}\""),
+    ("repair.createdMeta", "Created metadata for chapter \"{title}\""),
+    ("repair.adoptedChapter", "Added unlisted {path} to the chapter index"),
+    ("repair.removedMeta", "Deleted orphaned chapter metadata {path}"),
+    ("repair.removedDuplicateSession", "Removed duplicate chat session index entry \"{title}\""),
+    ("repair.removedMissingSession", "Removed missing chat session \"{title}\" from the index"),
+    ("repair.adoptedSession", "Added unlisted chat session \"{title}\" to the index"),
+    ("repair.removedTemp", "Deleted temporary file {path}"),
+    ("repair.movedAside", "Moved malformed {path} aside to {aside}"),
+    ("io.rename", "Could not move"),
+];
+
+fn catalog(locale: Locale) -> &'static [(&'static str, &'static str)] {
+    match locale {
+        Locale::ZhCn => ZH_CN,
+        Locale::En => EN,
+    }
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set_locale(locale: Locale) {
+    *LOCALE.write().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+// The message for `key` in `locale`, falling back to Chinese and then to the key itself.
+pub fn text(locale: Locale, key: &'static str) -> &'static str {
+    let find =
+        |catalog: &'static [(&'static str, &'static str)]| catalog.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    find(catalog(locale)).or_else(|| find(ZH_CN)).unwrap_or(key)
+}
+
+pub fn t(key: &'static str) -> &'static str {
+    text(locale(), key)
+}
+
+// `text` with each `{name}` replaced by its value.
+pub fn text_with(locale: Locale, key: &'static str, params: &[(&str, &dyn Display)]) -> String {
+    let mut out = text(locale, key).to_string();
+    for (name, value) in params {
+        out = out.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    out
+}
+
+pub fn tf(key: &'static str, params: &[(&str, &dyn Display)]) -> String {
+    text_with(locale(), key, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_cover_the_same_keys() {
+        let zh = ZH_CN.iter().map(|(k, _)| k);
+        assert!(zh.eq(EN.iter().map(|(k, _)| k)));
+
+        let conflict = text_with(Locale::En, "storage.conflict", &[("path", &"a.txt")]);
+        assert!(conflict.contains("a.txt") && !conflict.contains('{'));
+    }
+}
@@ -0,0 +1,142 @@
+use super::rtf::rtf_to_text;
+use super::text::decode;
+use super::{markdown_to_text, preview, write_chapters, ImportedChapter};
+use crate::error::AppError;
+use crate::types::*;
+use std::cmp::Ordering;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+const EXTENSIONS: &[&str] = &["txt", "md", "markdown", "rtf"];
+
+fn take_number(it: &mut Peekable<Chars>) -> String {
+    let mut n = String::new();
+    while let Some(c) = it.peek().copied().filter(|c| c.is_ascii_digit()) {
+        n.push(c);
+        it.next();
+    }
+    n.trim_start_matches('0').to_string()
+}
+
+// Natural order so that "2.txt" sorts before "10.txt".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (na, nb) = (take_number(&mut a), take_number(&mut b));
+                let ord = na.len().cmp(&nb.len()).then(na.cmp(&nb));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+// "001_第一章" / "01 - Title" / "3. 标题" -> the part after the ordering prefix.
+fn title_from_name(name: &str) -> String {
+    let stripped = name
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, '_' | '-' | '.' | '、'))
+        .trim();
+    if stripped.is_empty() {
+        name.trim().to_string()
+    } else {
+        stripped.to_string()
+    }
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| AppError::io("io.readDir", dir, e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')))
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        let name = |p: &PathBuf| p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        natural_cmp(&name(a), &name(b))
+    });
+    Ok(entries)
+}
+
+fn read_chapter(path: &Path, volume: Option<&str>) -> Result<ImportedChapter, AppError> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let bytes = fs::read(path).map_err(|e| AppError::io("import.readFile", path, e))?;
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let mut title = title_from_name(&stem);
+
+    let content = match ext.as_str() {
+        "rtf" => rtf_to_text(&bytes),
+        "md" | "markdown" => {
+            let (text, _) = decode(&bytes, None)?;
+            let text = text.replace("\r\n", "\n");
+            // a leading "# Heading" is the chapter title, not body text
+            let mut lines = text.lines().skip_while(|l| l.trim().is_empty()).peekable();
+            let heading = lines.peek().and_then(|l| l.trim().strip_prefix("# ")).map(|h| h.trim().to_string());
+            match heading {
+                Some(h) if !h.is_empty() => {
+                    title = h;
+                    markdown_to_text(&lines.skip(1).collect::<Vec<_>>().join("\n"))
+                }
+                _ => markdown_to_text(&text),
+            }
+        }
+        _ => decode(&bytes, None)?.0.replace("\r\n", "\n"),
+    };
+
+    Ok(ImportedChapter {
+        title,
+        volume: volume.map(|v| v.to_string()),
+        content: content.trim_matches('\n').to_string(),
+        summary: String::new(),
+    })
+}
+
+// Files are chapters and sub-directories are volumes; deeper directories take their own name.
+fn collect(dir: &Path, volume: Option<&str>, out: &mut Vec<ImportedChapter>) -> Result<(), AppError> {
+    for path in sorted_entries(dir)? {
+        if path.is_dir() {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            collect(&path, Some(&title_from_name(&name)), out)?;
+            continue;
+        }
+        let supported = path
+            .extension()
+            .is_some_and(|e| EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()));
+        if supported {
+            out.push(read_chapter(&path, volume)?);
+        }
+    }
+    Ok(())
+}
+
+fn read_folder(dir_path: &str) -> Result<Vec<ImportedChapter>, AppError> {
+    let mut chapters = vec![];
+    collect(Path::new(dir_path), None, &mut chapters)?;
+    Ok(chapters)
+}
+
+pub fn preview_folder(dir_path: &str) -> Result<ImportPreview, AppError> {
+    Ok(preview(&read_folder(dir_path)?, None))
+}
+
+pub fn import_folder(project_dir: String, dir_path: &str) -> Result<Vec<ChapterIndexItem>, AppError> {
+    write_chapters(&project_dir, read_folder(dir_path)?)
+}
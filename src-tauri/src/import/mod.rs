@@ -0,0 +1,106 @@
+mod folder;
+mod rtf;
+mod scrivener;
+mod text;
+
+pub use folder::{import_folder, preview_folder};
+pub use scrivener::{import_scrivener, preview_scrivener};
+pub use text::{import_manuscript, preview_manuscript};
+
+use crate::error::{AppError, ErrorKind};
+use crate::store::{self, ProjectStore};
+use crate::types::*;
+use regex::Regex;
+
+// A chapter detected by one of the importers, before it is written to the project.
+pub struct ImportedChapter {
+    pub title: String,
+    pub volume: Option<String>,
+    pub content: String,
+    pub summary: String,
+}
+
+pub fn preview(chapters: &[ImportedChapter], source_encoding: Option<String>) -> ImportPreview {
+    ImportPreview {
+        encoding: source_encoding,
+        chapters: chapters
+            .iter()
+            .map(|c| ImportPreviewChapter {
+                title: c.title.clone(),
+                volume: c.volume.clone(),
+                char_count: c.content.chars().filter(|c| !c.is_whitespace()).count() as u32,
+                excerpt: c.content.trim().chars().take(60).collect(),
+            })
+            .collect(),
+    }
+}
+
+// Reduce Markdown to the prose a chapter holds: markup is dropped, thematic breaks stay as scene breaks.
+pub fn markdown_to_text(md: &str) -> String {
+    let front_matter = Regex::new(r"(?s)\A---\n.*?\n---\n").unwrap();
+    let inline = [
+        (Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap(), ""),
+        (Regex::new(r"\[([^\]]+)\]\([^)]*\)").unwrap(), "$1"),
+        (Regex::new(r"\*\*(.+?)\*\*").unwrap(), "$1"),
+        (Regex::new(r"__(.+?)__").unwrap(), "$1"),
+        (Regex::new(r"\*([^*\s][^*]*?)\*").unwrap(), "$1"),
+        (Regex::new(r"`([^`]*)`").unwrap(), "$1"),
+        (Regex::new(r"\\([\\`*_{}\[\]()#+\-.!>])").unwrap(), "$1"),
+    ];
+    let thematic_break = Regex::new(r"^(?:\*\s*){3,}$|^(?:-\s*){3,}$|^(?:_\s*){3,}$").unwrap();
+    let block_prefix = Regex::new(r"^(?:#{1,6}\s+|>\s?|\s*[-*+]\s+)").unwrap();
+
+    let md = front_matter.replace(md, "");
+    let mut lines = vec![];
+    for line in md.lines() {
+        if thematic_break.is_match(line.trim()) {
+            lines.push("* * *".to_string());
+            continue;
+        }
+        let mut line = block_prefix.replace(line, "").to_string();
+        for (re, rep) in &inline {
+            line = re.replace_all(&line, *rep).to_string();
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+// A freshly initialised project only holds an empty "第一章"; drop it so the imported
+// manuscript starts at the top of the index instead of after a blank chapter.
+fn drop_placeholder_chapter(store: &dyn ProjectStore) -> Result<(), AppError> {
+    let index = store.list_chapters()?;
+    if let [only] = index.as_slice() {
+        let ch = store.load_chapter(only.id)?;
+        if ch.content.trim().is_empty() && ch.summary.trim().is_empty() {
+            store.delete_chapter(only.id)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_chapters(project_dir: &str, chapters: Vec<ImportedChapter>) -> Result<Vec<ChapterIndexItem>, AppError> {
+    if chapters.is_empty() {
+        return Err(AppError::new(ErrorKind::Invalid, "import.noChapters"));
+    }
+    let store = store::open(project_dir)?;
+    drop_placeholder_chapter(store.as_ref())?;
+
+    let mut created = Vec::with_capacity(chapters.len());
+    for imported in chapters {
+        let mut item = store.create_chapter(imported.title.clone())?;
+        let chapter = Chapter {
+            id: item.id,
+            title: imported.title,
+            content: imported.content,
+            summary: imported.summary,
+        };
+        store.save_chapter(&chapter)?;
+        if imported.volume.is_some() {
+            store.set_chapter_volume(item.id, imported.volume.clone())?;
+            item.volume = imported.volume;
+        }
+        created.push(item);
+    }
+    Ok(created)
+}
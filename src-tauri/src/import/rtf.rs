@@ -0,0 +1,223 @@
+use encoding_rs::{Encoding, BIG5, EUC_KR, GBK, SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+// Destinations whose text never belongs to the document body.
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "headerl",
+    "headerr",
+    "headerf",
+    "footer",
+    "footerl",
+    "footerr",
+    "footerf",
+    "footnote",
+    "annotation",
+    "listtable",
+    "listoverridetable",
+    "revtbl",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+    "fldinst",
+    "expandedcolortbl",
+];
+
+fn codepage(n: i32) -> &'static Encoding {
+    match n {
+        936 => GBK,
+        950 => BIG5,
+        932 => SHIFT_JIS,
+        949 => EUC_KR,
+        65001 => UTF_8,
+        _ => WINDOWS_1252,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct GroupState {
+    skip: bool,
+    uc: usize,
+}
+
+struct Output {
+    text: String,
+    bytes: Vec<u8>,
+    encoding: &'static Encoding,
+}
+
+impl Output {
+    fn flush(&mut self) {
+        if !self.bytes.is_empty() {
+            let (s, _, _) = self.encoding.decode(&self.bytes);
+            self.text.push_str(&s);
+            self.bytes.clear();
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.flush();
+        self.text.push(c);
+    }
+}
+
+// Plain-text extraction for the RTF written by Scrivener and word processors: paragraphs become
+// newlines, \uN and \'hh escapes are decoded, formatting and non-body destinations are dropped.
+pub fn rtf_to_text(rtf: &[u8]) -> String {
+    let mut out = Output {
+        text: String::new(),
+        bytes: vec![],
+        encoding: WINDOWS_1252,
+    };
+    let mut stack: Vec<GroupState> = vec![];
+    let mut state = GroupState { skip: false, uc: 1 };
+    // fallback characters still to swallow after a \uN
+    let mut pending_skip = 0usize;
+    let mut i = 0;
+
+    while i < rtf.len() {
+        let b = rtf[i];
+        match b {
+            b'{' => {
+                stack.push(state);
+                pending_skip = 0;
+                i += 1;
+            }
+            b'}' => {
+                state = stack.pop().unwrap_or(state);
+                pending_skip = 0;
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = rtf.get(i) else { break };
+                if next.is_ascii_alphabetic() {
+                    let start = i;
+                    while i < rtf.len() && rtf[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = std::str::from_utf8(&rtf[start..i]).unwrap_or_default();
+                    let num_start = i;
+                    if i < rtf.len() && rtf[i] == b'-' {
+                        i += 1;
+                    }
+                    while i < rtf.len() && rtf[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param = std::str::from_utf8(&rtf[num_start..i])
+                        .ok()
+                        .and_then(|s| s.parse::<i32>().ok());
+                    if i < rtf.len() && rtf[i] == b' ' {
+                        i += 1;
+                    }
+
+                    if SKIPPED_DESTINATIONS.contains(&word) {
+                        state.skip = true;
+                        continue;
+                    }
+                    match word {
+                        "ansicpg" => {
+                            out.flush();
+                            out.encoding = codepage(param.unwrap_or(1252));
+                        }
+                        "uc" => state.uc = param.unwrap_or(1).max(0) as usize,
+                        _ if state.skip => {}
+                        "u" => {
+                            let code = param.unwrap_or(0);
+                            let code = if code < 0 { code + 65536 } else { code } as u32;
+                            if let Some(c) = char::from_u32(code) {
+                                out.push(c);
+                            }
+                            pending_skip = state.uc;
+                        }
+                        "par" | "line" | "sect" | "page" | "row" => out.push('\n'),
+                        "tab" | "cell" => out.push('\t'),
+                        "emdash" => out.push('—'),
+                        "endash" => out.push('–'),
+                        "lquote" => out.push('‘'),
+                        "rquote" => out.push('’'),
+                        "ldblquote" => out.push('“'),
+                        "rdblquote" => out.push('”'),
+                        "bullet" => out.push('•'),
+                        _ => {}
+                    }
+                    continue;
+                }
+                i += 1;
+                match next {
+                    b'*' => state.skip = true,
+                    b'\'' => {
+                        let hex = rtf.get(i..i + 2).and_then(|h| std::str::from_utf8(h).ok());
+                        if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                            i += 2;
+                            if pending_skip > 0 {
+                                pending_skip -= 1;
+                            } else if !state.skip {
+                                out.bytes.push(byte);
+                            }
+                        }
+                    }
+                    b'\\' | b'{' | b'}' if !state.skip => {
+                        if pending_skip > 0 {
+                            pending_skip -= 1;
+                        } else {
+                            out.push(next as char);
+                        }
+                    }
+                    b'~' if !state.skip => out.push('\u{a0}'),
+                    b'_' if !state.skip => out.push('-'),
+                    b'\n' | b'\r' if !state.skip => out.push('\n'),
+                    _ => {}
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            _ => {
+                i += 1;
+                if state.skip {
+                    continue;
+                }
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                    continue;
+                }
+                if b < 0x80 {
+                    out.push(b as char);
+                } else {
+                    out.bytes.push(b);
+                }
+            }
+        }
+    }
+    out.flush();
+
+    out.text
+        .lines()
+        .map(|l| l.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_unicode_and_codepage_escapes() {
+        let rtf = br"{\rtf1\ansi\ansicpg936\deff0{\fonttbl{\f0\fnil SimSun;}}{\*\generator Scrivener;}
+\pard\f0 \u20170?\u22825?\'a3\'ac\par
+\'c4\'e3\'ba\'c3 \{ok\}\par
+}";
+        assert_eq!(rtf_to_text(rtf), "今天，\n你好 {ok}");
+    }
+}
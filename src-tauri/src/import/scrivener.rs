@@ -0,0 +1,231 @@
+use super::rtf::rtf_to_text;
+use super::{preview, write_chapters, ImportedChapter};
+use crate::error::{AppError, ErrorKind};
+use crate::types::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCENE_SEPARATOR: &str = "\n\n* * *\n\n";
+
+struct BinderItem {
+    id: String,
+    kind: String,
+    title: String,
+    include: bool,
+    children: Vec<BinderItem>,
+}
+
+impl BinderItem {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+// Accepts either the .scriv bundle directory or the .scrivx file inside it.
+fn locate(path: &Path) -> Result<(PathBuf, PathBuf), AppError> {
+    if path.is_file() {
+        let bundle = path
+            .parent()
+            .ok_or_else(|| AppError::new(ErrorKind::Invalid, "io.invalidPath"))?
+            .to_path_buf();
+        return Ok((bundle, path.to_path_buf()));
+    }
+    let entries = fs::read_dir(path).map_err(|e| AppError::io("import.readScrivener", path, e))?;
+    for entry in entries.flatten() {
+        let p = entry.path();
+        if p.extension().is_some_and(|e| e.eq_ignore_ascii_case("scrivx")) {
+            return Ok((path.to_path_buf(), p));
+        }
+    }
+    Err(AppError::new(ErrorKind::NotFound, "import.noScrivx"))
+}
+
+fn parse_item(node: roxmltree::Node) -> BinderItem {
+    let child_text = |name: &str| {
+        node.children()
+            .find(|c| c.has_tag_name(name))
+            .and_then(|c| c.text())
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let include = node
+        .children()
+        .find(|c| c.has_tag_name("MetaData"))
+        .and_then(|m| m.children().find(|c| c.has_tag_name("IncludeInCompile")))
+        .and_then(|c| c.text())
+        .map(|t| !t.trim().eq_ignore_ascii_case("no"))
+        .unwrap_or(true);
+    let children = node
+        .children()
+        .find(|c| c.has_tag_name("Children"))
+        .map(|c| c.children().filter(|n| n.has_tag_name("BinderItem")).map(parse_item).collect())
+        .unwrap_or_default();
+    BinderItem {
+        // Scrivener 3 uses UUID, Scrivener 2 a numeric ID
+        id: node.attribute("UUID").or_else(|| node.attribute("ID")).unwrap_or_default().to_string(),
+        kind: node.attribute("Type").unwrap_or_default().to_string(),
+        title: child_text("Title"),
+        include,
+        children,
+    }
+}
+
+fn parse_binder(xml: &str) -> Result<Vec<BinderItem>, AppError> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| AppError::new(ErrorKind::Invalid, "import.badScrivx").caused_by(e))?;
+    let binder = doc
+        .descendants()
+        .find(|n| n.has_tag_name("Binder"))
+        .ok_or_else(|| AppError::new(ErrorKind::Invalid, "import.noBinder"))?;
+    let mut items = binder
+        .children()
+        .filter(|n| n.has_tag_name("BinderItem"))
+        .map(parse_item)
+        .collect::<Vec<_>>();
+
+    // Only the manuscript (Draft) is imported; research and trash stay behind
+    if let Some(pos) = items.iter().position(|i| i.kind == "DraftFolder") {
+        return Ok(items.swap_remove(pos).children);
+    }
+    items.retain(|i| i.kind != "ResearchFolder" && i.kind != "TrashFolder");
+    Ok(items)
+}
+
+struct Reader {
+    bundle: PathBuf,
+}
+
+impl Reader {
+    fn read_text(&self, id: &str) -> String {
+        let candidates = [
+            self.bundle.join("Files").join("Data").join(id).join("content.rtf"),
+            self.bundle.join("Files").join("Docs").join(format!("{id}.rtf")),
+        ];
+        candidates
+            .iter()
+            .find_map(|p| fs::read(p).ok())
+            .map(|bytes| rtf_to_text(&bytes))
+            .unwrap_or_default()
+    }
+
+    fn read_synopsis(&self, id: &str) -> String {
+        let candidates = [
+            self.bundle.join("Files").join("Data").join(id).join("synopsis.txt"),
+            self.bundle.join("Files").join("Docs").join(format!("{id}_synopsis.txt")),
+        ];
+        candidates
+            .iter()
+            .find_map(|p| fs::read_to_string(p).ok())
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    // A document, or a folder whose children are all documents (scenes), becomes one chapter.
+    fn chapter(&self, item: &BinderItem, volume: Option<&str>) -> ImportedChapter {
+        let mut parts = vec![self.read_text(&item.id)];
+        let mut synopses = vec![self.read_synopsis(&item.id)];
+        for scene in item.children.iter().filter(|c| c.include) {
+            parts.push(self.read_text(&scene.id));
+            if synopses[0].is_empty() {
+                synopses.push(self.read_synopsis(&scene.id));
+            }
+        }
+        parts.retain(|p| !p.trim().is_empty());
+        synopses.retain(|s| !s.is_empty());
+        ImportedChapter {
+            title: item.title.clone(),
+            volume: volume.map(|v| v.to_string()),
+            content: parts.join(SCENE_SEPARATOR),
+            summary: synopses.join("\n"),
+        }
+    }
+
+    // Folders that contain other folders are volumes; the nearest one names the chapters below it.
+    fn collect(&self, items: &[BinderItem], volume: Option<&str>, out: &mut Vec<ImportedChapter>) {
+        for item in items.iter().filter(|i| i.include) {
+            if item.children.iter().all(|c| c.is_leaf()) {
+                out.push(self.chapter(item, volume));
+                continue;
+            }
+            let own = self.read_text(&item.id);
+            if !own.trim().is_empty() {
+                out.push(ImportedChapter {
+                    title: item.title.clone(),
+                    volume: Some(item.title.clone()),
+                    content: own,
+                    summary: self.read_synopsis(&item.id),
+                });
+            }
+            self.collect(&item.children, Some(&item.title), out);
+        }
+    }
+}
+
+fn read_project(path: &str) -> Result<Vec<ImportedChapter>, AppError> {
+    let (bundle, scrivx) = locate(Path::new(path))?;
+    let xml = fs::read_to_string(&scrivx).map_err(|e| AppError::io("import.readScrivx", &scrivx, e))?;
+    let items = parse_binder(&xml)?;
+    let mut chapters = vec![];
+    Reader { bundle }.collect(&items, None, &mut chapters);
+    Ok(chapters)
+}
+
+pub fn preview_scrivener(path: &str) -> Result<ImportPreview, AppError> {
+    Ok(preview(&read_project(path)?, None))
+}
+
+pub fn import_scrivener(project_dir: String, path: &str) -> Result<Vec<ChapterIndexItem>, AppError> {
+    write_chapters(&project_dir, read_project(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIVX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ScrivenerProject Version="2.0">
+<Binder>
+  <BinderItem UUID="D" Type="DraftFolder"><Title>Draft</Title><Children>
+    <BinderItem UUID="V1" Type="Folder"><Title>第一卷</Title><Children>
+      <BinderItem UUID="C1" Type="Folder"><Title>第一章</Title><Children>
+        <BinderItem UUID="S1" Type="Text"><Title>场景一</Title></BinderItem>
+        <BinderItem UUID="S2" Type="Text"><Title>场景二</Title></BinderItem>
+      </Children></BinderItem>
+      <BinderItem UUID="C2" Type="Text"><Title>第二章</Title></BinderItem>
+      <BinderItem UUID="X" Type="Text"><Title>废稿</Title><MetaData><IncludeInCompile>No</IncludeInCompile></MetaData></BinderItem>
+    </Children></BinderItem>
+  </Children></BinderItem>
+  <BinderItem UUID="R" Type="ResearchFolder"><Title>Research</Title></BinderItem>
+</Binder>
+</ScrivenerProject>"#;
+
+    fn write_doc(bundle: &Path, id: &str, rtf: &str, synopsis: Option<&str>) {
+        let dir = bundle.join("Files").join("Data").join(id);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("content.rtf"), rtf).unwrap();
+        if let Some(s) = synopsis {
+            fs::write(dir.join("synopsis.txt"), s).unwrap();
+        }
+    }
+
+    #[test]
+    fn maps_binder_to_volumes_and_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("Novel.scriv");
+        fs::create_dir_all(&bundle).unwrap();
+        fs::write(bundle.join("Novel.scrivx"), SCRIVX).unwrap();
+        write_doc(&bundle, "S1", r"{\rtf1\ansi \u24320?\u22987?\par}", Some("开场"));
+        write_doc(&bundle, "S2", r"{\rtf1\ansi second\par}", None);
+        write_doc(&bundle, "C2", r"{\rtf1\ansi two\par}", None);
+        write_doc(&bundle, "X", r"{\rtf1\ansi cut\par}", None);
+
+        let chapters = read_project(&bundle.to_string_lossy()).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "第一章");
+        assert_eq!(chapters[0].volume.as_deref(), Some("第一卷"));
+        assert_eq!(chapters[0].content, "开始\n\n* * *\n\nsecond");
+        assert_eq!(chapters[0].summary, "开场");
+        assert_eq!(chapters[1].content, "two");
+    }
+}
@@ -0,0 +1,221 @@
+use super::{preview, write_chapters, ImportedChapter};
+use crate::error::{AppError, ErrorKind};
+use crate::{i18n, storage, types::*};
+use encoding_rs::{Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+const CN_NUM: &str = "0-9０-９零〇一二两三四五六七八九十百千万";
+
+// Built-in heading patterns, used when the caller does not configure any.
+fn default_chapter_patterns() -> Vec<String> {
+    vec![
+        format!(r"^第\s*[{CN_NUM}]+\s*[章回节](?:[\s:：、.．].*)?$"),
+        r"^(?i:chapter)\s+(?:\d+|[ivxlcdm]+)\b.*$".to_string(),
+        r"^#{1,6}\s+\S.*$".to_string(),
+        r"^(?:序章|楔子|引子|序言|前言|尾声|后记|番外)(?:[\s:：、].*)?$".to_string(),
+    ]
+}
+
+fn default_volume_patterns() -> Vec<String> {
+    vec![format!(r"^第\s*[{CN_NUM}]+\s*[卷部集](?:[\s:：、.．].*)?$")]
+}
+
+// Anything longer is prose that happens to start like a heading.
+const MAX_HEADING_CHARS: usize = 50;
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>, AppError> {
+    patterns
+        .iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            Regex::new(p).map_err(|e| {
+                AppError::new(ErrorKind::Invalid, "import.badPattern")
+                    .with_param("pattern", p)
+                    .caused_by(e)
+            })
+        })
+        .collect()
+}
+
+// BOM first, then strict UTF-8, then GB18030 (a superset of GBK/GB2312).
+pub fn decode(bytes: &[u8], label: Option<&str>) -> Result<(String, &'static Encoding), AppError> {
+    if let Some(label) = label.map(str::trim).filter(|l| !l.is_empty()) {
+        let enc = Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| AppError::new(ErrorKind::Invalid, "import.unknownEncoding").with_param("encoding", label))?;
+        let (text, _, had_errors) = enc.decode(bytes);
+        if had_errors {
+            return Err(AppError::new(ErrorKind::Invalid, "import.decode").with_param("encoding", enc.name()));
+        }
+        return Ok((text.into_owned(), enc));
+    }
+
+    if let Some((enc, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = enc.decode_without_bom_handling(&bytes[bom_len..]);
+        return Ok((text.into_owned(), enc));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok((text.to_string(), UTF_8));
+    }
+    if let Some(text) = GB18030.decode_without_bom_handling_and_without_replacement(bytes) {
+        return Ok((text.into_owned(), GB18030));
+    }
+    // UTF-16 without BOM is rare but shows up in files saved by old Windows editors
+    for enc in [UTF_16LE, UTF_16BE] {
+        if let Some(text) = enc.decode_without_bom_handling_and_without_replacement(bytes) {
+            if !text.contains('\u{0}') {
+                return Ok((text.into_owned(), enc));
+            }
+        }
+    }
+    Err(AppError::new(ErrorKind::Invalid, "import.detectEncoding"))
+}
+
+fn heading_title(line: &str) -> String {
+    line.trim_start_matches('#').trim().to_string()
+}
+
+// Untitled text before the first heading gets its title in `locale`.
+pub fn split_chapters(
+    text: &str,
+    chapter_patterns: &[Regex],
+    volume_patterns: &[Regex],
+    locale: Locale,
+) -> Vec<ImportedChapter> {
+    let mut chapters: Vec<ImportedChapter> = vec![];
+    let mut volume: Option<String> = None;
+    let mut preface: Vec<&str> = vec![];
+    let mut body: Vec<&str> = vec![];
+    let mut current: Option<(String, Option<String>)> = None;
+
+    let flush = |chapters: &mut Vec<ImportedChapter>, title: String, volume: Option<String>, body: &mut Vec<&str>| {
+        chapters.push(ImportedChapter {
+            title,
+            volume,
+            content: body.join("\n").trim_matches('\n').to_string(),
+            summary: String::new(),
+        });
+        body.clear();
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_matches(|c: char| c.is_whitespace() || c == '\u{3000}');
+        let is_heading_candidate = !trimmed.is_empty() && trimmed.chars().count() <= MAX_HEADING_CHARS;
+
+        if is_heading_candidate && volume_patterns.iter().any(|re| re.is_match(trimmed)) {
+            if let Some((title, vol)) = current.take() {
+                flush(&mut chapters, title, vol, &mut body);
+            }
+            volume = Some(heading_title(trimmed));
+            continue;
+        }
+        if is_heading_candidate && chapter_patterns.iter().any(|re| re.is_match(trimmed)) {
+            if let Some((title, vol)) = current.take() {
+                flush(&mut chapters, title, vol, &mut body);
+            }
+            current = Some((heading_title(trimmed), volume.clone()));
+            continue;
+        }
+        match current {
+            Some(_) => body.push(line.trim_end()),
+            None => preface.push(line.trim_end()),
+        }
+    }
+    if let Some((title, vol)) = current.take() {
+        flush(&mut chapters, title, vol, &mut body);
+    }
+
+    // Text before the first heading (synopsis, author's note...) becomes its own chapter
+    let preface = preface.join("\n").trim_matches('\n').to_string();
+    if !preface.trim().is_empty() {
+        let title = if chapters.is_empty() {
+            "import.body"
+        } else {
+            "import.preface"
+        };
+        chapters.insert(
+            0,
+            ImportedChapter {
+                title: i18n::text(locale, title).to_string(),
+                volume: None,
+                content: preface,
+                summary: String::new(),
+            },
+        );
+    }
+    chapters
+}
+
+fn read_and_split(
+    options: &ManuscriptImportOptions,
+    locale: Locale,
+) -> Result<(Vec<ImportedChapter>, &'static Encoding), AppError> {
+    let bytes =
+        fs::read(&options.file_path).map_err(|e| AppError::io("import.readFile", Path::new(&options.file_path), e))?;
+    let (text, enc) = decode(&bytes, options.encoding.as_deref())?;
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+    let chapter_patterns = match &options.heading_patterns {
+        Some(p) if !p.is_empty() => compile(p)?,
+        _ => compile(&default_chapter_patterns())?,
+    };
+    let volume_patterns = match &options.volume_patterns {
+        Some(p) => compile(p)?,
+        None => compile(&default_volume_patterns())?,
+    };
+    Ok((split_chapters(&text, &chapter_patterns, &volume_patterns, locale), enc))
+}
+
+pub fn preview_manuscript(options: &ManuscriptImportOptions) -> Result<ImportPreview, AppError> {
+    let (chapters, enc) = read_and_split(options, i18n::locale())?;
+    Ok(preview(&chapters, Some(enc.name().to_string())))
+}
+
+pub fn import_manuscript(
+    project_dir: String,
+    options: &ManuscriptImportOptions,
+) -> Result<Vec<ChapterIndexItem>, AppError> {
+    let locale = storage::project_locale(Path::new(&project_dir));
+    let (chapters, _) = read_and_split(options, locale)?;
+    write_chapters(&project_dir, chapters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_detects_gbk() {
+        let (bytes, _, _) = GB18030.encode("第一章 开端\n正文");
+        let (text, enc) = decode(&bytes, None).unwrap();
+        assert_eq!(enc, GB18030);
+        assert_eq!(text, "第一章 开端\n正文");
+        assert_eq!(decode("hello".as_bytes(), None).unwrap().1, UTF_8);
+    }
+
+    #[test]
+    fn split_on_default_patterns() {
+        let text = "作品简介\n\n第一卷 风起\n第一章 开端\n　　他来了。\n第二章：相遇\n　　她也来了。\n第十二回\n第三章节的内容很长很长很长很长很长很长很长很长很长很长很长很长很长很长很长很长很长很长很长\nChapter 13 The End\nfin\n";
+        let chapters = split_chapters(
+            text,
+            &compile(&default_chapter_patterns()).unwrap(),
+            &compile(&default_volume_patterns()).unwrap(),
+            Locale::ZhCn,
+        );
+        let titles = chapters.iter().map(|c| c.title.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec!["序", "第一章 开端", "第二章：相遇", "第十二回", "Chapter 13 The End"]
+        );
+        assert_eq!(chapters[0].content, "作品简介");
+        assert_eq!(chapters[1].volume.as_deref(), Some("第一卷 风起"));
+        assert_eq!(chapters[1].content, "　　他来了。");
+        assert!(chapters[3].content.starts_with("第三章节"));
+
+        let md = "# 开篇\n内容\n## 第二节\n更多内容";
+        let chapters = split_chapters(md, &compile(&default_chapter_patterns()).unwrap(), &[], Locale::ZhCn);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "第二节");
+    }
+}
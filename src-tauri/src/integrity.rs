@@ -0,0 +1,494 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{crypto, i18n, lock, storage, store, types::*};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn rel(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn issue(
+    kind: IntegrityIssueKind,
+    root: &Path,
+    path: &Path,
+    chapter_id: Option<u32>,
+    detail: String,
+) -> IntegrityIssue {
+    IntegrityIssue {
+        kind,
+        path: rel(root, path),
+        chapter_id,
+        detail,
+    }
+}
+
+// Ok(None) when the file does not exist, Err with the parse error when it is malformed.
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, AppError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let raw = fs::read(path).map_err(|e| AppError::read(&name, e))?;
+    let raw = crypto::decode(path, raw)?;
+    serde_json::from_slice(&raw)
+        .map(Some)
+        .map_err(|e| AppError::malformed(&name, e))
+}
+
+fn temp_files(root: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, skip: &[PathBuf], out: &mut Vec<PathBuf>) {
+        for path in fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()) {
+            if skip.contains(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, skip, out);
+            } else if path.extension().is_some_and(|e| e == "tmp") {
+                out.push(path);
+            }
+        }
+    }
+    let skip = [root.join(".git"), storage::backups_dir(root)];
+    let mut out = vec![];
+    walk(root, &skip, &mut out);
+    out.sort();
+    out
+}
+
+// chapters/chapter_NNN.txt and chapter_NNN.json, keyed by id
+struct ChapterFiles {
+    texts: BTreeMap<u32, PathBuf>,
+    metas: BTreeMap<u32, PathBuf>,
+}
+
+fn chapter_files(root: &Path) -> ChapterFiles {
+    let mut files = ChapterFiles {
+        texts: BTreeMap::new(),
+        metas: BTreeMap::new(),
+    };
+    let entries = fs::read_dir(storage::chapters_dir(root))
+        .into_iter()
+        .flatten()
+        .flatten();
+    for path in entries.map(|e| e.path()) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let Some(rest) = name.strip_prefix("chapter_") else {
+            continue;
+        };
+        let (id, ext) = rest.split_once('.').unwrap_or((rest, ""));
+        let Ok(id) = id.parse::<u32>() else { continue };
+        match ext {
+            "txt" => files.texts.insert(id, path),
+            "json" => files.metas.insert(id, path),
+            _ => None,
+        };
+    }
+    files
+}
+
+// session_<id>.json files, keyed by the id in the file name
+fn session_files(root: &Path) -> BTreeMap<String, PathBuf> {
+    fs::read_dir(storage::chat_sessions_dir(root))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            let id = name.strip_prefix("session_")?.strip_suffix(".json")?.to_string();
+            Some((id, path))
+        })
+        .collect()
+}
+
+pub fn verify_project(project_dir: String) -> Result<IntegrityReport, AppError> {
+    use IntegrityIssueKind::*;
+    let root = PathBuf::from(&project_dir);
+    if !root.is_dir() {
+        return Err(AppError::new(ErrorKind::NotFound, "storage.projectMissing"));
+    }
+    // ciphertext would otherwise be reported (and repaired) as malformed JSON
+    crypto::ensure_unlocked(&root)?;
+    let mut issues = vec![];
+
+    for tmp in temp_files(&root) {
+        issues.push(issue(
+            LeftoverTemp,
+            &root,
+            &tmp,
+            None,
+            i18n::t("integrity.leftoverTemp").to_string(),
+        ));
+    }
+
+    let root_files = [
+        (
+            storage::config_file(&root),
+            read_json::<Preset>(&storage::config_file(&root)).err(),
+        ),
+        (
+            storage::llm_config_file(&root),
+            read_json::<LlmConfig>(&storage::llm_config_file(&root)).err(),
+        ),
+        (
+            storage::prompts_file(&root),
+            read_json::<BTreeMap<PromptTemplateName, String>>(&storage::prompts_file(&root)).err(),
+        ),
+        (
+            storage::summaries_file(&root),
+            read_json::<Vec<SummaryRecord>>(&storage::summaries_file(&root)).err(),
+        ),
+        (
+            storage::manifest_file(&root),
+            read_json::<ProjectManifest>(&storage::manifest_file(&root)).err(),
+        ),
+    ];
+    for (path, error) in root_files {
+        if let Some(e) = error {
+            issues.push(issue(MalformedJson, &root, &path, None, e.to_string()));
+        }
+    }
+
+    match storage::project_backend(&root) {
+        StorageBackend::Files => verify_layout(&root, &mut issues),
+        StorageBackend::Sqlite => verify_database(&root, &mut issues),
+    }
+    Ok(IntegrityReport { issues })
+}
+
+// project.db replaces the chapter and session files, so only SQLite's own check applies.
+fn verify_database(root: &Path, issues: &mut Vec<IntegrityIssue>) {
+    let db = store::db_file(root);
+    let problems = if !db.exists() {
+        vec![i18n::t("integrity.databaseMissing").to_string()]
+    } else {
+        store::SqliteStore::integrity_check(root)
+            .unwrap_or_else(|e| vec![format!("{}: {e}", i18n::t("integrity.databaseUnreadable"))])
+    };
+    for detail in problems {
+        issues.push(issue(IntegrityIssueKind::DatabaseCorrupt, root, &db, None, detail));
+    }
+}
+
+// The chapter and chat session files of a project kept in files.
+fn verify_layout(root: &Path, issues: &mut Vec<IntegrityIssue>) {
+    use IntegrityIssueKind::*;
+
+    // chapters
+    let index_path = storage::chapters_index_file(root);
+    let index = match read_json::<Vec<ChapterIndexItem>>(&index_path) {
+        Ok(index) => index.unwrap_or_default(),
+        Err(e) => {
+            issues.push(issue(MalformedJson, root, &index_path, None, e.to_string()));
+            vec![]
+        }
+    };
+    let mut listed = HashSet::new();
+    for item in &index {
+        if !listed.insert(item.id) {
+            let detail = i18n::tf("integrity.duplicateChapter", &[("id", &item.id)]);
+            issues.push(issue(DuplicateId, root, &index_path, Some(item.id), detail));
+        }
+    }
+    let files = chapter_files(root);
+    for item in &index {
+        if !files.texts.contains_key(&item.id) {
+            let path = storage::chapter_txt(root, item.id);
+            let detail = i18n::tf("integrity.missingText", &[("title", &item.title)]);
+            issues.push(issue(MissingText, root, &path, Some(item.id), detail));
+        } else if !files.metas.contains_key(&item.id) {
+            let path = storage::chapter_meta(root, item.id);
+            let detail = i18n::tf("integrity.missingMeta", &[("title", &item.title)]);
+            issues.push(issue(MissingMeta, root, &path, Some(item.id), detail));
+        }
+    }
+    for (id, path) in files.texts.iter().chain(files.metas.iter()) {
+        if !listed.contains(id) {
+            issues.push(issue(
+                OrphanedFile,
+                root,
+                path,
+                Some(*id),
+                i18n::t("integrity.orphanedChapterFile").to_string(),
+            ));
+        }
+    }
+    for (id, path) in &files.metas {
+        if let Err(e) = read_json::<serde_json::Map<String, serde_json::Value>>(path) {
+            issues.push(issue(MalformedJson, root, path, Some(*id), e.to_string()));
+        }
+    }
+
+    // chat sessions
+    let sessions_path = storage::sessions_index_file(root);
+    let sessions = match read_json::<Vec<ChatSessionIndexItem>>(&sessions_path) {
+        Ok(sessions) => sessions.unwrap_or_default(),
+        Err(e) => {
+            issues.push(issue(MalformedJson, root, &sessions_path, None, e.to_string()));
+            vec![]
+        }
+    };
+    let session_paths = session_files(root);
+    let mut listed = HashSet::new();
+    for s in &sessions {
+        if !listed.insert(s.id.as_str()) {
+            let detail = i18n::tf("integrity.duplicateSession", &[("title", &s.title)]);
+            issues.push(issue(DuplicateId, root, &sessions_path, None, detail));
+        } else if !session_paths.contains_key(&s.id) {
+            let path = storage::session_file(root, &s.id);
+            let detail = i18n::tf("integrity.missingSession", &[("title", &s.title)]);
+            issues.push(issue(MissingSession, root, &path, None, detail));
+        }
+    }
+    for (id, path) in &session_paths {
+        if let Err(e) = read_json::<ChatSession>(path) {
+            issues.push(issue(MalformedJson, root, path, None, e.to_string()));
+        } else if !listed.contains(id.as_str()) {
+            issues.push(issue(
+                OrphanedFile,
+                root,
+                path,
+                None,
+                i18n::t("integrity.orphanedSessionFile").to_string(),
+            ));
+        }
+    }
+}
+
+fn rebuild_chapter_index(root: &Path, changes: &mut Vec<String>) -> Result<(), AppError> {
+    let mut index = storage::list_chapters(root.to_string_lossy().to_string())?;
+    let mut listed = HashSet::new();
+    index.retain(|item| {
+        let first = listed.insert(item.id);
+        if !first {
+            changes.push(i18n::tf("repair.removedDuplicateChapter", &[("id", &item.id)]));
+        }
+        first
+    });
+
+    let files = chapter_files(root);
+    for item in &index {
+        if !files.texts.contains_key(&item.id) {
+            // through storage so the placeholder is encrypted like any other chapter
+            let txt = storage::chapter_txt(root, item.id);
+            lock::forget(&txt);
+            storage::write_tracked(&txt, b"")?;
+            changes.push(i18n::tf("repair.createdText", &[("title", &item.title)]));
+        }
+        if !files.metas.contains_key(&item.id) {
+            let meta = json!({ "id": item.id, "title": item.title, "summary": "" });
+            let meta_path = storage::chapter_meta(root, item.id);
+            lock::forget(&meta_path);
+            storage::atomic_write_json(&meta_path, &meta)?;
+            changes.push(i18n::tf("repair.createdMeta", &[("title", &item.title)]));
+        }
+    }
+
+    // text files written outside the app are adopted, stray metadata without text is dropped
+    let locale = storage::project_locale(root);
+    for (id, path) in &files.texts {
+        if listed.contains(id) {
+            continue;
+        }
+        let meta_path = storage::chapter_meta(root, *id);
+        let title = read_json::<serde_json::Value>(&meta_path)
+            .ok()
+            .flatten()
+            .and_then(|v| v.get("title")?.as_str().map(|t| t.to_string()))
+            .unwrap_or_else(|| i18n::text_with(locale, "chapter.numbered", &[("id", id)]));
+        if !meta_path.exists() {
+            storage::atomic_write_json(&meta_path, &json!({ "id": id, "title": title, "summary": "" }))?;
+        }
+        changes.push(i18n::tf("repair.adoptedChapter", &[("path", &rel(root, path))]));
+        index.push(ChapterIndexItem {
+            id: *id,
+            title,
+            volume: None,
+        });
+    }
+    for (id, path) in &files.metas {
+        if !listed.contains(id) && !files.texts.contains_key(id) {
+            fs::remove_file(path).map_err(|e| AppError::io("io.remove", path, e))?;
+            lock::remember_removed(path);
+            changes.push(i18n::tf("repair.removedMeta", &[("path", &rel(root, path))]));
+        }
+    }
+
+    storage::atomic_write_json(&storage::chapters_index_file(root), &index)
+}
+
+fn rebuild_session_index(root: &Path, changes: &mut Vec<String>) -> Result<(), AppError> {
+    if !storage::chat_sessions_dir(root).exists() {
+        return Ok(());
+    }
+    let files = session_files(root);
+    let mut sessions = storage::list_chat_sessions(root.to_string_lossy().to_string())?;
+    let mut listed = HashSet::new();
+    sessions.retain(|s| {
+        if !listed.insert(s.id.clone()) {
+            changes.push(i18n::tf("repair.removedDuplicateSession", &[("title", &s.title)]));
+            return false;
+        }
+        if !files.contains_key(&s.id) {
+            changes.push(i18n::tf("repair.removedMissingSession", &[("title", &s.title)]));
+            return false;
+        }
+        true
+    });
+    for (id, path) in &files {
+        if listed.contains(id) {
+            continue;
+        }
+        if let Ok(Some(session)) = read_json::<ChatSession>(path) {
+            changes.push(i18n::tf("repair.adoptedSession", &[("title", &session.title)]));
+            sessions.push(ChatSessionIndexItem {
+                id: id.clone(),
+                title: session.title,
+            });
+        }
+    }
+    let index_path = storage::sessions_index_file(root);
+    storage::atomic_write_json(&index_path, &sessions)
+}
+
+// Fixes what verify_project reports. Nothing is deleted that could hold writing: malformed
+// files are renamed to *.corrupt, orphaned texts are added back to the index. The whole pass
+// holds the write guard so no save lands between reading the index and writing it back.
+pub fn repair_project(project_dir: String) -> Result<RepairReport, AppError> {
+    let root = PathBuf::from(&project_dir);
+    let _write = lock::write_guard(&root);
+    let report = verify_project(project_dir.clone())?;
+    // a damaged database has no safe automatic fix; restoring a backup is the way back
+    if report
+        .issues
+        .iter()
+        .any(|i| i.kind == IntegrityIssueKind::DatabaseCorrupt)
+    {
+        return Err(AppError::new(ErrorKind::Io, "repair.databaseCorrupt"));
+    }
+    let mut changes = vec![];
+
+    for found in &report.issues {
+        let path = root.join(&found.path);
+        match found.kind {
+            IntegrityIssueKind::LeftoverTemp => {
+                fs::remove_file(&path).map_err(|e| AppError::io("io.remove", &path, e))?;
+                changes.push(i18n::tf("repair.removedTemp", &[("path", &found.path)]));
+            }
+            IntegrityIssueKind::MalformedJson => {
+                let aside = path.with_extension("json.corrupt");
+                fs::rename(&path, &aside).map_err(|e| AppError::io("io.rename", &path, e))?;
+                lock::remember_removed(&path);
+                let aside = rel(&root, &aside);
+                changes.push(i18n::tf(
+                    "repair.movedAside",
+                    &[("path", &found.path), ("aside", &aside)],
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    if storage::project_backend(&root) == StorageBackend::Files {
+        rebuild_chapter_index(&root, &mut changes)?;
+        rebuild_session_index(&root, &mut changes)?;
+    }
+    // recreates default config/summaries for anything that was moved aside
+    storage::init_project(project_dir.clone())?;
+
+    let remaining = verify_project(project_dir)?.issues;
+    Ok(RepairReport { changes, remaining })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_and_repairs_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        storage::create_chapter(root.clone(), "第二章".to_string()).unwrap();
+
+        let mut index = storage::list_chapters(root.clone()).unwrap();
+        index.push(index[0].clone());
+        storage::atomic_write_json(&storage::chapters_index_file(dir.path()), &index).unwrap();
+        fs::remove_file(storage::chapter_txt(dir.path(), 2)).unwrap();
+        fs::write(storage::chapter_txt(dir.path(), 7), "外部编辑").unwrap();
+        fs::write(storage::config_file(dir.path()), "{ broken").unwrap();
+        fs::write(dir.path().join("summaries.json.tmp"), "[]").unwrap();
+
+        let kinds = verify_project(root.clone())
+            .unwrap()
+            .issues
+            .iter()
+            .map(|i| i.kind)
+            .collect::<HashSet<_>>();
+        for kind in [
+            IntegrityIssueKind::DuplicateId,
+            IntegrityIssueKind::MissingText,
+            IntegrityIssueKind::OrphanedFile,
+            IntegrityIssueKind::MalformedJson,
+            IntegrityIssueKind::LeftoverTemp,
+        ] {
+            assert!(kinds.contains(&kind), "{kind:?} not reported");
+        }
+
+        let repaired = repair_project(root.clone()).unwrap();
+        assert!(repaired.remaining.is_empty(), "{:?}", repaired.remaining);
+        let ids = storage::list_chapters(root.clone())
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2, 7]);
+        assert_eq!(storage::load_chapter(root.clone(), 7).unwrap().content, "外部编辑");
+        assert!(dir.path().join("config.json.corrupt").exists());
+    }
+
+    #[test]
+    fn checks_sqlite_projects_with_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        store::convert(root.clone(), StorageBackend::Sqlite).unwrap();
+        assert!(verify_project(root.clone()).unwrap().issues.is_empty());
+        // nothing is rebuilt in the file layout
+        assert!(repair_project(root.clone()).unwrap().changes.is_empty());
+        assert!(!storage::chapters_index_file(dir.path()).exists());
+
+        fs::write(store::db_file(dir.path()), "not a database").unwrap();
+        let issues = verify_project(root.clone()).unwrap().issues;
+        assert_eq!(issues[0].kind, IntegrityIssueKind::DatabaseCorrupt);
+        let refused = repair_project(root).unwrap_err();
+        assert_eq!(refused.key, "repair.databaseCorrupt");
+    }
+
+    #[test]
+    fn repairs_encrypted_project_without_writing_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        crypto::encrypt_project(root.clone(), "correct horse".to_string(), false).unwrap();
+        let txt = storage::chapter_txt(dir.path(), 1);
+        fs::remove_file(&txt).unwrap();
+
+        let report = repair_project(root.clone()).unwrap();
+        assert!(report.remaining.is_empty(), "{:?}", report.remaining);
+        // an empty chapter still gets the encryption header
+        assert!(!fs::read(&txt).unwrap().is_empty());
+        assert_eq!(storage::load_chapter(root, 1).unwrap().content, "");
+    }
+}
@@ -1,161 +1,539 @@
+mod audit;
+mod backup;
+mod crypto;
+mod diagnostics;
+mod durable;
+mod error;
+mod export;
+mod generation;
+mod i18n;
+mod import;
+mod integrity;
 mod llm;
+mod lock;
+mod logging;
 mod prompt;
 mod secure;
 mod state;
 mod storage;
+mod store;
+mod template;
 mod types;
+mod usage;
+mod vcs;
+mod watcher;
 
+// An in-memory project with sample content, for previews built on this crate.
+pub use store::{fixtures, MemoryStore, ProjectStore};
+
+use error::AppError;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::Emitter;
 use types::*;
 
 #[tauri::command]
-fn app_get_state(app: tauri::AppHandle) -> Result<AppState, String> {
+#[tracing::instrument(skip_all, err)]
+fn app_get_state(app: tauri::AppHandle) -> Result<AppState, AppError> {
     state::load_app_state(&app)
 }
 
 #[tauri::command]
-fn app_set_state(app: tauri::AppHandle, next: AppState) -> Result<(), String> {
+#[tracing::instrument(skip_all, err)]
+fn app_set_state(app: tauri::AppHandle, next: AppState) -> Result<(), AppError> {
     state::save_app_state(&app, &next)
 }
 
 #[tauri::command]
-fn app_get_default_project_dir(app: tauri::AppHandle) -> Result<String, String> {
+#[tracing::instrument(skip_all, err)]
+fn app_get_default_project_dir(app: tauri::AppHandle) -> Result<String, AppError> {
     state::default_project_dir(&app)
 }
 
 #[tauri::command]
-fn storage_init_project(project_dir: String) -> Result<ProjectInfo, String> {
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_init_project(project_dir: String) -> Result<ProjectInfo, AppError> {
     storage::init_project(project_dir)
 }
 
 #[tauri::command]
-fn storage_list_chapters(project_dir: String) -> Result<Vec<ChapterIndexItem>, String> {
-    storage::list_chapters(project_dir)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_acquire_lock(project_dir: String, force: bool) -> Result<ProjectLockStatus, AppError> {
+    lock::acquire(project_dir, force)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_release_lock(project_dir: String) -> Result<(), AppError> {
+    lock::release(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_watch(app: tauri::AppHandle, project_dir: String) -> Result<(), AppError> {
+    watcher::watch(project_dir, move |changes| {
+        let _ = app.emit(watcher::EVENT, changes);
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir))]
+fn project_unwatch(project_dir: String) {
+    watcher::unwatch(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_list_chapters(project_dir: String) -> Result<Vec<ChapterIndexItem>, AppError> {
+    store::open(&project_dir)?.list_chapters()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_create_chapter(project_dir: String, title: String) -> Result<ChapterIndexItem, AppError> {
+    store::open(&project_dir)?.create_chapter(title)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id), err)]
+fn storage_rename_chapter(project_dir: String, id: u32, title: String) -> Result<(), AppError> {
+    store::open(&project_dir)?.rename_chapter(id, title)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id), err)]
+fn storage_set_chapter_volume(project_dir: String, id: u32, volume: Option<String>) -> Result<(), AppError> {
+    store::open(&project_dir)?.set_chapter_volume(id, volume)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id), err)]
+fn storage_delete_chapter(project_dir: String, id: u32) -> Result<(), AppError> {
+    store::open(&project_dir)?.delete_chapter(id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id), err)]
+fn storage_load_chapter(project_dir: String, id: u32) -> Result<Chapter, AppError> {
+    store::open(&project_dir)?.load_chapter(id)
 }
 
 #[tauri::command]
-fn storage_create_chapter(project_dir: String, title: String) -> Result<ChapterIndexItem, String> {
-    storage::create_chapter(project_dir, title)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_save_chapter(project_dir: String, chapter: Chapter, force: Option<bool>) -> Result<(), AppError> {
+    let store = store::open(&project_dir)?;
+    if force.unwrap_or(false) {
+        store.save_chapter_overwriting(&chapter)?;
+    } else {
+        store.save_chapter(&chapter)?;
+    }
+    vcs::note_chapter_saved(&project_dir, &chapter);
+    Ok(())
 }
 
 #[tauri::command]
-fn storage_rename_chapter(project_dir: String, id: u32, title: String) -> Result<(), String> {
-    storage::rename_chapter(project_dir, id, title)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_verify_project(project_dir: String) -> Result<IntegrityReport, AppError> {
+    integrity::verify_project(project_dir)
 }
 
 #[tauri::command]
-fn storage_delete_chapter(project_dir: String, id: u32) -> Result<(), String> {
-    storage::delete_chapter(project_dir, id)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_repair_project(project_dir: String) -> Result<RepairReport, AppError> {
+    integrity::repair_project(project_dir)
 }
 
 #[tauri::command]
-fn storage_load_chapter(project_dir: String, id: u32) -> Result<Chapter, String> {
-    storage::load_chapter(project_dir, id)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_search(project_dir: String, query: String, limit: Option<usize>) -> Result<Vec<SearchHit>, AppError> {
+    store::open(&project_dir)?.search(&query, limit.unwrap_or(50))
 }
 
 #[tauri::command]
-fn storage_save_chapter(project_dir: String, chapter: Chapter) -> Result<(), String> {
-    storage::save_chapter(project_dir, &chapter)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir))]
+fn storage_get_backend(project_dir: String) -> StorageBackend {
+    store::backend(project_dir)
 }
 
 #[tauri::command]
-fn storage_load_summaries(project_dir: String) -> Result<Vec<SummaryRecord>, String> {
-    storage::load_summaries(project_dir)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_convert_backend(project_dir: String, backend: StorageBackend) -> Result<(), AppError> {
+    store::convert(project_dir, backend)
 }
 
 #[tauri::command]
-fn storage_append_summary(project_dir: String, record: SummaryRecord) -> Result<(), String> {
-    storage::append_summary(project_dir, record)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_load_summaries(project_dir: String) -> Result<Vec<SummaryRecord>, AppError> {
+    store::open(&project_dir)?.load_summaries()
 }
 
 #[tauri::command]
-fn storage_load_preset(project_dir: String) -> Result<Preset, String> {
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_append_summary(project_dir: String, record: SummaryRecord) -> Result<(), AppError> {
+    store::open(&project_dir)?.append_summary(record)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_load_preset(project_dir: String) -> Result<Preset, AppError> {
     storage::load_preset(project_dir)
 }
 
 #[tauri::command]
-fn storage_save_preset(project_dir: String, preset: Preset) -> Result<(), String> {
-    storage::save_preset(project_dir, &preset)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_save_preset(project_dir: String, preset: Preset) -> Result<(), AppError> {
+    storage::save_preset(project_dir.clone(), &preset)?;
+    // versioning is best-effort and never fails the save itself
+    if let Err(e) = vcs::preset_changed(&project_dir) {
+        tracing::warn!(error = %e, "versioning commit failed");
+    }
+    Ok(())
 }
 
 #[tauri::command]
-fn preset_export(file_path: String, preset: Preset) -> Result<(), String> {
+#[tracing::instrument(skip_all, err)]
+fn preset_export(file_path: String, preset: Preset) -> Result<(), AppError> {
     storage::export_preset(file_path, &preset)
 }
 
 #[tauri::command]
-fn preset_import(file_path: String) -> Result<Preset, String> {
+#[tracing::instrument(skip_all, err)]
+fn preset_import(file_path: String) -> Result<Preset, AppError> {
     storage::import_preset(file_path)
 }
 
 #[tauri::command]
-fn storage_load_llm_config(project_dir: String) -> Result<LlmConfig, String> {
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn export_epub(project_dir: String, options: EpubExportOptions) -> Result<(), AppError> {
+    export::export_epub(project_dir, &options)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn export_docx(project_dir: String, options: DocxExportOptions) -> Result<(), AppError> {
+    export::export_docx(project_dir, &options)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn export_markdown(project_dir: String, options: MarkdownExportOptions) -> Result<(), AppError> {
+    export::export_markdown(project_dir, &options)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn import_preview_manuscript(options: ManuscriptImportOptions) -> Result<ImportPreview, AppError> {
+    import::preview_manuscript(&options)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn import_manuscript(project_dir: String, options: ManuscriptImportOptions) -> Result<Vec<ChapterIndexItem>, AppError> {
+    import::import_manuscript(project_dir, &options)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn import_preview_scrivener(path: String) -> Result<ImportPreview, AppError> {
+    import::preview_scrivener(&path)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn import_scrivener(project_dir: String, path: String) -> Result<Vec<ChapterIndexItem>, AppError> {
+    import::import_scrivener(project_dir, &path)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn import_preview_folder(dir_path: String) -> Result<ImportPreview, AppError> {
+    import::preview_folder(&dir_path)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn import_folder(project_dir: String, dir_path: String) -> Result<Vec<ChapterIndexItem>, AppError> {
+    import::import_folder(project_dir, &dir_path)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_backup(project_dir: String, output_path: Option<String>) -> Result<BackupInfo, AppError> {
+    backup::backup_project(project_dir, output_path)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_list_backups(project_dir: String) -> Result<Vec<BackupInfo>, AppError> {
+    backup::list_backups(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn project_restore(archive_path: String, target_dir: String) -> Result<ProjectInfo, AppError> {
+    backup::restore_project(archive_path, target_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_load_backup_settings(project_dir: String) -> Result<BackupSettings, AppError> {
+    storage::load_backup_settings(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn project_save_backup_settings(project_dir: String, settings: BackupSettings) -> Result<(), AppError> {
+    storage::save_backup_settings(project_dir, &settings)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn vcs_load_settings(project_dir: String) -> Result<VersioningSettings, AppError> {
+    storage::load_versioning_settings(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn vcs_save_settings(project_dir: String, settings: VersioningSettings) -> Result<(), AppError> {
+    vcs::save_settings(project_dir, &settings)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, chapter_id), err)]
+fn vcs_record_ai_accepted(project_dir: String, chapter_id: u32) -> Result<Option<VcsCommit>, AppError> {
+    vcs::ai_accepted(&project_dir, chapter_id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn vcs_history(project_dir: String, limit: Option<usize>) -> Result<Vec<VcsCommit>, AppError> {
+    vcs::history(project_dir, limit.unwrap_or(100))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn vcs_checkout(project_dir: String, commit_id: String, branch_name: String) -> Result<(), AppError> {
+    vcs::checkout(project_dir, commit_id, branch_name)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn crypto_status(project_dir: String) -> Result<EncryptionStatus, AppError> {
+    crypto::status(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn crypto_unlock(project_dir: String, passphrase: String, remember: bool) -> Result<(), AppError> {
+    crypto::unlock(project_dir, passphrase, remember)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn crypto_lock(project_dir: String) -> Result<(), AppError> {
+    crypto::lock_project(project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn crypto_encrypt_project(
+    project_dir: String,
+    passphrase: String,
+    remember: bool,
+) -> Result<EncryptionReport, AppError> {
+    crypto::encrypt_project(project_dir, passphrase, remember)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn crypto_decrypt_project(project_dir: String, passphrase: String) -> Result<(), AppError> {
+    crypto::decrypt_project(project_dir, passphrase)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_load_llm_config(project_dir: String) -> Result<LlmConfig, AppError> {
     storage::load_llm_config(project_dir)
 }
 
 #[tauri::command]
-fn storage_save_llm_config(project_dir: String, config: LlmConfig) -> Result<(), String> {
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn storage_save_llm_config(project_dir: String, config: LlmConfig) -> Result<(), AppError> {
     storage::save_llm_config(project_dir, &config)
 }
 
 #[tauri::command]
-fn chat_list_sessions(project_dir: String) -> Result<Vec<ChatSessionIndexItem>, String> {
-    storage::list_chat_sessions(project_dir)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn chat_list_sessions(project_dir: String) -> Result<Vec<ChatSessionIndexItem>, AppError> {
+    store::open(&project_dir)?.list_chat_sessions()
 }
 
 #[tauri::command]
-fn chat_create_session(project_dir: String, title: Option<String>) -> Result<ChatSessionIndexItem, String> {
-    storage::create_chat_session(project_dir, title)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn chat_create_session(project_dir: String, title: Option<String>) -> Result<ChatSessionIndexItem, AppError> {
+    store::open(&project_dir)?.create_chat_session(title)
 }
 
 #[tauri::command]
-fn chat_load_session(project_dir: String, session_id: String) -> Result<ChatSession, String> {
-    storage::load_chat_session(project_dir, session_id)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, session_id = %session_id), err)]
+fn chat_load_session(project_dir: String, session_id: String) -> Result<ChatSession, AppError> {
+    store::open(&project_dir)?.load_chat_session(&session_id)
 }
 
 #[tauri::command]
-fn chat_save_session(project_dir: String, session: ChatSession) -> Result<(), String> {
-    storage::save_chat_session(project_dir, &session)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn chat_save_session(project_dir: String, session: ChatSession) -> Result<(), AppError> {
+    store::open(&project_dir)?.save_chat_session(&session)
 }
 
 #[tauri::command]
-fn chat_delete_session(project_dir: String, session_id: String) -> Result<(), String> {
-    storage::delete_chat_session(project_dir, session_id)
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, session_id = %session_id), err)]
+fn chat_delete_session(project_dir: String, session_id: String) -> Result<(), AppError> {
+    store::open(&project_dir)?.delete_chat_session(&session_id)
 }
 
 #[tauri::command]
-fn secure_has_api_key(endpoint_id: String) -> Result<bool, String> {
+#[tracing::instrument(skip_all, err)]
+fn secure_has_api_key(endpoint_id: String) -> Result<bool, AppError> {
     secure::has_api_key(&endpoint_id)
 }
 
 #[tauri::command]
-fn secure_set_api_key(endpoint_id: String, api_key: String) -> Result<(), String> {
+#[tracing::instrument(skip_all, err)]
+fn secure_set_api_key(endpoint_id: String, api_key: String) -> Result<(), AppError> {
     secure::set_api_key(&endpoint_id, &api_key)
 }
 
 #[tauri::command]
-fn secure_delete_api_key(endpoint_id: String) -> Result<(), String> {
+#[tracing::instrument(skip_all, err)]
+fn secure_delete_api_key(endpoint_id: String) -> Result<(), AppError> {
     secure::delete_api_key(&endpoint_id)
 }
 
 #[tauri::command]
-async fn llm_fetch_models(base_url: String, endpoint_id: String) -> Result<Vec<String>, String> {
+#[tracing::instrument(skip_all, err)]
+async fn llm_fetch_models(base_url: String, endpoint_id: String) -> Result<Vec<String>, AppError> {
     llm::fetch_models(&base_url, &endpoint_id).await
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, chapter_id), err)]
 async fn llm_continue(
     project_dir: String,
     chapter_id: u32,
     instruction: String,
-) -> Result<GenerationResponse, String> {
+) -> Result<GenerationResponse, AppError> {
     llm::continue_chapter(&project_dir, chapter_id, &instruction).await
 }
 
 #[tauri::command]
-async fn llm_discuss(project_dir: String, session_id: String, user_message: String) -> Result<ChatMessage, String> {
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, session_id = %session_id), err)]
+async fn llm_discuss(project_dir: String, session_id: String, user_message: String) -> Result<ChatMessage, AppError> {
     llm::discuss(&project_dir, &session_id, &user_message).await
 }
 
+// Assembles the request the commands above would send, the same way `prompt_preview` does,
+// without sending it.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, task = ?task), err)]
+fn llm_dry_run(
+    project_dir: String,
+    task: LlmTask,
+    chapter_id: Option<u32>,
+    session_id: Option<String>,
+    instruction: String,
+) -> Result<DryRun, AppError> {
+    llm::dry_run(&project_dir, task, chapter_id, session_id.as_deref(), &instruction)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn prompt_templates_list(project_dir: String) -> Result<Vec<PromptTemplate>, AppError> {
+    prompt::list_templates(&project_dir)
+}
+
+// `source: None` goes back to the built-in template.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, name = ?name), err)]
+fn prompt_template_save(
+    project_dir: String,
+    name: PromptTemplateName,
+    source: Option<String>,
+) -> Result<(), AppError> {
+    prompt::save_template(&project_dir, name, source)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, task = ?task), err)]
+fn prompt_preview(
+    project_dir: String,
+    task: LlmTask,
+    chapter_id: Option<u32>,
+    session_id: Option<String>,
+    instruction: String,
+    drafts: BTreeMap<PromptTemplateName, String>,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    llm::preview_prompt(
+        &project_dir,
+        task,
+        chapter_id,
+        session_id.as_deref(),
+        &instruction,
+        drafts,
+    )
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir), err)]
+fn llm_audit_list(project_dir: String) -> Result<Vec<LlmAuditIndexItem>, AppError> {
+    audit::list(&project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id = %id), err)]
+fn llm_audit_get(project_dir: String, id: String) -> Result<LlmAuditEntry, AppError> {
+    audit::load(&project_dir, &id)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(project_dir = %project_dir, id = %id), err)]
+async fn llm_audit_replay(
+    project_dir: String,
+    id: String,
+    endpoint_id: Option<String>,
+    model: Option<String>,
+) -> Result<LlmAuditEntry, AppError> {
+    llm::replay(&project_dir, &id, endpoint_id, model).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn usage_summary(
+    group_by: UsageGroupBy,
+    from: Option<String>,
+    to: Option<String>,
+    project_dir: Option<String>,
+) -> Result<Vec<UsageBucket>, AppError> {
+    usage::summary(group_by, from, to, project_dir)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn usage_budget_status() -> Result<BudgetStatus, AppError> {
+    usage::status()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err)]
+fn diagnostics_export(
+    app: tauri::AppHandle,
+    project_dir: Option<String>,
+    output_path: String,
+) -> Result<(), AppError> {
+    let log_dir = state::log_dir(&app)?;
+    diagnostics::write_bundle(&log_dir, project_dir, std::path::Path::new(&output_path))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -166,18 +544,52 @@ pub fn run() {
             app_set_state,
             app_get_default_project_dir,
             storage_init_project,
+            project_acquire_lock,
+            project_release_lock,
+            project_watch,
+            project_unwatch,
             storage_list_chapters,
             storage_create_chapter,
             storage_rename_chapter,
+            storage_set_chapter_volume,
             storage_delete_chapter,
             storage_load_chapter,
             storage_save_chapter,
+            storage_verify_project,
+            storage_repair_project,
+            storage_search,
+            storage_get_backend,
+            storage_convert_backend,
             storage_load_summaries,
             storage_append_summary,
             storage_load_preset,
             storage_save_preset,
             preset_export,
             preset_import,
+            export_epub,
+            export_docx,
+            export_markdown,
+            import_preview_manuscript,
+            import_manuscript,
+            import_preview_scrivener,
+            import_scrivener,
+            import_preview_folder,
+            import_folder,
+            project_backup,
+            project_list_backups,
+            project_restore,
+            project_load_backup_settings,
+            project_save_backup_settings,
+            vcs_load_settings,
+            vcs_save_settings,
+            vcs_record_ai_accepted,
+            vcs_history,
+            vcs_checkout,
+            crypto_status,
+            crypto_unlock,
+            crypto_lock,
+            crypto_encrypt_project,
+            crypto_decrypt_project,
             storage_load_llm_config,
             storage_save_llm_config,
             chat_list_sessions,
@@ -191,9 +603,46 @@ pub fn run() {
             llm_fetch_models,
             llm_continue,
             llm_discuss,
+            llm_dry_run,
+            prompt_templates_list,
+            prompt_template_save,
+            prompt_preview,
+            llm_audit_list,
+            llm_audit_get,
+            llm_audit_replay,
+            usage_summary,
+            usage_budget_status,
+            diagnostics_export,
         ])
         .setup(|app| {
-            let _ = state::ensure_app_state_file(app.handle());
+            // the app works without a log, so a failure here is not fatal
+            if let Ok(dir) = state::log_dir(app.handle()) {
+                if let Err(e) = logging::init(&dir) {
+                    eprintln!("{e}");
+                }
+            }
+            tracing::info!(version = env!("CARGO_PKG_VERSION"), "app started");
+            if let Ok(file) = state::usage_file(app.handle()) {
+                usage::init(file);
+            }
+            // loading the state also applies its interface language, log settings and budget
+            let _ = state::load_app_state(app.handle());
+
+            // project lock heartbeats, automatic backups for the currently open project,
+            // and commits for chapter saves that have settled for a minute
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(Duration::from_secs(60));
+                lock::refresh_held();
+                vcs::flush_pending(Duration::from_secs(60), |e| {
+                    let _ = handle.emit(vcs::COMMIT_FAILED_EVENT, e);
+                });
+                if let Ok(Some(dir)) = state::load_app_state(&handle).map(|s| s.last_project_dir) {
+                    if let Err(e) = backup::run_scheduled(&dir) {
+                        tracing::warn!(project_dir = %dir, error = %e, "scheduled backup failed");
+                    }
+                }
+            });
             Ok(())
         })
         .run(tauri::generate_context!())
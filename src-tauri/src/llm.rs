@@ -1,21 +1,94 @@
-use crate::{prompt, secure, storage, types::*};
+use crate::error::{AppError, ErrorKind};
+use crate::store::{self, ProjectStore};
+use crate::{audit, generation, i18n, logging, prompt, secure, storage, types::*, usage};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use std::path::Path;
+use std::time::Instant;
+use uuid::Uuid;
 
 fn normalize_base_url(base_url: &str) -> String {
     base_url.trim_end_matches('/').to_string()
 }
 
-fn headers(api_key: &str) -> Result<HeaderMap, String> {
+fn headers(api_key: &str) -> Result<HeaderMap, AppError> {
     let mut headers = HeaderMap::new();
     headers.insert(
         AUTHORIZATION,
         HeaderValue::from_str(&format!("Bearer {api_key}"))
-            .map_err(|e| format!("无效的 API Key: {e}"))?,
+            .map_err(|e| AppError::new(ErrorKind::Config, "llm.invalidApiKey").caused_by(e))?,
     );
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
     Ok(headers)
 }
 
+fn api_key(endpoint_id: &str) -> Result<String, AppError> {
+    secure::get_api_key(endpoint_id).map_err(|e| {
+        AppError::new(ErrorKind::Config, "llm.missingApiKey")
+        .caused_by(e)
+    })
+}
+
+fn network_error(e: reqwest::Error) -> AppError {
+    AppError::new(ErrorKind::Network, "llm.network").caused_by(e)
+}
+
+fn bad_response(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Server, "llm.badResponse").caused_by(e)
+}
+
+// The provider's own explanation: `error.message` in OpenAI-style bodies, else the raw text.
+fn provider_message(body: &str) -> String {
+    let parsed = serde_json::from_str::<serde_json::Value>(body).ok();
+    let message = parsed.as_ref().and_then(|v| {
+        v.pointer("/error/message")
+            .or_else(|| v.get("message"))
+            .or_else(|| v.get("error"))
+            .and_then(|m| m.as_str())
+    });
+    match message {
+        Some(m) => m.to_string(),
+        None => body.trim().chars().take(500).collect(),
+    }
+}
+
+// Phrases providers use in error bodies for an oversized prompt and for an empty balance.
+const CONTEXT_LENGTH_HINTS: &[&str] = &["context_length", "maximum context", "context window", "too many tokens"];
+const QUOTA_HINTS: &[&str] = &[
+    "insufficient_quota",
+    "insufficient balance",
+    "exceeded your current quota",
+];
+
+// Sorts a failed response into an error kind. Providers disagree on statuses (some send 429
+// for an empty balance, 400 for an oversized prompt), so the body's error code wins.
+fn classify_response(status: u16, body: &str) -> AppError {
+    let lower = body.to_lowercase();
+    let mentions = |hints: &[&str]| hints.iter().any(|h| lower.contains(h));
+    let err = if status == 413 || mentions(CONTEXT_LENGTH_HINTS) {
+        AppError::new(ErrorKind::ContextLength, "llm.contextLength")
+    } else if status == 402 || mentions(QUOTA_HINTS) {
+        AppError::new(ErrorKind::Quota, "llm.quotaExceeded")
+    } else if status == 401 || status == 403 {
+        AppError::new(ErrorKind::Auth, "llm.unauthorized")
+    } else if status == 429 {
+        AppError::new(ErrorKind::RateLimit, "llm.rateLimited")
+    } else if status >= 500 {
+        AppError::new(ErrorKind::Server, "llm.serverError")
+    } else {
+        AppError::new(ErrorKind::Other, "llm.requestFailed")
+    };
+    err.with_param("status", status).caused_by(provider_message(body))
+}
+
+async fn check_status(res: reqwest::Response) -> Result<reqwest::Response, AppError> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status().as_u16();
+    let body = res.text().await.unwrap_or_default();
+    Err(classify_response(status, &body))
+}
+
 #[derive(serde::Deserialize)]
 struct ModelsResponse {
     data: Vec<ModelItem>,
@@ -26,8 +99,8 @@ struct ModelItem {
     id: String,
 }
 
-pub async fn fetch_models(base_url: &str, endpoint_id: &str) -> Result<Vec<String>, String> {
-    let api_key = secure::get_api_key(endpoint_id)?;
+pub async fn fetch_models(base_url: &str, endpoint_id: &str) -> Result<Vec<String>, AppError> {
+    let api_key = api_key(endpoint_id)?;
     let url = format!("{}/models", normalize_base_url(base_url));
     let client = reqwest::Client::new();
     let res = client
@@ -35,23 +108,18 @@ pub async fn fetch_models(base_url: &str, endpoint_id: &str) -> Result<Vec<Strin
         .headers(headers(&api_key)?)
         .send()
         .await
-        .map_err(|e| format!("请求模型列表失败: {e}"))?;
+        .map_err(network_error)?;
+    let res = check_status(res).await?;
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let body = res.text().await.unwrap_or_default();
-        return Err(format!("请求模型列表失败: {status} {body}"));
-    }
-
-    let parsed: ModelsResponse = res.json().await.map_err(|e| format!("解析模型列表失败: {e}"))?;
+    let parsed: ModelsResponse = res.json().await.map_err(bad_response)?;
     let mut models = parsed.data.into_iter().map(|m| m.id).collect::<Vec<_>>();
     models.sort();
     Ok(models)
 }
 
-fn active_endpoint(cfg: &LlmConfig) -> Result<EndpointConfig, String> {
+fn active_endpoint(cfg: &LlmConfig) -> Result<EndpointConfig, AppError> {
     if cfg.endpoints.is_empty() {
-        return Err("请先在“模型设置”中添加一个 API 端点".to_string());
+        return Err(AppError::new(ErrorKind::Config, "llm.noEndpoint"));
     }
     if let Some(id) = &cfg.active_endpoint_id {
         if let Some(ep) = cfg.endpoints.iter().find(|e| &e.id == id) {
@@ -68,31 +136,360 @@ fn active_model(cfg: &LlmConfig, ep: &EndpointConfig) -> String {
         .unwrap_or_else(|| ep.default_model.clone())
 }
 
-async fn post_chat_completions(
-    base_url: &str,
-    api_key: &str,
+// What a task runs on once its profile's gaps are filled in.
+struct Resolved {
+    endpoint: EndpointConfig,
+    model: String,
+    parameters: ModelParameters,
+}
+
+// A profile's endpoint takes its own default model unless the profile names one; without an
+// endpoint (or if it was deleted) the task follows the active endpoint and model.
+fn resolve(cfg: &LlmConfig, task: LlmTask) -> Result<Resolved, AppError> {
+    let profile = cfg.profile(task);
+    let model = profile.model.filter(|m| !m.trim().is_empty());
+    let chosen = profile
+        .endpoint_id
+        .as_ref()
+        .and_then(|id| cfg.endpoints.iter().find(|e| &e.id == id));
+    let (endpoint, model) = match chosen {
+        Some(ep) => (ep.clone(), model.unwrap_or_else(|| ep.default_model.clone())),
+        None => {
+            let ep = active_endpoint(cfg)?;
+            let model = match (&profile.endpoint_id, model) {
+                (None, Some(model)) => model,
+                _ => active_model(cfg, &ep),
+            };
+            (ep, model)
+        }
+    };
+    let parameters = profile.parameters.unwrap_or_else(|| endpoint.parameters.clone());
+    Ok(Resolved {
+        endpoint,
+        model,
+        parameters,
+    })
+}
+
+// A chat completion as the provider returned it; `raw` is the response body.
+struct Completion {
+    content: String,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<String>,
+    raw: String,
+}
+
+impl Completion {
+    // stopped by max_tokens rather than by the model
+    fn cut_off(&self) -> bool {
+        matches!(self.finish_reason.as_deref(), Some("length" | "max_tokens"))
+    }
+}
+
+// Where a chat completion goes, and what it is logged as in the project's audit log.
+struct Call<'a> {
+    project_dir: &'a str,
+    action: &'a str,
+    endpoint: &'a EndpointConfig,
+    model: &'a str,
+    parameters: &'a ModelParameters,
+    api_key: &'a str,
+    // the JSON schema the reply should follow, if any
+    schema: Option<&'a serde_json::Value>,
+    replay_of: Option<&'a str>,
+}
+
+async fn post_chat_completions(call: &Call<'_>, messages: Vec<serde_json::Value>) -> Result<Completion, AppError> {
+    usage::ensure_within_budget()?;
+    call_logged(call, messages).await.1
+}
+
+// Sends the request and records it, successful or not, in the project's audit log.
+async fn call_logged(
+    call: &Call<'_>,
+    messages: Vec<serde_json::Value>,
+) -> (LlmAuditEntry, Result<Completion, AppError>) {
+    let prompt = messages
+        .iter()
+        .filter_map(|m| m["content"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    tracing::info!(
+        base_url = call.endpoint.base_url,
+        model = call.model,
+        messages = messages.len(),
+        prompt = %logging::text(&prompt),
+        "chat completion request"
+    );
+    let created_at = prompt::now_iso();
+    let started = Instant::now();
+    let result = send_chat_completions(
+        call.endpoint,
+        call.api_key,
+        call.model,
+        call.parameters,
+        &messages,
+        call.schema,
+    )
+    .await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(c) => tracing::info!(elapsed_ms, response = %logging::text(&c.content), "chat completion done"),
+        Err(e) => tracing::warn!(
+            elapsed_ms,
+            kind = ?e.kind,
+            status = ?e.params.get("status"),
+            error = %e,
+            "chat completion failed"
+        ),
+    }
+
+    let entry = LlmAuditEntry {
+        id: Uuid::new_v4().to_string(),
+        created_at,
+        action: call.action.to_string(),
+        endpoint_id: call.endpoint.id.clone(),
+        endpoint_name: call.endpoint.name.clone(),
+        model: call.model.to_string(),
+        parameters: call.parameters.clone(),
+        messages,
+        response_schema: call.schema.cloned(),
+        latency_ms: elapsed_ms,
+        usage: result.as_ref().ok().and_then(|c| c.usage.clone()),
+        response: result.as_ref().ok().map(|c| c.raw.clone()),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        replay_of: call.replay_of.map(str::to_string),
+    };
+    // a generation that worked shouldn't fail because it couldn't be logged
+    if let Err(e) = audit::append(call.project_dir, &entry) {
+        tracing::warn!(error = %e, "could not write the model call log");
+    }
+    if let Some(tokens) = &entry.usage {
+        if let Err(e) = usage::record_call(call.project_dir, call.endpoint, call.model, call.action, tokens) {
+            tracing::warn!(error = %e, "could not record token usage");
+        }
+    }
+    (entry, result)
+}
+
+// OpenAI-style `usage`; some providers leave out the total or the whole field.
+fn parse_usage(v: &serde_json::Value) -> Option<TokenUsage> {
+    let usage = v.get("usage")?;
+    let count = |key: &str| usage.get(key).and_then(|n| n.as_u64());
+    let prompt_tokens = count("prompt_tokens")?;
+    let completion_tokens = count("completion_tokens").unwrap_or(0);
+    Some(TokenUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: count("total_tokens").unwrap_or(prompt_tokens + completion_tokens),
+    })
+}
+
+// Which optional parameters an API accepts; the rest are left out so strict providers don't
+// reject the request.
+struct Support {
+    // temperature and top_p
+    sampling: bool,
+    top_k: bool,
+    min_p: bool,
+    repetition_penalty: bool,
+    penalties: bool,
+    seed: bool,
+    stop: bool,
+    reasoning: bool,
+    // response_format with a JSON schema, or only {"type": "json_object"}
+    json_schema: bool,
+    json_object: bool,
+}
+
+// OpenAI's reasoning models (the o-series and gpt-5) reject every sampling setting and stop
+// sequences, and take max_completion_tokens instead of max_tokens. Only they take a reasoning effort.
+fn openai_reasoning_model(model: &str) -> bool {
+    let name = model.trim().to_lowercase();
+    ["o1", "o3", "o4", "gpt-5"].iter().any(|family| name.starts_with(family))
+}
+
+// `reasoning_model` is one of OpenAI's reasoning models.
+fn support(provider: Provider, reasoning_model: bool) -> Support {
+    let none = Support {
+        sampling: true,
+        top_k: false,
+        min_p: false,
+        repetition_penalty: false,
+        penalties: false,
+        seed: false,
+        stop: true,
+        reasoning: false,
+        json_schema: false,
+        json_object: false,
+    };
+    match provider {
+        Provider::OpenAi if reasoning_model => Support {
+            sampling: false,
+            stop: false,
+            seed: true,
+            reasoning: true,
+            json_schema: true,
+            ..none
+        },
+        Provider::OpenAi => Support {
+            penalties: true,
+            seed: true,
+            json_schema: true,
+            ..none
+        },
+        Provider::OpenRouter => Support {
+            top_k: true,
+            min_p: true,
+            repetition_penalty: true,
+            penalties: true,
+            seed: true,
+            reasoning: true,
+            json_schema: true,
+            ..none
+        },
+        Provider::DeepSeek => Support {
+            penalties: true,
+            json_object: true,
+            ..none
+        },
+        Provider::Gemini => Support {
+            seed: true,
+            reasoning: true,
+            json_schema: true,
+            ..none
+        },
+        Provider::Local => Support {
+            top_k: true,
+            min_p: true,
+            repetition_penalty: true,
+            penalties: true,
+            seed: true,
+            json_schema: true,
+            ..none
+        },
+        // top_k has always been sent to other services
+        Provider::Auto | Provider::Generic => Support {
+            top_k: true,
+            penalties: true,
+            seed: true,
+            ..none
+        },
+    }
+}
+
+// `Auto` endpoints are recognised by host; local and private addresses are self-hosted servers.
+fn provider_of(endpoint: &EndpointConfig) -> Provider {
+    if endpoint.provider != Provider::Auto {
+        return endpoint.provider;
+    }
+    let Some(host) = reqwest::Url::parse(endpoint.base_url.trim())
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_matches(['[', ']']).to_lowercase()))
+    else {
+        return Provider::Generic;
+    };
+    let is_local = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_unspecified(),
+        Ok(std::net::IpAddr::V6(ip)) => ip.is_loopback(),
+        Err(_) => host == "localhost" || host.ends_with(".local"),
+    };
+    match host.as_str() {
+        "api.openai.com" => Provider::OpenAi,
+        "openrouter.ai" => Provider::OpenRouter,
+        "api.deepseek.com" => Provider::DeepSeek,
+        "generativelanguage.googleapis.com" => Provider::Gemini,
+        _ if is_local => Provider::Local,
+        _ => Provider::Generic,
+    }
+}
+
+// `schema` asks for output in that shape where the provider can enforce it; elsewhere the
+// prompt alone has to do.
+fn request_body(
+    provider: Provider,
     model: &str,
     params: &ModelParameters,
-    messages: Vec<serde_json::Value>,
-) -> Result<String, String> {
-    let url = format!("{}/chat/completions", normalize_base_url(base_url));
+    messages: &[serde_json::Value],
+    schema: Option<&serde_json::Value>,
+) -> serde_json::Value {
+    let reasoning_model = provider == Provider::OpenAi && openai_reasoning_model(model);
+    let support = support(provider, reasoning_model);
     let mut body = serde_json::json!({
       "model": model,
       "messages": messages,
-      "max_tokens": params.max_tokens,
-      "temperature": params.temperature,
     });
+    let reasoning = params.reasoning_effort.filter(|_| support.reasoning);
 
-    if let Some(top_p) = params.top_p {
-        if top_p < 1.0 {
+    if reasoning_model {
+        body["max_completion_tokens"] = serde_json::json!(params.max_tokens);
+    } else {
+        body["max_tokens"] = serde_json::json!(params.max_tokens);
+    }
+    if support.sampling {
+        body["temperature"] = serde_json::json!(params.temperature);
+        if let Some(top_p) = params.top_p {
             body["top_p"] = serde_json::json!(top_p);
         }
     }
-    if let Some(top_k) = params.top_k {
-        if top_k > 0 {
-            body["top_k"] = serde_json::json!(top_k);
+    if let Some(top_k) = params.top_k.filter(|k| *k > 0 && support.top_k) {
+        body["top_k"] = serde_json::json!(top_k);
+    }
+    if let Some(min_p) = params.min_p.filter(|p| *p > 0.0 && support.min_p) {
+        body["min_p"] = serde_json::json!(min_p);
+    }
+    if let Some(penalty) = params.repetition_penalty.filter(|_| support.repetition_penalty) {
+        body["repetition_penalty"] = serde_json::json!(penalty);
+    }
+    if support.penalties {
+        if let Some(penalty) = params.presence_penalty {
+            body["presence_penalty"] = serde_json::json!(penalty);
+        }
+        if let Some(penalty) = params.frequency_penalty {
+            body["frequency_penalty"] = serde_json::json!(penalty);
+        }
+    }
+    let stop = params.stop.iter().filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    if support.stop && !stop.is_empty() {
+        body["stop"] = serde_json::json!(stop);
+    }
+    if let Some(seed) = params.seed.filter(|_| support.seed) {
+        body["seed"] = serde_json::json!(seed);
+    }
+    if let Some(effort) = reasoning {
+        if provider == Provider::OpenRouter {
+            body["reasoning"] = serde_json::json!({ "effort": effort.as_str() });
+        } else {
+            body["reasoning_effort"] = serde_json::json!(effort.as_str());
+        }
+    }
+    if let Some(schema) = schema {
+        if support.json_schema {
+            body["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "output", "strict": true, "schema": schema },
+            });
+        } else if support.json_object {
+            body["response_format"] = serde_json::json!({ "type": "json_object" });
         }
     }
+    // explicit extra fields are the user's call, so they go to every provider and win
+    for (key, value) in &params.extra_body {
+        body[key] = value.clone();
+    }
+    body
+}
+
+async fn send_chat_completions(
+    endpoint: &EndpointConfig,
+    api_key: &str,
+    model: &str,
+    params: &ModelParameters,
+    messages: &[serde_json::Value],
+    schema: Option<&serde_json::Value>,
+) -> Result<Completion, AppError> {
+    let url = format!("{}/chat/completions", normalize_base_url(&endpoint.base_url));
+    let body = request_body(provider_of(endpoint), model, params, messages, schema);
 
     let client = reqwest::Client::new();
     let res = client
@@ -101,89 +498,235 @@ async fn post_chat_completions(
         .json(&body)
         .send()
         .await
-        .map_err(|e| format!("请求失败: {e}"))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let body = res.text().await.unwrap_or_default();
-        return Err(format!("请求失败: {status} {body}"));
-    }
+        .map_err(network_error)?;
+    let res = check_status(res).await?;
 
-    let v: serde_json::Value = res.json().await.map_err(|e| format!("解析响应失败: {e}"))?;
-    let content = v
-        .get("choices")
-        .and_then(|c| c.get(0))
+    let raw = res.text().await.map_err(network_error)?;
+    let v: serde_json::Value = serde_json::from_str(&raw).map_err(bad_response)?;
+    let choice = v.get("choices").and_then(|c| c.get(0));
+    let content = choice
         .and_then(|c0| c0.get("message"))
         .and_then(|m| m.get("content"))
         .and_then(|c| c.as_str())
-        .ok_or("响应缺少 choices[0].message.content")?;
-    Ok(content.to_string())
+        .ok_or_else(|| bad_response(i18n::t("llm.missingContent")))?
+        .to_string();
+    let finish_reason = choice
+        .and_then(|c0| c0.get("finish_reason"))
+        .and_then(|r| r.as_str())
+        .map(str::to_string);
+    Ok(Completion {
+        content,
+        usage: parse_usage(&v),
+        finish_reason,
+        raw,
+    })
 }
 
-fn extract_json_block(raw: &str) -> Option<String> {
-    // Try ```json ... ```
-    let start = raw.find("```json")?;
-    let after = &raw[start + "```json".len()..];
-    let end = after.find("```")?;
-    Some(after[..end].trim().to_string())
+// A request's messages and the story context they were built from. The real calls, the
+// template preview and the dry runs all build them here so they can't drift apart.
+struct Prompt {
+    messages: Vec<serde_json::Value>,
+    context: Vec<ContextPiece>,
 }
 
-fn parse_generation(raw: &str) -> GenerationResponse {
-    let candidate = extract_json_block(raw).unwrap_or_else(|| raw.trim().to_string());
-    match serde_json::from_str::<serde_json::Value>(&candidate) {
-        Ok(v) => GenerationResponse {
-            content: v.get("content").and_then(|x| x.as_str()).unwrap_or("").to_string(),
-            summary: v.get("summary").and_then(|x| x.as_str()).unwrap_or("").to_string(),
-            raw: None,
-        },
-        Err(_) => GenerationResponse {
-            content: raw.to_string(),
-            summary: "".to_string(),
-            raw: Some(raw.to_string()),
-        },
+fn piece(kind: ContextKind, label: &str, text: &str) -> ContextPiece {
+    ContextPiece {
+        kind,
+        label: label.to_string(),
+        chars: text.chars().count(),
+        estimated_tokens: prompt::estimate_tokens(text),
     }
 }
 
-pub async fn continue_chapter(project_dir: &str, chapter_id: u32, instruction: &str) -> Result<GenerationResponse, String> {
-    let preset = storage::load_preset(project_dir.to_string())?;
-    let cfg = storage::load_llm_config(project_dir.to_string())?;
-    let ep = active_endpoint(&cfg)?;
-    let model = active_model(&cfg, &ep);
-    let api_key = secure::get_api_key(&ep.id)
-        .map_err(|_| "当前端点未设置 API Key（请到“模型设置”里设置）".to_string())?;
-
-    let chapter = storage::load_chapter(project_dir.to_string(), chapter_id)?;
-    let summaries = storage::load_summaries(project_dir.to_string())?;
-    let mut ctx = summaries
+fn preset_piece(preset: &Preset) -> ContextPiece {
+    let text = [preset.style.as_str(), preset.pov.as_str()]
+        .into_iter()
+        .chain(preset.rules.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n");
+    piece(ContextKind::Preset, "", &text)
+}
+
+// The chapter text plus the latest 20 non-empty summaries as context.
+fn continue_prompt(
+    store: &dyn ProjectStore,
+    preset: &Preset,
+    templates: &prompt::Overrides,
+    locale: Locale,
+    chapter_id: Option<u32>,
+    instruction: &str,
+) -> Result<Prompt, AppError> {
+    let chapter = chapter_id.map(|id| store.load_chapter(id)).transpose()?;
+    let summaries = recent_summaries(store)?;
+    let input = prompt::PromptInput {
+        preset,
+        summaries: &summaries,
+        chapter: chapter.as_ref().map_or("", |c| c.content.as_str()),
+        instruction,
+    };
+    let messages = prompt::continue_messages(templates, locale, &input)?;
+
+    let mut context = vec![preset_piece(preset)];
+    context.extend(summaries.iter().map(|(title, summary)| piece(ContextKind::Summary, title, summary)));
+    if let Some(chapter) = &chapter {
+        context.push(piece(ContextKind::Chapter, &chapter.title, &chapter.content));
+    }
+    if !instruction.trim().is_empty() {
+        context.push(piece(ContextKind::Instruction, "", instruction.trim()));
+    }
+    Ok(Prompt { messages, context })
+}
+
+// `history` is the session before `user_message`.
+fn discuss_prompt(
+    preset: &Preset,
+    templates: &prompt::Overrides,
+    locale: Locale,
+    history: &[ChatMessage],
+    user_message: &str,
+) -> Result<Prompt, AppError> {
+    let history = chat_history(history);
+    let system = prompt::discuss_system(templates, locale, preset)?;
+    let messages = prompt::to_openai_messages(system, &history, user_message.to_string());
+
+    let mut context = vec![preset_piece(preset)];
+    if !history.is_empty() {
+        let text = history.iter().map(|m| m.content.as_str()).collect::<Vec<_>>().join("\n");
+        context.push(piece(ContextKind::History, &history.len().to_string(), &text));
+    }
+    context.push(piece(ContextKind::Instruction, "", user_message));
+    Ok(Prompt { messages, context })
+}
+
+// The latest 20 non-empty summaries as (chapter title, summary), oldest first.
+fn recent_summaries(store: &dyn ProjectStore) -> Result<Vec<(String, String)>, AppError> {
+    let mut summaries = store
+        .load_summaries()?
         .into_iter()
         .filter(|s| !s.summary.trim().is_empty())
         .rev()
         .take(20)
         .map(|s| (s.chapter_title, s.summary))
         .collect::<Vec<_>>();
-    ctx.reverse();
+    summaries.reverse();
+    Ok(summaries)
+}
+
+// Follow-up requests made for a continuation that hit max_tokens before giving up.
+const MAX_CONTINUATIONS: usize = 3;
 
-    let system = prompt::build_system_prompt(&preset, "continue");
-    let user = prompt::build_user_prompt(&ctx, &chapter.content, "continue", instruction);
-    let messages = vec![
-        serde_json::json!({ "role": "system", "content": system }),
-        serde_json::json!({ "role": "user", "content": user }),
-    ];
+pub async fn continue_chapter(
+    project_dir: &str,
+    chapter_id: u32,
+    instruction: &str,
+) -> Result<GenerationResponse, AppError> {
+    let preset = storage::load_preset(project_dir.to_string())?;
+    let templates = storage::load_prompt_templates(project_dir.to_string())?;
+    let cfg = storage::load_llm_config(project_dir.to_string())?;
+    let target = resolve(&cfg, LlmTask::Continue)?;
+    let api_key = api_key(&target.endpoint.id)?;
+
+    // the store isn't Send, so it must be gone before the request is awaited
+    let locale = storage::project_locale(Path::new(project_dir));
+    let messages = continue_prompt(
+        store::open(project_dir)?.as_ref(),
+        &preset,
+        &templates,
+        locale,
+        Some(chapter_id),
+        instruction,
+    )?
+    .messages;
+
+    let schema = generation::schema();
+    let call = Call {
+        project_dir,
+        action: LlmTask::Continue.as_str(),
+        endpoint: &target.endpoint,
+        model: &target.model,
+        parameters: &target.parameters,
+        api_key: &api_key,
+        schema: Some(&schema),
+        replay_of: None,
+    };
+    let mut completion = post_chat_completions(&call, messages.clone()).await?;
+    let mut output = completion.content.clone();
 
-    let raw = post_chat_completions(&ep.base_url, &api_key, &model, &ep.parameters, messages).await?;
-    Ok(parse_generation(&raw))
+    // a reply cut off by max_tokens is picked up where it stopped; the follow-ups go without
+    // the schema, which would make the model start a new object instead of finishing this one
+    let follow_up = Call { schema: None, ..call };
+    let resume = prompt::resume_instruction(&templates, locale)?;
+    let mut followed = 0;
+    while completion.cut_off() && followed < MAX_CONTINUATIONS {
+        followed += 1;
+        let mut next = messages.clone();
+        next.push(serde_json::json!({ "role": "assistant", "content": output }));
+        next.push(serde_json::json!({ "role": "user", "content": resume }));
+        completion = match post_chat_completions(&follow_up, next).await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(error = %e, "continuing a cut-off generation failed");
+                break;
+            }
+        };
+        output = generation::join(&output, &completion.content);
+    }
+
+    let mut result = generation::parse(&output);
+    result.truncated = completion.cut_off();
+
+    // the summaries feed later prompts, so a missing one is worth a second, short call
+    if result.summary.trim().is_empty() && !result.content.trim().is_empty() {
+        match summarize(project_dir, &cfg, &templates, locale, &result.content).await {
+            Ok(summary) => result.summary = summary,
+            Err(e) => tracing::warn!(error = %e, "summary fallback failed"),
+        }
+    }
+    Ok(result)
+}
+
+async fn summarize(
+    project_dir: &str,
+    cfg: &LlmConfig,
+    templates: &prompt::Overrides,
+    locale: Locale,
+    text: &str,
+) -> Result<String, AppError> {
+    let messages = prompt::summary_messages(templates, locale, text)?;
+    let target = resolve(cfg, LlmTask::Summarize)?;
+    let api_key = api_key(&target.endpoint.id)?;
+    let call = Call {
+        project_dir,
+        action: LlmTask::Summarize.as_str(),
+        endpoint: &target.endpoint,
+        model: &target.model,
+        parameters: &target.parameters,
+        api_key: &api_key,
+        schema: None,
+        replay_of: None,
+    };
+    let completion = post_chat_completions(&call, messages).await?;
+    Ok(completion.content.trim().to_string())
+}
+
+fn chat_history(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    messages
+        .iter()
+        .filter(|m| m.role == "user" || m.role == "assistant")
+        .cloned()
+        .collect()
 }
 
-pub async fn discuss(project_dir: &str, session_id: &str, user_message: &str) -> Result<ChatMessage, String> {
+pub async fn discuss(project_dir: &str, session_id: &str, user_message: &str) -> Result<ChatMessage, AppError> {
     let preset = storage::load_preset(project_dir.to_string())?;
+    let templates = storage::load_prompt_templates(project_dir.to_string())?;
     let cfg = storage::load_llm_config(project_dir.to_string())?;
-    let ep = active_endpoint(&cfg)?;
-    let model = active_model(&cfg, &ep);
-    let api_key = secure::get_api_key(&ep.id)
-        .map_err(|_| "当前端点未设置 API Key（请到“模型设置”里设置）".to_string())?;
+    let target = resolve(&cfg, LlmTask::Discuss)?;
+    let api_key = api_key(&target.endpoint.id)?;
 
-    let mut session = storage::load_chat_session(project_dir.to_string(), session_id.to_string())?;
-    let system = prompt::build_system_prompt(&preset, "discuss");
+    let mut session = store::open(project_dir)?.load_chat_session(session_id)?;
+    let locale = storage::project_locale(Path::new(project_dir));
+    let messages = discuss_prompt(&preset, &templates, locale, &session.messages, user_message)?.messages;
 
     let user_msg = ChatMessage {
         role: "user".to_string(),
@@ -192,30 +735,349 @@ pub async fn discuss(project_dir: &str, session_id: &str, user_message: &str) ->
     };
     session.messages.push(user_msg.clone());
 
-    let history = session
-        .messages
-        .iter()
-        .filter(|m| m.role == "user" || m.role == "assistant")
-        .cloned()
-        .collect::<Vec<_>>();
-    let messages = prompt::to_openai_messages(system, &history[..history.len().saturating_sub(1)], user_message.to_string());
-
-    let raw = post_chat_completions(&ep.base_url, &api_key, &model, &ep.parameters, messages).await?;
+    let call = Call {
+        project_dir,
+        action: LlmTask::Discuss.as_str(),
+        endpoint: &target.endpoint,
+        model: &target.model,
+        parameters: &target.parameters,
+        api_key: &api_key,
+        schema: None,
+        replay_of: None,
+    };
+    let completion = post_chat_completions(&call, messages).await?;
     let assistant = ChatMessage {
         role: "assistant".to_string(),
-        content: raw,
+        content: completion.content,
         created_at: prompt::now_iso(),
     };
     session.messages.push(assistant.clone());
 
-    if session.title.trim().is_empty() || session.title == "新对话" {
+    if session.title.trim().is_empty() || session.title == i18n::text(locale, "session.defaultTitle") {
         let t = user_message.trim();
         if !t.is_empty() {
             session.title = t.chars().take(16).collect::<String>();
         }
     }
 
-    storage::save_chat_session(project_dir.to_string(), &session)?;
+    store::open(project_dir)?.save_chat_session(&session)?;
     Ok(assistant)
 }
 
+// The prompt `task` would be sent with right now, with `drafts` standing in for the saved
+// templates of the same name. A discussion without `session_id` starts a new session.
+fn prompt_for(
+    project_dir: &str,
+    task: LlmTask,
+    chapter_id: Option<u32>,
+    session_id: Option<&str>,
+    instruction: &str,
+    drafts: prompt::Overrides,
+) -> Result<Prompt, AppError> {
+    for (name, source) in &drafts {
+        prompt::validate(*name, source)?;
+    }
+    let mut templates = storage::load_prompt_templates(project_dir.to_string())?;
+    templates.extend(drafts);
+    let preset = storage::load_preset(project_dir.to_string())?;
+    let locale = storage::project_locale(Path::new(project_dir));
+    let store = store::open(project_dir)?;
+    let prompt = match task {
+        LlmTask::Continue => continue_prompt(store.as_ref(), &preset, &templates, locale, chapter_id, instruction)?,
+        LlmTask::Discuss => {
+            let history = match session_id {
+                Some(id) => store.load_chat_session(id)?.messages,
+                None => vec![],
+            };
+            discuss_prompt(&preset, &templates, locale, &history, instruction)?
+        }
+        _ => return Err(AppError::new(ErrorKind::Invalid, "prompt.previewUnsupported")),
+    };
+    Ok(prompt)
+}
+
+// The messages `task` would send, for checking template drafts; nothing goes to the model.
+pub fn preview_prompt(
+    project_dir: &str,
+    task: LlmTask,
+    chapter_id: Option<u32>,
+    session_id: Option<&str>,
+    instruction: &str,
+    drafts: prompt::Overrides,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    Ok(prompt_for(project_dir, task, chapter_id, session_id, instruction, drafts)?.messages)
+}
+
+// The preview plus where it would go and what it could cost. Only the first request is
+// shown; follow-ups for a cut-off reply or a missing summary depend on the answer.
+pub fn dry_run(
+    project_dir: &str,
+    task: LlmTask,
+    chapter_id: Option<u32>,
+    session_id: Option<&str>,
+    instruction: &str,
+) -> Result<DryRun, AppError> {
+    let prompt = prompt_for(project_dir, task, chapter_id, session_id, instruction, prompt::Overrides::new())?;
+    let cfg = storage::load_llm_config(project_dir.to_string())?;
+    request_preview(task, &cfg, prompt)
+}
+
+fn request_preview(task: LlmTask, cfg: &LlmConfig, prompt: Prompt) -> Result<DryRun, AppError> {
+    let target = resolve(cfg, task)?;
+    let schema = (task == LlmTask::Continue).then(generation::schema);
+    let Prompt { messages, context } = prompt;
+    let provider = provider_of(&target.endpoint);
+    // a few tokens of framing per message on top of the text
+    let prompt_tokens = messages
+        .iter()
+        .map(|m| prompt::estimate_tokens(m["content"].as_str().unwrap_or("")) + 4)
+        .sum::<u64>();
+    let most = TokenUsage {
+        prompt_tokens,
+        completion_tokens: target.parameters.max_tokens as u64,
+        total_tokens: prompt_tokens + target.parameters.max_tokens as u64,
+    };
+    Ok(DryRun {
+        task,
+        provider,
+        request_body: request_body(provider, &target.model, &target.parameters, &messages, schema.as_ref()),
+        estimated_max_cost: usage::cost(&target.endpoint, &target.model, &most),
+        estimated_prompt_tokens: prompt_tokens,
+        endpoint_id: target.endpoint.id,
+        endpoint_name: target.endpoint.name,
+        model: target.model,
+        parameters: target.parameters,
+        messages,
+        context,
+    })
+}
+
+// Sends a logged request again with the same messages and parameters, to `endpoint_id` and
+// `model` if given, so outputs can be compared. The replay gets its own log entry.
+pub async fn replay(
+    project_dir: &str,
+    id: &str,
+    endpoint_id: Option<String>,
+    model: Option<String>,
+) -> Result<LlmAuditEntry, AppError> {
+    let original = audit::load(project_dir, id)?;
+    let cfg = storage::load_llm_config(project_dir.to_string())?;
+    let wanted = endpoint_id.unwrap_or_else(|| original.endpoint_id.clone());
+    let ep = match cfg.endpoints.iter().find(|e| e.id == wanted) {
+        Some(ep) => ep.clone(),
+        None => active_endpoint(&cfg)?,
+    };
+    // the logged model may not exist on another endpoint
+    let model = model.filter(|m| !m.trim().is_empty()).unwrap_or_else(|| {
+        if ep.id == original.endpoint_id {
+            original.model.clone()
+        } else {
+            ep.default_model.clone()
+        }
+    });
+    let api_key = api_key(&ep.id)?;
+    usage::ensure_within_budget()?;
+
+    let call = Call {
+        project_dir,
+        action: &original.action,
+        endpoint: &ep,
+        model: &model,
+        parameters: &original.parameters,
+        api_key: &api_key,
+        schema: original.response_schema.as_ref(),
+        replay_of: Some(&original.id),
+    };
+    let (entry, result) = call_logged(&call, original.messages.clone()).await;
+    result?;
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::fixtures;
+
+    #[test]
+    fn continue_prompt_carries_preset_summaries_and_chapter() {
+        let store = fixtures::sample_novel();
+        let preset = fixtures::sample_preset();
+        let none = prompt::Overrides::new();
+        let messages = continue_prompt(&store, &preset, &none, Locale::ZhCn, Some(2), "写老人开口说话").unwrap().messages;
+
+        let system = messages[0]["content"].as_str().unwrap();
+        assert!(system.contains(&preset.style));
+        let user = messages[1]["content"].as_str().unwrap();
+        assert!(user.contains("林舟在雾港码头看见熄灭三年的灯塔重新亮起。"));
+        assert!(user.contains("顶层的灯室里坐着一个陌生的老人。"));
+        assert!(user.contains("写老人开口说话"));
+        assert!(continue_prompt(&store, &preset, &none, Locale::ZhCn, Some(99), "").is_err());
+
+        let english = continue_prompt(&store, &Preset::default_en(), &none, Locale::En, Some(2), "").unwrap().messages;
+        assert!(english[0]["content"].as_str().unwrap().contains("Third person limited"));
+        assert!(english[1]["content"].as_str().unwrap().contains("## Task: Continue the text"));
+
+        let custom = prompt::Overrides::from([(
+            PromptTemplateName::ContinueUser,
+            "{{#each summaries}}{{title}}|{{/each}}\n要求：{{instruction}}".to_string(),
+        )]);
+        let messages = continue_prompt(&store, &preset, &custom, Locale::ZhCn, Some(2), " 慢一点 ").unwrap().messages;
+        assert!(messages[0]["content"].as_str().unwrap().contains("## 写作规则"));
+        assert!(messages[1]["content"].as_str().unwrap().ends_with("|\n要求：慢一点"));
+        let broken = prompt::Overrides::from([(PromptTemplateName::ContinueUser, "{{#if chapter}}".to_string())]);
+        assert!(continue_prompt(&store, &preset, &broken, Locale::ZhCn, Some(2), "").is_err());
+    }
+
+    #[test]
+    fn dry_run_shows_the_request_and_its_context() {
+        let store = fixtures::sample_novel();
+        let preset = fixtures::sample_preset();
+        let mut ep = EndpointConfig::new("OpenAI".into(), "https://api.openai.com/v1".into(), "gpt-4o".into());
+        ep.pricing.push(ModelPricing {
+            model: "gpt-4o".to_string(),
+            input_per_million: 2.5,
+            output_per_million: 10.0,
+        });
+        let mut cfg = LlmConfig {
+            active_endpoint_id: Some(ep.id.clone()),
+            endpoints: vec![ep],
+            active_model: None,
+            profiles: Default::default(),
+        };
+        cfg.fill_default_profiles();
+
+        let none = prompt::Overrides::new();
+        let prompt = continue_prompt(&store, &preset, &none, Locale::ZhCn, Some(2), "写老人开口说话").unwrap();
+        let run = request_preview(LlmTask::Continue, &cfg, prompt).unwrap();
+        assert_eq!((run.provider, run.model.as_str()), (Provider::OpenAi, "gpt-4o"));
+        assert_eq!(run.request_body["messages"], serde_json::Value::Array(run.messages.clone()));
+        assert_eq!(run.request_body["response_format"]["type"], "json_schema");
+        let kinds = run.context.iter().map(|p| p.kind).collect::<Vec<_>>();
+        assert_eq!(kinds.first(), Some(&ContextKind::Preset));
+        assert_eq!(kinds.last(), Some(&ContextKind::Instruction));
+        assert!(kinds.contains(&ContextKind::Summary) && kinds.contains(&ContextKind::Chapter));
+        assert!(run.estimated_prompt_tokens > run.context.iter().map(|p| p.estimated_tokens).sum::<u64>());
+        assert!(run.estimated_max_cost.is_some_and(|c| c > 0.0));
+    }
+
+    #[test]
+    fn resolves_task_profiles() {
+        let main = EndpointConfig::new("Main".into(), "https://a.example/v1".into(), "big".into());
+        let cheap = EndpointConfig::new("Cheap".into(), "https://b.example/v1".into(), "small".into());
+        let mut cfg = LlmConfig {
+            endpoints: vec![main.clone(), cheap.clone()],
+            active_endpoint_id: Some(main.id.clone()),
+            active_model: Some("big-latest".to_string()),
+            profiles: Default::default(),
+        };
+        cfg.fill_default_profiles();
+
+        let cont = resolve(&cfg, LlmTask::Continue).unwrap();
+        assert_eq!((cont.endpoint.id.as_str(), cont.model.as_str()), (main.id.as_str(), "big-latest"));
+        assert_eq!(cont.parameters.temperature, main.parameters.temperature);
+        let summary = resolve(&cfg, LlmTask::Summarize).unwrap();
+        assert!(summary.parameters.temperature < 0.5);
+
+        cfg.profiles.get_mut(&LlmTask::Summarize).unwrap().endpoint_id = Some(cheap.id.clone());
+        let summary = resolve(&cfg, LlmTask::Summarize).unwrap();
+        assert_eq!((summary.endpoint.id.as_str(), summary.model.as_str()), (cheap.id.as_str(), "small"));
+
+        // a deleted endpoint falls back to the active one
+        cfg.endpoints.retain(|e| e.id != cheap.id);
+        assert_eq!(resolve(&cfg, LlmTask::Summarize).unwrap().endpoint.id, main.id);
+    }
+
+    #[test]
+    fn sends_only_supported_parameters() {
+        let endpoint = |url: &str| EndpointConfig::new("ep".into(), url.into(), "m".into());
+        assert_eq!(provider_of(&endpoint("https://api.openai.com/v1")), Provider::OpenAi);
+        assert_eq!(provider_of(&endpoint("http://127.0.0.1:8080/v1")), Provider::Local);
+        assert_eq!(provider_of(&endpoint("http://192.168.1.20:8000/v1")), Provider::Local);
+        assert_eq!(provider_of(&endpoint("https://api.example.com/v1")), Provider::Generic);
+
+        let mut params = ModelParameters {
+            top_p: Some(1.0),
+            top_k: Some(40),
+            min_p: Some(0.05),
+            presence_penalty: Some(0.3),
+            repetition_penalty: Some(1.1),
+            stop: vec!["###".to_string(), String::new()],
+            seed: Some(7),
+            ..ModelParameters::default_for_writing()
+        };
+        params.extra_body.insert("user".to_string(), serde_json::json!("novel"));
+
+        let openai = request_body(Provider::OpenAi, "gpt-4o", &params, &[], None);
+        assert_eq!(openai["top_p"], 1.0);
+        assert_eq!(openai["seed"], 7);
+        assert_eq!(openai["stop"], serde_json::json!(["###"]));
+        assert_eq!(openai["user"], "novel");
+        assert!(openai.get("top_k").is_none() && openai.get("min_p").is_none());
+        assert!(openai.get("repetition_penalty").is_none());
+        assert!(openai.get("presence_penalty").is_some());
+        let o_series = request_body(Provider::OpenAi, "o4-mini", &params, &[], None);
+        assert!(o_series.get("temperature").is_none() && o_series.get("top_p").is_none());
+        assert!(o_series.get("presence_penalty").is_none() && o_series.get("max_tokens").is_none());
+        assert_eq!(o_series["seed"], 7);
+        assert!(o_series.get("stop").is_none());
+
+        let local = request_body(Provider::Local, "qwen", &params, &[], None);
+        assert_eq!(local["top_k"], 40);
+        assert!(local.get("min_p").is_some() && local.get("repetition_penalty").is_some());
+
+        params.reasoning_effort = Some(ReasoningEffort::High);
+        let reasoning = request_body(Provider::OpenAi, "o3", &params, &[], None);
+        assert_eq!(reasoning["reasoning_effort"], "high");
+        assert_eq!(reasoning["max_completion_tokens"], 4000);
+        assert!(reasoning.get("temperature").is_none() && reasoning.get("max_tokens").is_none());
+        assert!(reasoning.get("presence_penalty").is_none());
+        let chat = request_body(Provider::OpenAi, "gpt-4o", &params, &[], None);
+        assert!(chat.get("reasoning_effort").is_none() && chat.get("max_completion_tokens").is_none());
+        assert_eq!(chat["max_tokens"], 4000);
+        let routed = request_body(Provider::OpenRouter, "o3", &params, &[], None);
+        assert_eq!(routed["reasoning"]["effort"], "high");
+        assert!(request_body(Provider::DeepSeek, "r1", &params, &[], None).get("reasoning_effort").is_none());
+
+        let schema = generation::schema();
+        let structured = request_body(Provider::OpenAi, "gpt-4o", &params, &[], Some(&schema));
+        assert_eq!(structured["response_format"]["json_schema"]["schema"], schema);
+        let deepseek = request_body(Provider::DeepSeek, "chat", &params, &[], Some(&schema));
+        assert_eq!(deepseek["response_format"]["type"], "json_object");
+        assert!(request_body(Provider::Generic, "m", &params, &[], Some(&schema)).get("response_format").is_none());
+    }
+
+    #[test]
+    fn reads_token_usage() {
+        let full =
+            serde_json::json!({ "usage": { "prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150 } });
+        assert_eq!(
+            parse_usage(&full),
+            Some(TokenUsage {
+                prompt_tokens: 120,
+                completion_tokens: 30,
+                total_tokens: 150
+            })
+        );
+        let no_total = serde_json::json!({ "usage": { "prompt_tokens": 7, "completion_tokens": 3 } });
+        assert_eq!(parse_usage(&no_total).unwrap().total_tokens, 10);
+        assert_eq!(parse_usage(&serde_json::json!({ "choices": [] })), None);
+    }
+
+    #[test]
+    fn classifies_provider_errors() {
+        let openai = |code: &str| format!(r#"{{"error":{{"message":"details","code":"{code}"}}}}"#);
+        assert_eq!(classify_response(401, "").kind, ErrorKind::Auth);
+        assert_eq!(classify_response(429, "").kind, ErrorKind::RateLimit);
+        assert_eq!(
+            classify_response(429, &openai("insufficient_quota")).kind,
+            ErrorKind::Quota
+        );
+        assert_eq!(classify_response(402, "").kind, ErrorKind::Quota);
+        let too_long = classify_response(400, &openai("context_length_exceeded"));
+        assert_eq!(too_long.kind, ErrorKind::ContextLength);
+        assert_eq!(too_long.sources, vec!["details".to_string()]);
+        assert_eq!(too_long.params["status"], "400");
+        assert_eq!(classify_response(503, "upstream down").kind, ErrorKind::Server);
+        assert_eq!(classify_response(404, "").kind, ErrorKind::Other);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, types::*};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A lock whose holder has not refreshed it for this long is considered abandoned.
+const STALE_AFTER_SECS: u64 = 180;
+
+pub(crate) fn lock_file(project_dir: &Path) -> PathBuf {
+    project_dir.join(".creatorai").join("lock.json")
+}
+
+fn instance_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname").map(|h| h.trim().to_string()))
+        .unwrap_or_default()
+}
+
+fn held() -> &'static Mutex<HashSet<PathBuf>> {
+    static HELD: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    HELD.get_or_init(Default::default)
+}
+
+fn read_lock(project_dir: &Path) -> Option<ProjectLockInfo> {
+    let raw = fs::read_to_string(lock_file(project_dir)).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn write_error(e: io::Error) -> AppError {
+    AppError::new(ErrorKind::Io, "lock.write").caused_by(e)
+}
+
+fn lock_contents(acquired_at: u64) -> Result<String, AppError> {
+    let info = ProjectLockInfo {
+        instance_id: instance_id().to_string(),
+        pid: std::process::id(),
+        host: host_name(),
+        acquired_at,
+        heartbeat_at: now_secs(),
+    };
+    serde_json::to_string_pretty(&info).map_err(|e| AppError::new(ErrorKind::Other, "io.serialize").caused_by(e))
+}
+
+// Only for refreshing a lock this instance already holds.
+fn write_lock(project_dir: &Path, acquired_at: u64) -> Result<(), AppError> {
+    durable::write(&lock_file(project_dir), lock_contents(acquired_at)?.as_bytes()).map_err(write_error)
+}
+
+// Creates the lock file only if there is none (O_EXCL), so of two instances racing for a
+// free project exactly one wins. Ok(false) when a lock file already exists.
+fn create_lock(project_dir: &Path) -> Result<bool, AppError> {
+    let path = lock_file(project_dir);
+    let dir = path.parent().unwrap_or(project_dir);
+    fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+    let raw = lock_contents(now_secs())?;
+    let mut file = match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(write_error(e)),
+    };
+    let written = file
+        .write_all(raw.as_bytes())
+        .and_then(|_| file.sync_all())
+        .and_then(|_| durable::sync_dir(dir));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path);
+        return Err(write_error(e));
+    }
+    Ok(true)
+}
+
+// A lock file that can't be parsed is most likely being written by its creator right now,
+// so it only counts as stale once the file itself is old.
+fn is_stale(project_dir: &Path, holder: Option<&ProjectLockInfo>) -> bool {
+    let age = |since: u64| now_secs().saturating_sub(since) > STALE_AFTER_SECS;
+    match holder {
+        Some(h) => age(h.heartbeat_at),
+        None => fs::metadata(lock_file(project_dir))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .is_none_or(|t| age(t.as_secs())),
+    }
+}
+
+// Moves a stale (or forcibly taken) lock out of the way. Only one instance's rename can
+// succeed; if the moved file isn't the one that was judged, its holder refreshed it or
+// another instance replaced it in between, so it is put back. Either way the caller looks again.
+fn claim(project_dir: &Path, judged: &[u8]) -> Result<(), AppError> {
+    let path = lock_file(project_dir);
+    let claimed = path.with_file_name(format!("lock.{}.json", instance_id()));
+    match fs::rename(&path, &claimed) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(write_error(e)),
+    }
+    if fs::read(&claimed).is_ok_and(|raw| raw != judged) {
+        // a hard link never replaces a lock that appeared meanwhile
+        let _ = fs::hard_link(&claimed, &path);
+    }
+    let _ = fs::remove_file(&claimed);
+    Ok(())
+}
+
+fn took(root: PathBuf) -> ProjectLockStatus {
+    let holder = read_lock(&root);
+    held().lock().unwrap_or_else(|e| e.into_inner()).insert(root);
+    ProjectLockStatus { acquired: true, holder }
+}
+
+// Takes the project lock unless another live instance holds it. With `force` the lock is
+// taken over regardless; the caller is expected to have asked the user first.
+pub fn acquire(project_dir: String, force: bool) -> Result<ProjectLockStatus, AppError> {
+    let root = PathBuf::from(&project_dir);
+    // each pass either takes a free lock or clears a stale one; losing a race means looking again
+    for _ in 0..3 {
+        if create_lock(&root)? {
+            return Ok(took(root));
+        }
+        let Ok(raw) = fs::read(lock_file(&root)) else {
+            continue;
+        };
+        let holder = serde_json::from_slice::<ProjectLockInfo>(&raw).ok();
+        if let Some(h) = holder.as_ref().filter(|h| h.instance_id == instance_id()) {
+            write_lock(&root, h.acquired_at)?;
+            return Ok(took(root));
+        }
+        if !force && !is_stale(&root, holder.as_ref()) {
+            return Ok(ProjectLockStatus {
+                acquired: false,
+                holder,
+            });
+        }
+        claim(&root, &raw)?;
+    }
+    Ok(ProjectLockStatus {
+        acquired: false,
+        holder: read_lock(&root),
+    })
+}
+
+// Whether this instance holds the project lock right now.
+pub(crate) fn holds(root: &Path) -> bool {
+    held().lock().unwrap_or_else(|e| e.into_inner()).contains(root)
+        && read_lock(root).is_some_and(|h| h.instance_id == instance_id())
+}
+
+pub fn release(project_dir: String) -> Result<(), AppError> {
+    let root = PathBuf::from(&project_dir);
+    held().lock().unwrap_or_else(|e| e.into_inner()).remove(&root);
+    if read_lock(&root).is_some_and(|h| h.instance_id == instance_id()) {
+        fs::remove_file(lock_file(&root)).map_err(|e| AppError::new(ErrorKind::Io, "lock.release").caused_by(e))?;
+    }
+    Ok(())
+}
+
+// Called by the scheduler so that locks of open projects never look stale.
+pub fn refresh_held() {
+    let dirs = held().lock().unwrap_or_else(|e| e.into_inner()).clone();
+    for root in dirs {
+        if let Some(h) = read_lock(&root).filter(|h| h.instance_id == instance_id()) {
+            let _ = write_lock(&root, h.acquired_at);
+        }
+    }
+}
+
+// In-process serialisation of read-modify-write cycles on a project. Re-entrant per thread,
+// since storage functions call each other (create_chapter -> save_chapter).
+thread_local! {
+    static HELD_HERE: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+}
+
+fn busy() -> &'static (Mutex<HashSet<PathBuf>>, Condvar) {
+    static BUSY: OnceLock<(Mutex<HashSet<PathBuf>>, Condvar)> = OnceLock::new();
+    BUSY.get_or_init(Default::default)
+}
+
+pub(crate) struct WriteGuard {
+    root: Option<PathBuf>,
+}
+
+pub(crate) fn write_guard(root: &Path) -> WriteGuard {
+    if HELD_HERE.with(|h| h.borrow().contains(root)) {
+        return WriteGuard { root: None };
+    }
+    let (lock, cvar) = busy();
+    let mut projects = lock.lock().unwrap_or_else(|e| e.into_inner());
+    while projects.contains(root) {
+        projects = cvar.wait(projects).unwrap_or_else(|e| e.into_inner());
+    }
+    projects.insert(root.to_path_buf());
+    HELD_HERE.with(|h| h.borrow_mut().insert(root.to_path_buf()));
+    WriteGuard {
+        root: Some(root.to_path_buf()),
+    }
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        if let Some(root) = self.root.take() {
+            HELD_HERE.with(|h| h.borrow_mut().remove(&root));
+            let (lock, cvar) = busy();
+            lock.lock().unwrap_or_else(|e| e.into_inner()).remove(&root);
+            cvar.notify_all();
+        }
+    }
+}
+
+// What a file looked like the last time this process read or wrote it.
+#[derive(Clone, PartialEq)]
+struct Fingerprint {
+    len: u64,
+    modified: Option<SystemTime>,
+    sha256: String,
+}
+
+// None marks a file this process deleted itself.
+fn fingerprints() -> &'static Mutex<HashMap<PathBuf, Option<Fingerprint>>> {
+    static KNOWN: OnceLock<Mutex<HashMap<PathBuf, Option<Fingerprint>>>> = OnceLock::new();
+    KNOWN.get_or_init(Default::default)
+}
+
+fn known(path: &Path) -> Option<Option<Fingerprint>> {
+    fingerprints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(path)
+        .cloned()
+}
+
+fn fingerprint_of(path: &Path, contents: &[u8]) -> Fingerprint {
+    Fingerprint {
+        len: contents.len() as u64,
+        modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+        sha256: format!("{:x}", Sha256::digest(contents)),
+    }
+}
+
+pub(crate) fn remember(path: &Path, contents: &[u8]) {
+    let fp = fingerprint_of(path, contents);
+    fingerprints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), Some(fp));
+}
+
+pub(crate) fn remember_removed(path: &Path) {
+    fingerprints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), None);
+}
+
+pub(crate) fn forget(path: &Path) {
+    fingerprints().lock().unwrap_or_else(|e| e.into_inner()).remove(path);
+}
+
+// Drops everything known below `root`, after the app itself rewrote the project wholesale.
+pub(crate) fn forget_project(root: &Path) {
+    fingerprints()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|p, _| !p.starts_with(root));
+}
+
+fn matches(path: &Path, known: &Fingerprint) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    if meta.len() == known.len && meta.modified().ok() == known.modified {
+        return true;
+    }
+    fs::read(path).is_ok_and(|current| format!("{:x}", Sha256::digest(&current)) == known.sha256)
+}
+
+// True when the file on disk is exactly what this process last read, wrote or deleted;
+// used to tell our own writes apart from external edits.
+pub(crate) fn is_known_state(path: &Path) -> bool {
+    match known(path) {
+        Some(Some(fp)) => matches(path, &fp),
+        Some(None) => !path.exists(),
+        None => false,
+    }
+}
+
+// Fails when `path` changed on disk since this process last read or wrote it, so that
+// edits from another window, editor or sync client are not silently overwritten.
+pub(crate) fn ensure_unchanged(path: &Path) -> Result<(), AppError> {
+    let Some(Some(fp)) = known(path) else {
+        return Ok(());
+    };
+    // deleted externally: writing it back loses nothing
+    if !path.exists() || matches(path, &fp) {
+        return Ok(());
+    }
+    Err(AppError::new(ErrorKind::Conflict, "storage.conflict").with_param("path", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_is_exclusive_until_stale_or_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        assert!(acquire(root.clone(), false).unwrap().acquired);
+
+        // pretend another live instance holds it
+        let mut other = read_lock(dir.path()).unwrap();
+        other.instance_id = "other".to_string();
+        fs::write(lock_file(dir.path()), serde_json::to_string(&other).unwrap()).unwrap();
+        let status = acquire(root.clone(), false).unwrap();
+        assert!(!status.acquired);
+        assert_eq!(status.holder.unwrap().instance_id, "other");
+
+        // a lock still being written by its creator is held, not stale
+        fs::write(lock_file(dir.path()), "").unwrap();
+        assert!(!acquire(root.clone(), false).unwrap().acquired);
+
+        other.heartbeat_at -= STALE_AFTER_SECS + 1;
+        fs::write(lock_file(dir.path()), serde_json::to_string(&other).unwrap()).unwrap();
+        assert!(acquire(root.clone(), false).unwrap().acquired);
+        assert_eq!(read_lock(dir.path()).unwrap().instance_id, instance_id());
+
+        other.heartbeat_at = now_secs();
+        fs::write(lock_file(dir.path()), serde_json::to_string(&other).unwrap()).unwrap();
+        assert!(acquire(root.clone(), true).unwrap().acquired);
+        release(root).unwrap();
+        assert_eq!(fs::read_dir(dir.path().join(".creatorai")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn detects_external_modification() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapter_001.txt");
+        fs::write(&path, "ours").unwrap();
+        remember(&path, b"ours");
+        assert!(ensure_unchanged(&path).is_ok());
+
+        fs::write(&path, "edited in vim").unwrap();
+        let conflict = ensure_unchanged(&path).unwrap_err();
+        assert_eq!(conflict.kind, ErrorKind::Conflict);
+        assert_eq!(conflict.key, "storage.conflict");
+        forget(&path);
+        assert!(ensure_unchanged(&path).is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::i18n;
+use regex::Regex;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+const FILE_PREFIX: &str = "creatorai";
+const FILE_SUFFIX: &str = "log";
+// one file per day
+const KEEP_FILES: usize = 7;
+
+// keeps the background writer alive (and flushing) for the life of the process
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+// prompts and generated text are logged as their length unless the user opts in
+static LOG_TEXT: AtomicBool = AtomicBool::new(false);
+
+// Sends this crate's info-level events (and everyone else's warnings) to daily log files in
+// `dir`, with secrets masked on the way out.
+pub fn init(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{} {dir:?}: {e}", i18n::t("logging.createDir")))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix(FILE_SUFFIX)
+        .max_log_files(KEEP_FILES)
+        .build(dir)
+        .map_err(|e| format!("{}: {e}", i18n::t("logging.createFile")))?;
+    let (writer, guard) = tracing_appender::non_blocking(Redacting(appender));
+    let filter = Targets::new()
+        .with_default(LevelFilter::WARN)
+        .with_target(env!("CARGO_CRATE_NAME"), LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false))
+        .with(filter)
+        .try_init()
+        .map_err(|e| format!("{}: {e}", i18n::t("logging.init")))?;
+    let _ = GUARD.set(guard);
+    Ok(())
+}
+
+pub fn set_log_text(enabled: bool) {
+    LOG_TEXT.store(enabled, Ordering::Relaxed);
+}
+
+// What to log in place of manuscript text, prompts and model output.
+pub fn text(s: &str) -> String {
+    if LOG_TEXT.load(Ordering::Relaxed) {
+        s.to_string()
+    } else {
+        format!("<{} chars>", s.chars().count())
+    }
+}
+
+// Log files, newest first.
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(FILE_PREFIX) && n.ends_with(FILE_SUFFIX))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    // the date in the name sorts chronologically
+    files.sort();
+    files.reverse();
+    files
+}
+
+fn secret_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r#"(?i)(bearer\s+)[^\s"',]+"#, "${1}[REDACTED]"),
+            (
+                r#"(?i)("?(?:api[_-]?key|authorization|password|passphrase|secret)"?\s*[:=]\s*"?)[^\s"',}]+"#,
+                "${1}[REDACTED]",
+            ),
+            (r"\bsk-[A-Za-z0-9_\-]{8,}", "[REDACTED]"),
+        ]
+        .into_iter()
+        .map(|(re, rep)| (Regex::new(re).expect("valid pattern"), rep))
+        .collect()
+    })
+}
+
+// Masks API keys and other credentials that could end up in a message, e.g. a provider
+// echoing the request headers back in an error.
+pub fn redact(line: &str) -> String {
+    let mut out = line.to_string();
+    for (re, rep) in secret_patterns() {
+        out = re.replace_all(&out, *rep).into_owned();
+    }
+    out
+}
+
+// The formatter hands over one whole event per write.
+struct Redacting<W>(W);
+
+impl<W: Write> Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_credentials_and_text() {
+        let line = r#"request failed: {"error":"bad key sk-abcdef123456"} Authorization: Bearer sk-xyz987654321 "api_key": "k-1""#;
+        let out = redact(line);
+        assert!(!out.contains("abcdef123456") && !out.contains("xyz987654321") && !out.contains("k-1"));
+        assert!(out.starts_with("request failed"));
+        assert_eq!(redact("model=gpt-4o status=200"), "model=gpt-4o status=200");
+
+        assert_eq!(text("秘密的开头"), "<5 chars>");
+
+        let mut buf = vec![];
+        Redacting(&mut buf).write_all(b"password=hunter2\n").unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "password=[REDACTED]\n");
+    }
+}
@@ -1,21 +1,82 @@
-use crate::types::{ChatMessage, Preset};
-
-pub fn build_system_prompt(preset: &Preset, task_action: &str) -> String {
-    let rules_text = preset
-        .rules
-        .iter()
-        .map(|r| format!("- {r}"))
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    let mut base_prompt = format!(
-        "你是一位专业的小说写作助手。\n\n## 写作风格\n{}\n\n## 叙事视角\n{}\n\n## 写作规则\n{}\n",
-        preset.style, preset.pov, rules_text
-    );
-
-    if task_action == "continue" {
-        base_prompt.push_str(
-            r#"
+use crate::error::{AppError, ErrorKind};
+use crate::storage;
+use crate::template::{self, Var, VarKind};
+use crate::types::{ChatMessage, Locale, Preset, PromptTemplate, PromptTemplateName};
+use std::collections::BTreeMap;
+
+// The templates a project overrides, by name; the others use the built-in text.
+pub type Overrides = BTreeMap<PromptTemplateName, String>;
+
+const PRESET_VARS: [Var; 3] = [
+    Var {
+        name: "style",
+        kind: VarKind::Text,
+    },
+    Var {
+        name: "pov",
+        kind: VarKind::Text,
+    },
+    Var {
+        name: "rules",
+        kind: VarKind::List(&[]),
+    },
+];
+
+// Projects have no codex yet, so this list is always empty; templates can already use it.
+const CODEX_VAR: Var = Var {
+    name: "codex",
+    kind: VarKind::List(&["name", "content"]),
+};
+
+const CONTINUE_VARS: [Var; 7] = [
+    PRESET_VARS[0],
+    PRESET_VARS[1],
+    PRESET_VARS[2],
+    CODEX_VAR,
+    Var {
+        name: "summaries",
+        kind: VarKind::List(&["title", "summary"]),
+    },
+    Var {
+        name: "chapter",
+        kind: VarKind::Text,
+    },
+    Var {
+        name: "instruction",
+        kind: VarKind::Text,
+    },
+];
+
+const DISCUSS_VARS: [Var; 4] = [PRESET_VARS[0], PRESET_VARS[1], PRESET_VARS[2], CODEX_VAR];
+
+fn variables(name: PromptTemplateName) -> &'static [Var] {
+    match name {
+        PromptTemplateName::ContinueSystem | PromptTemplateName::ContinueUser => &CONTINUE_VARS,
+        PromptTemplateName::DiscussSystem => &DISCUSS_VARS,
+        PromptTemplateName::SummarizeSystem | PromptTemplateName::Resume => &[],
+    }
+}
+
+const CONTINUE_SYSTEM_ZH: &str = r#"你是一位专业的小说写作助手。
+
+## 写作风格
+{{style}}
+
+## 叙事视角
+{{pov}}
+
+## 写作规则
+{{#each rules}}
+- {{this}}
+{{/each}}
+{{#if codex}}
+
+## 设定资料
+{{#each codex}}
+- {{name}}：{{content}}
+{{/each}}
+{{/if}}
+
 ## 输出要求
 你必须以 JSON 格式输出，包含两个字段：
 1. "content": 生成的正文内容
@@ -30,57 +91,307 @@ pub fn build_system_prompt(preset: &Preset, task_action: &str) -> String {
 ```
 
 只输出 JSON，不要有其他内容。
-"#,
-        );
-    } else {
-        base_prompt.push_str(
-            r#"
+"#;
+
+const CONTINUE_SYSTEM_EN: &str = r#"You are a professional fiction writing assistant.
+
+## Style
+{{style}}
+
+## Point of view
+{{pov}}
+
+## Rules
+{{#each rules}}
+- {{this}}
+{{/each}}
+{{#if codex}}
+
+## Reference
+{{#each codex}}
+- {{name}}: {{content}}
+{{/each}}
+{{/if}}
+
+## Output
+You must answer in JSON with two fields:
+1. "content": the text you wrote
+2. "summary": a short summary of that text (30-60 words)
+
+Example:
+```json
+{
+  "content": "The new passage...",
+  "summary": "What happens in it..."
+}
+```
+
+Output only the JSON, nothing else.
+"#;
+
+const CONTINUE_USER_ZH: &str = "{{#if summaries}}
+## 前文摘要
+{{#each summaries}}
+【{{title}}】{{summary}}
+{{/each}}
+
+{{/if}}
+{{#if chapter}}
+## 当前章节内容
+{{chapter}}
+
+{{/if}}
+## 任务：续写正文
+{{#if instruction}}
+用户说：{{instruction}}
+{{/if}}
+";
+
+const CONTINUE_USER_EN: &str = "{{#if summaries}}
+## Story so far
+{{#each summaries}}
+[{{title}}] {{summary}}
+{{/each}}
+
+{{/if}}
+{{#if chapter}}
+## Current chapter
+{{chapter}}
+
+{{/if}}
+## Task: Continue the text
+{{#if instruction}}
+The author says: {{instruction}}
+{{/if}}
+";
+
+const DISCUSS_SYSTEM_ZH: &str = "你是一位专业的小说写作助手。
+
+## 写作风格
+{{style}}
+
+## 叙事视角
+{{pov}}
+
+## 写作规则
+{{#each rules}}
+- {{this}}
+{{/each}}
+{{#if codex}}
+
+## 设定资料
+{{#each codex}}
+- {{name}}：{{content}}
+{{/each}}
+{{/if}}
+
 ## 输出要求
 你现在是创作顾问模式。请与用户讨论创作思路、情节发展、角色塑造等问题。
 给出专业的建议和灵感启发，像一个有经验的编辑在和作者交流。
 直接用自然语言回复，不需要 JSON 格式。
-"#,
-        );
+";
+
+const DISCUSS_SYSTEM_EN: &str = "You are a professional fiction writing assistant.
+
+## Style
+{{style}}
+
+## Point of view
+{{pov}}
+
+## Rules
+{{#each rules}}
+- {{this}}
+{{/each}}
+{{#if codex}}
+
+## Reference
+{{#each codex}}
+- {{name}}: {{content}}
+{{/each}}
+{{/if}}
+
+## Output
+You are acting as a writing consultant. Discuss ideas, plot development and characters with the user.
+Give professional advice and inspiration, the way an experienced editor talks with an author.
+Reply in plain prose, not JSON.
+";
+
+fn default_source(name: PromptTemplateName, locale: Locale) -> &'static str {
+    let zh = locale == Locale::ZhCn;
+    match name {
+        PromptTemplateName::ContinueSystem if zh => CONTINUE_SYSTEM_ZH,
+        PromptTemplateName::ContinueSystem => CONTINUE_SYSTEM_EN,
+        PromptTemplateName::ContinueUser if zh => CONTINUE_USER_ZH,
+        PromptTemplateName::ContinueUser => CONTINUE_USER_EN,
+        PromptTemplateName::DiscussSystem if zh => DISCUSS_SYSTEM_ZH,
+        PromptTemplateName::DiscussSystem => DISCUSS_SYSTEM_EN,
+        PromptTemplateName::SummarizeSystem if zh => {
+            "你是一位小说编辑。用 50-100 字概括用户给出的小说片段，只输出摘要本身。"
+        }
+        PromptTemplateName::SummarizeSystem => {
+            "You are a fiction editor. Summarize the passage the user gives in 30-60 words. Output only the summary."
+        }
+        PromptTemplateName::Resume if zh => {
+            "输出被截断了。请从中断处紧接着继续输出，不要重复已输出的内容，也不要添加任何说明。"
+        }
+        PromptTemplateName::Resume => {
+            "Your output was cut off. Continue exactly where it stopped, without repeating anything or adding any commentary."
+        }
     }
+}
 
-    base_prompt
+fn parse_checked(name: PromptTemplateName, source: &str) -> Result<template::Template, String> {
+    let template = template::parse(source)?;
+    template.check(variables(name))?;
+    Ok(template)
 }
 
-pub fn build_user_prompt(
-    chapter_summaries: &[(String, String)],
-    current_text: &str,
-    task_action: &str,
-    instruction: &str,
-) -> String {
-    let mut parts: Vec<String> = vec![];
+fn render(
+    overrides: &Overrides,
+    locale: Locale,
+    name: PromptTemplateName,
+    context: &serde_json::Value,
+) -> Result<String, AppError> {
+    let source = overrides
+        .get(&name)
+        .map_or(default_source(name, locale), String::as_str);
+    let template = parse_checked(name, source)
+        .map_err(|e| AppError::new(ErrorKind::Invalid, "prompt.invalidTemplate").caused_by(e))?;
+    Ok(template.render(context).trim_end().to_string())
+}
+
+// The story context a prompt is built from.
+pub struct PromptInput<'a> {
+    pub preset: &'a Preset,
+    // (chapter title, summary), oldest first
+    pub summaries: &'a [(String, String)],
+    pub chapter: &'a str,
+    pub instruction: &'a str,
+}
+
+fn context(input: &PromptInput) -> serde_json::Value {
+    serde_json::json!({
+        "style": input.preset.style,
+        "pov": input.preset.pov,
+        "rules": input.preset.rules,
+        "codex": [],
+        "summaries": input
+            .summaries
+            .iter()
+            .map(|(title, summary)| serde_json::json!({ "title": title, "summary": summary }))
+            .collect::<Vec<_>>(),
+        "chapter": input.chapter,
+        "instruction": input.instruction.trim(),
+    })
+}
+
+pub fn continue_messages(
+    overrides: &Overrides,
+    locale: Locale,
+    input: &PromptInput,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let ctx = context(input);
+    let system = render(overrides, locale, PromptTemplateName::ContinueSystem, &ctx)?;
+    let user = render(overrides, locale, PromptTemplateName::ContinueUser, &ctx)?;
+    Ok(vec![
+        serde_json::json!({ "role": "system", "content": system }),
+        serde_json::json!({ "role": "user", "content": user }),
+    ])
+}
 
-    if !chapter_summaries.is_empty() {
-        parts.push("## 前文摘要".to_string());
-        for (title, summary) in chapter_summaries {
-            parts.push(format!("【{title}】{summary}"));
+pub fn discuss_system(overrides: &Overrides, locale: Locale, preset: &Preset) -> Result<String, AppError> {
+    let input = PromptInput {
+        preset,
+        summaries: &[],
+        chapter: "",
+        instruction: "",
+    };
+    render(overrides, locale, PromptTemplateName::DiscussSystem, &context(&input))
+}
+
+// The fallback request for a continuation that came back without a summary.
+pub fn summary_messages(overrides: &Overrides, locale: Locale, text: &str) -> Result<Vec<serde_json::Value>, AppError> {
+    let system = render(
+        overrides,
+        locale,
+        PromptTemplateName::SummarizeSystem,
+        &serde_json::json!({}),
+    )?;
+    Ok(vec![
+        serde_json::json!({ "role": "system", "content": system }),
+        serde_json::json!({ "role": "user", "content": text }),
+    ])
+}
+
+// Sent after a reply that hit max_tokens, with that reply as the assistant turn.
+pub fn resume_instruction(overrides: &Overrides, locale: Locale) -> Result<String, AppError> {
+    render(overrides, locale, PromptTemplateName::Resume, &serde_json::json!({}))
+}
+
+fn variable_names(name: PromptTemplateName) -> Vec<String> {
+    let mut out = vec![];
+    for var in variables(name) {
+        match var.kind {
+            VarKind::Text => out.push(var.name.to_string()),
+            VarKind::List([]) => out.push(format!("{}[]", var.name)),
+            VarKind::List(fields) => out.extend(fields.iter().map(|f| format!("{}[].{f}", var.name))),
         }
-        parts.push("".to_string());
     }
+    out
+}
 
-    if !current_text.is_empty() {
-        parts.push("## 当前章节内容".to_string());
-        parts.push(current_text.to_string());
-        parts.push("".to_string());
-    }
+// Every template with the text in use: the project's own, else the built-in one.
+pub fn list_templates(project_dir: &str) -> Result<Vec<PromptTemplate>, AppError> {
+    let overrides = storage::load_prompt_templates(project_dir.to_string())?;
+    let locale = storage::project_locale(std::path::Path::new(project_dir));
+    Ok(PromptTemplateName::ALL
+        .into_iter()
+        .map(|name| PromptTemplate {
+            name,
+            source: overrides
+                .get(&name)
+                .cloned()
+                .unwrap_or_else(|| default_source(name, locale).to_string()),
+            customized: overrides.contains_key(&name),
+            variables: variable_names(name),
+        })
+        .collect())
+}
 
-    let action_text = match task_action {
-        "continue" => "续写正文",
-        "discuss" => "讨论创作",
-        "outline" => "生成大纲",
-        "polish" => "润色修改",
-        other => other,
-    };
-    parts.push(format!("## 任务：{action_text}"));
-    if !instruction.trim().is_empty() {
-        parts.push(format!("用户说：{}", instruction.trim()));
+// Rejects a template that doesn't parse or uses a variable it can't have.
+pub fn validate(name: PromptTemplateName, source: &str) -> Result<(), AppError> {
+    parse_checked(name, source)
+        .map(|_| ())
+        .map_err(|e| AppError::new(ErrorKind::Invalid, "prompt.invalidTemplate").caused_by(e))
+}
+
+// Saving the built-in text, or nothing, goes back to the built-in template.
+pub fn save_template(project_dir: &str, name: PromptTemplateName, source: Option<String>) -> Result<(), AppError> {
+    let locale = storage::project_locale(std::path::Path::new(project_dir));
+    let mut overrides = storage::load_prompt_templates(project_dir.to_string())?;
+    match source {
+        Some(source) if source != default_source(name, locale) => {
+            validate(name, &source)?;
+            overrides.insert(name, source);
+        }
+        _ => {
+            overrides.remove(&name);
+        }
     }
+    storage::save_prompt_templates(project_dir.to_string(), &overrides)
+}
 
-    parts.join("\n")
+// A rough token count without the model's tokenizer: about one token per CJK character
+// and one per four characters of anything else.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let (cjk, other) = text.chars().fold((0u64, 0u64), |(cjk, other), c| match c {
+        '\u{3000}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}' | '\u{ff00}'..='\u{ffef}' => {
+            (cjk + 1, other)
+        }
+        _ => (cjk, other + 1),
+    });
+    cjk + other.div_ceil(4)
 }
 
 pub fn now_iso() -> String {
@@ -1,36 +1,64 @@
+use crate::error::{AppError, ErrorKind};
 use keyring::Entry;
 
 const SERVICE: &str = "creatorai-v2";
 
-fn entry(endpoint_id: &str) -> Entry {
-    Entry::new(SERVICE, endpoint_id).expect("keyring entry")
+fn entry(endpoint_id: &str) -> Result<Entry, AppError> {
+    Entry::new(SERVICE, endpoint_id).map_err(|e| AppError::new(ErrorKind::Io, "secure.keychain").caused_by(e))
 }
 
-pub fn has_api_key(endpoint_id: &str) -> Result<bool, String> {
-    match entry(endpoint_id).get_password() {
+pub fn has_api_key(endpoint_id: &str) -> Result<bool, AppError> {
+    match entry(endpoint_id)?.get_password() {
         Ok(v) => Ok(!v.is_empty()),
         Err(keyring::Error::NoEntry) => Ok(false),
-        Err(e) => Err(format!("读取 API Key 失败: {e}")),
+        Err(e) => Err(AppError::new(ErrorKind::Io, "secure.readApiKey").caused_by(e)),
     }
 }
 
-pub fn set_api_key(endpoint_id: &str, api_key: &str) -> Result<(), String> {
-    entry(endpoint_id)
+pub fn set_api_key(endpoint_id: &str, api_key: &str) -> Result<(), AppError> {
+    entry(endpoint_id)?
         .set_password(api_key)
-        .map_err(|e| format!("保存 API Key 失败: {e}"))
+        .map_err(|e| AppError::new(ErrorKind::Io, "secure.saveApiKey").caused_by(e))
 }
 
-pub fn delete_api_key(endpoint_id: &str) -> Result<(), String> {
-    match entry(endpoint_id).delete_password() {
+pub fn delete_api_key(endpoint_id: &str) -> Result<(), AppError> {
+    match entry(endpoint_id)?.delete_password() {
         Ok(_) => Ok(()),
         Err(keyring::Error::NoEntry) => Ok(()),
-        Err(e) => Err(format!("删除 API Key 失败: {e}")),
+        Err(e) => Err(AppError::new(ErrorKind::Io, "secure.deleteApiKey").caused_by(e)),
     }
 }
 
-pub fn get_api_key(endpoint_id: &str) -> Result<String, String> {
-    entry(endpoint_id)
+// Keys of encrypted projects the user chose to remember on this machine.
+fn project_key_entry(key_id: &str) -> Result<Entry, AppError> {
+    entry(&format!("project-key:{key_id}"))
+}
+
+pub fn get_project_key(key_id: &str) -> Result<Option<String>, AppError> {
+    match project_key_entry(key_id)?.get_password() {
+        Ok(v) => Ok(Some(v)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::new(ErrorKind::Io, "secure.readProjectKey").caused_by(e)),
+    }
+}
+
+pub fn set_project_key(key_id: &str, key: &str) -> Result<(), AppError> {
+    project_key_entry(key_id)?
+        .set_password(key)
+        .map_err(|e| AppError::new(ErrorKind::Io, "secure.saveProjectKey").caused_by(e))
+}
+
+pub fn delete_project_key(key_id: &str) -> Result<(), AppError> {
+    match project_key_entry(key_id)?.delete_password() {
+        Ok(_) => Ok(()),
+        Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::new(ErrorKind::Io, "secure.deleteProjectKey").caused_by(e)),
+    }
+}
+
+pub fn get_api_key(endpoint_id: &str) -> Result<String, AppError> {
+    entry(endpoint_id)?
         .get_password()
-        .map_err(|e| format!("读取 API Key 失败: {e}"))
+        .map_err(|e| AppError::new(ErrorKind::Io, "secure.readApiKey").caused_by(e))
 }
 
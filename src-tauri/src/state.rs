@@ -1,56 +1,82 @@
+use crate::error::{AppError, ErrorKind};
 use crate::types::AppState;
+use crate::{durable, i18n, logging, usage};
 use serde_json::json;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::Manager;
 
-fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
     app.path()
         .app_data_dir()
-        .map_err(|e| format!("无法获取应用数据目录: {e}"))
+        .map_err(|e| AppError::new(ErrorKind::Io, "state.appDataDir").caused_by(e))
 }
 
-pub fn default_project_dir(app: &tauri::AppHandle) -> Result<String, String> {
+pub fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app_data_dir(app)?.join("logs"))
+}
+
+pub fn usage_file(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app_data_dir(app)?.join("usage.jsonl"))
+}
+
+pub fn default_project_dir(app: &tauri::AppHandle) -> Result<String, AppError> {
     let dir = app_data_dir(app)?.join("MyNovel");
     Ok(dir.to_string_lossy().to_string())
 }
 
-fn state_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn state_file(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
     Ok(app_data_dir(app)?.join("app_state.json"))
 }
 
-pub fn ensure_app_state_file(app: &tauri::AppHandle) -> Result<(), String> {
+pub fn ensure_app_state_file(app: &tauri::AppHandle) -> Result<(), AppError> {
     let dir = app_data_dir(app)?;
-    fs::create_dir_all(&dir).map_err(|e| format!("无法创建应用数据目录: {e}"))?;
+    fs::create_dir_all(&dir).map_err(|e| AppError::new(ErrorKind::Io, "state.createAppDataDir").caused_by(e))?;
+    durable::recover(&dir, |_| true);
 
     let file = state_file(app)?;
     if !file.exists() {
-        fs::write(&file, json!({}).to_string())
-            .map_err(|e| format!("无法写入 app_state.json: {e}"))?;
+        durable::write(&file, json!({}).to_string().as_bytes()).map_err(|e| {
+            AppError::new(ErrorKind::Io, "file.write")
+                .with_param("file", "app_state.json")
+                .caused_by(e)
+        })?;
     }
     Ok(())
 }
 
-pub fn load_app_state(app: &tauri::AppHandle) -> Result<AppState, String> {
+pub fn load_app_state(app: &tauri::AppHandle) -> Result<AppState, AppError> {
     ensure_app_state_file(app)?;
     let file = state_file(app)?;
-    let raw = fs::read_to_string(&file).map_err(|e| format!("无法读取 app_state.json: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("app_state.json 格式错误: {e}"))
+    let raw = fs::read_to_string(&file).map_err(|e| AppError::read("app_state.json", e))?;
+    let state: AppState = serde_json::from_str(&raw).map_err(|e| AppError::malformed("app_state.json", e))?;
+    apply(&state);
+    Ok(state)
 }
 
-pub fn save_app_state(app: &tauri::AppHandle, next: &AppState) -> Result<(), String> {
+pub fn save_app_state(app: &tauri::AppHandle, next: &AppState) -> Result<(), AppError> {
     ensure_app_state_file(app)?;
     let file = state_file(app)?;
-    atomic_write_json(&file, next)
+    atomic_write_json(&file, next)?;
+    apply(next);
+    Ok(())
+}
+
+// Settings in the app state that the backend itself follows.
+fn apply(state: &AppState) {
+    i18n::set_locale(state.locale);
+    logging::set_log_text(state.log_full_text);
+    usage::set_budget(state.budget.clone());
 }
 
-fn atomic_write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), String> {
-    let dir = path.parent().ok_or("无效路径")?;
-    fs::create_dir_all(dir).map_err(|e| format!("无法创建目录: {e}"))?;
+fn atomic_write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), AppError> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorKind::Invalid, "io.invalidPath"))?;
+    fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
 
-    let tmp = path.with_extension("json.tmp");
-    let raw = serde_json::to_string_pretty(value).map_err(|e| format!("序列化失败: {e}"))?;
-    fs::write(&tmp, raw).map_err(|e| format!("写入临时文件失败: {e}"))?;
-    fs::rename(&tmp, path).map_err(|e| format!("保存失败: {e}"))?;
+    let raw =
+        serde_json::to_string_pretty(value).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?;
+    durable::write(path, raw.as_bytes()).map_err(|e| AppError::io("io.save", path, e))?;
     Ok(())
 }
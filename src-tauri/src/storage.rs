@@ -1,18 +1,28 @@
+use crate::error::{AppError, ErrorKind};
 use crate::types::*;
+use crate::{crypto, durable, i18n, lock};
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+// Layout version written to project.json. Bump it together with a new entry in MIGRATIONS.
+pub const SCHEMA_VERSION: u32 = 1;
+
 fn p(project_dir: String) -> PathBuf {
     PathBuf::from(project_dir)
 }
 
-fn chapters_dir(project_dir: &Path) -> PathBuf {
+pub(crate) fn manifest_file(project_dir: &Path) -> PathBuf {
+    project_dir.join("project.json")
+}
+
+pub(crate) fn chapters_dir(project_dir: &Path) -> PathBuf {
     project_dir.join("chapters")
 }
 
-fn chat_sessions_dir(project_dir: &Path) -> PathBuf {
+pub(crate) fn chat_sessions_dir(project_dir: &Path) -> PathBuf {
     project_dir.join("chat_sessions")
 }
 
@@ -24,63 +34,294 @@ fn vectors_dir(project_dir: &Path) -> PathBuf {
     creatorai_dir(project_dir).join("vectors")
 }
 
-fn chapters_index_file(project_dir: &Path) -> PathBuf {
+pub(crate) fn backups_dir(project_dir: &Path) -> PathBuf {
+    creatorai_dir(project_dir).join("backups")
+}
+
+fn backup_settings_file(project_dir: &Path) -> PathBuf {
+    creatorai_dir(project_dir).join("backup_settings.json")
+}
+
+fn versioning_settings_file(project_dir: &Path) -> PathBuf {
+    creatorai_dir(project_dir).join("versioning.json")
+}
+
+pub(crate) fn llm_log_dir(project_dir: &Path) -> PathBuf {
+    creatorai_dir(project_dir).join("llm_log")
+}
+
+pub(crate) fn llm_log_index_file(project_dir: &Path) -> PathBuf {
+    llm_log_dir(project_dir).join("index.json")
+}
+
+pub(crate) fn llm_log_entry_file(project_dir: &Path, id: &str) -> PathBuf {
+    llm_log_dir(project_dir).join(format!("entry_{id}.json"))
+}
+
+pub(crate) fn chapters_index_file(project_dir: &Path) -> PathBuf {
     chapters_dir(project_dir).join("index.json")
 }
 
-fn chapter_txt(project_dir: &Path, id: u32) -> PathBuf {
+pub(crate) fn chapter_txt(project_dir: &Path, id: u32) -> PathBuf {
     chapters_dir(project_dir).join(format!("chapter_{id:03}.txt"))
 }
 
-fn chapter_meta(project_dir: &Path, id: u32) -> PathBuf {
+pub(crate) fn chapter_meta(project_dir: &Path, id: u32) -> PathBuf {
     chapters_dir(project_dir).join(format!("chapter_{id:03}.json"))
 }
 
-fn config_file(project_dir: &Path) -> PathBuf {
+pub(crate) fn config_file(project_dir: &Path) -> PathBuf {
     project_dir.join("config.json")
 }
 
-fn llm_config_file(project_dir: &Path) -> PathBuf {
+pub(crate) fn llm_config_file(project_dir: &Path) -> PathBuf {
     project_dir.join("llm_config.json")
 }
 
-fn summaries_file(project_dir: &Path) -> PathBuf {
+pub(crate) fn prompts_file(project_dir: &Path) -> PathBuf {
+    project_dir.join("prompts.json")
+}
+
+pub(crate) fn summaries_file(project_dir: &Path) -> PathBuf {
     project_dir.join("summaries.json")
 }
 
-fn sessions_index_file(project_dir: &Path) -> PathBuf {
+pub(crate) fn sessions_index_file(project_dir: &Path) -> PathBuf {
     chat_sessions_dir(project_dir).join("index.json")
 }
 
-fn session_file(project_dir: &Path, session_id: &str) -> PathBuf {
+pub(crate) fn session_file(project_dir: &Path, session_id: &str) -> PathBuf {
     chat_sessions_dir(project_dir).join(format!("session_{session_id}.json"))
 }
 
-fn ensure_dir(dir: &Path) -> Result<(), String> {
-    fs::create_dir_all(dir).map_err(|e| format!("无法创建目录 {dir:?}: {e}"))
+// Finishes or discards writes that a crash interrupted, in the folders the app writes to. At
+// the top of the project only the app's own files are considered.
+fn recover_writes(root: &Path) {
+    let top = [
+        manifest_file(root),
+        config_file(root),
+        llm_config_file(root),
+        prompts_file(root),
+        summaries_file(root),
+    ];
+    durable::recover(root, |target| top.iter().any(|f| f == target));
+    for dir in [
+        chapters_dir(root),
+        chat_sessions_dir(root),
+        creatorai_dir(root),
+        vectors_dir(root),
+        backups_dir(root),
+        llm_log_dir(root),
+    ] {
+        durable::recover(&dir, |_| true);
+    }
+}
+
+fn ensure_dir(dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))
+}
+
+// Reads a project file and records its fingerprint, so a later write can tell whether
+// someone else changed it in the meantime. Encrypted files are decrypted here. `what` names
+// the file in errors.
+pub(crate) fn read_tracked(path: &Path, what: &str) -> Result<String, AppError> {
+    let raw = fs::read(path).map_err(|e| AppError::read(what, e))?;
+    lock::remember(path, &raw);
+    let plain = crypto::decode(path, raw)?;
+    String::from_utf8(plain).map_err(|e| AppError::malformed(what, e))
 }
 
-fn atomic_write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), String> {
-    let dir = path.parent().ok_or("无效路径")?;
+pub(crate) fn write_tracked(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    lock::ensure_unchanged(path)?;
+    let out = crypto::encode(path, contents)?;
+    durable::write(path, &out).map_err(|e| AppError::io("io.save", path, e))?;
+    lock::remember(path, &out);
+    Ok(())
+}
+
+pub(crate) fn atomic_write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), AppError> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::new(ErrorKind::Invalid, "io.invalidPath"))?;
     ensure_dir(dir)?;
 
-    let tmp = path.with_extension("json.tmp");
-    let raw = serde_json::to_string_pretty(value).map_err(|e| format!("序列化失败: {e}"))?;
-    fs::write(&tmp, raw).map_err(|e| format!("写入失败 {tmp:?}: {e}"))?;
-    fs::rename(&tmp, path).map_err(|e| format!("保存失败 {path:?}: {e}"))?;
+    let mut raw = serde_json::to_string_pretty(value)
+        .map_err(|e| AppError::new(ErrorKind::Other, "io.serialize").caused_by(e))?;
+    // trailing newline keeps diffs clean for projects tracked in git
+    raw.push('\n');
+    write_tracked(path, raw.as_bytes())
+}
+
+// Every file that belongs to the project, as (relative "/"-separated path, absolute path).
+// Git metadata, backup archives, the model call log and in-flight temp files are not part of
+// the project.
+pub(crate) fn project_files(project_dir: &Path) -> Result<Vec<(String, PathBuf)>, AppError> {
+    fn walk(root: &Path, dir: &Path, skip: &[PathBuf], out: &mut Vec<(String, PathBuf)>) -> Result<(), AppError> {
+        let entries = fs::read_dir(dir).map_err(|e| AppError::io("io.readDir", dir, e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if skip.contains(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                walk(root, &path, skip, out)?;
+                continue;
+            }
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if name.ends_with(".tmp") || name == ".DS_Store" {
+                continue;
+            }
+            let rel = path
+                .strip_prefix(root)
+                .map_err(|e| AppError::io("io.invalidPath", &path, e))?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((rel, path));
+        }
+        Ok(())
+    }
+
+    let skip = [
+        project_dir.join(".git"),
+        backups_dir(project_dir),
+        llm_log_dir(project_dir),
+        lock::lock_file(project_dir),
+    ];
+    let mut files = vec![];
+    walk(project_dir, project_dir, &skip, &mut files)?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+// Whether `rel` (relative, '/'-separated) is one of the files the app keeps in a project, as
+// opposed to anything else the user stores in the folder.
+pub(crate) fn is_app_file(rel: &str) -> bool {
+    const TOP: &[&str] = &[
+        "project.json",
+        "config.json",
+        "llm_config.json",
+        "prompts.json",
+        "summaries.json",
+        "project.db",
+    ];
+    const DIRS: &[&str] = &["chapters/", "chat_sessions/", ".creatorai/"];
+    TOP.contains(&rel) || DIRS.iter().any(|dir| rel.starts_with(dir))
+}
+
+pub fn load_manifest(project_dir: String) -> Result<Option<ProjectManifest>, AppError> {
+    let file = manifest_file(&p(project_dir));
+    if !file.exists() {
+        return Ok(None);
+    }
+    let raw = read_tracked(&file, "project.json")?;
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|e| AppError::malformed("project.json", e))
+}
+
+fn write_manifest(root: &Path, schema_version: u32, migrated: bool, locale: Locale) -> Result<(), AppError> {
+    let manifest = ProjectManifest {
+        schema_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        migrated_at: migrated.then(crate::prompt::now_iso),
+        backend: project_backend(root),
+        locale,
+    };
+    atomic_write_json(&manifest_file(root), &manifest)
+}
+
+pub(crate) fn project_backend(root: &Path) -> StorageBackend {
+    load_manifest(root.to_string_lossy().to_string())
+        .ok()
+        .flatten()
+        .map(|m| m.backend)
+        .unwrap_or_default()
+}
+
+pub(crate) fn project_locale(root: &Path) -> Locale {
+    load_manifest(root.to_string_lossy().to_string())
+        .ok()
+        .flatten()
+        .map(|m| m.locale)
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_project_backend(root: &Path, backend: StorageBackend) -> Result<(), AppError> {
+    let mut manifest = load_manifest(root.to_string_lossy().to_string())?
+        .ok_or_else(|| AppError::new(ErrorKind::NotFound, "storage.missingManifest"))?;
+    manifest.backend = backend;
+    manifest.app_version = env!("CARGO_PKG_VERSION").to_string();
+    atomic_write_json(&manifest_file(root), &manifest)
+}
+
+// Version 0 is every project written before project.json existed. Chapters could be
+// listed in the index with only a .txt, which later code expects to have a meta file.
+fn migrate_v0_to_v1(root: &Path) -> Result<(), AppError> {
+    let index = list_chapters(root.to_string_lossy().to_string())?;
+    for item in index {
+        let meta = chapter_meta(root, item.id);
+        if chapter_txt(root, item.id).exists() && !meta.exists() {
+            atomic_write_json(&meta, &json!({ "id": item.id, "title": item.title, "summary": "" }))?;
+        }
+    }
     Ok(())
 }
 
-pub fn init_project(project_dir: String) -> Result<ProjectInfo, String> {
+type Migration = fn(&Path) -> Result<(), AppError>;
+
+// MIGRATIONS[n] upgrades a project from schema version n to n + 1.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+// Brings an existing project up to SCHEMA_VERSION. The original files are archived into the
+// backups folder first, and the manifest is bumped after every step so a failed upgrade
+// resumes where it stopped.
+fn migrate_project(root: &Path) -> Result<(), AppError> {
+    let legacy = chapters_index_file(root).exists() || config_file(root).exists();
+    let version = match load_manifest(root.to_string_lossy().to_string())? {
+        Some(m) => m.schema_version,
+        None if legacy => 0,
+        // a new project is written in the interface language
+        None => return write_manifest(root, SCHEMA_VERSION, false, i18n::locale()),
+    };
+    if version > SCHEMA_VERSION {
+        return Err(AppError::new(ErrorKind::Invalid, "storage.schemaTooNew")
+            .with_param("version", version)
+            .with_param("supported", SCHEMA_VERSION));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    crate::backup::backup_before_migration(root, version)?;
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        step(root).map_err(|e| {
+            AppError::new(e.kind, "storage.migrationFailed")
+                .with_param("version", from)
+                .caused_by(e)
+        })?;
+        write_manifest(root, from as u32 + 1, true, project_locale(root))?;
+    }
+    Ok(())
+}
+
+pub fn init_project(project_dir: String) -> Result<ProjectInfo, AppError> {
     let root = p(project_dir);
+    let _write = lock::write_guard(&root);
     ensure_dir(&root)?;
+    // without the project lock, temps may belong to another instance still writing them
+    if lock::holds(&root) {
+        recover_writes(&root);
+    }
+    migrate_project(&root)?;
     ensure_dir(&chapters_dir(&root))?;
     ensure_dir(&chat_sessions_dir(&root))?;
     ensure_dir(&vectors_dir(&root))?;
 
     // preset
     if !config_file(&root).exists() {
-        atomic_write_json(&config_file(&root), &Preset::default_zh())?;
+        atomic_write_json(&config_file(&root), &Preset::default_for(project_locale(&root)))?;
     }
 
     // llm config
@@ -90,14 +331,21 @@ pub fn init_project(project_dir: String) -> Result<ProjectInfo, String> {
             "https://api.openai.com/v1".to_string(),
             "gemini-3-pro-preview".to_string(),
         );
-        let cfg = LlmConfig {
+        let mut cfg = LlmConfig {
             endpoints: vec![default_endpoint.clone()],
             active_endpoint_id: Some(default_endpoint.id),
             active_model: Some(default_endpoint.default_model),
+            profiles: Default::default(),
         };
+        cfg.fill_default_profiles();
         atomic_write_json(&llm_config_file(&root), &cfg)?;
     }
 
+    // chapters, summaries and sessions of SQLite projects live in project.db
+    if project_backend(&root) == StorageBackend::Sqlite {
+        return Ok(project_info(&root));
+    }
+
     // summaries
     if !summaries_file(&root).exists() {
         atomic_write_json(&summaries_file(&root), &json!([]))?;
@@ -107,35 +355,45 @@ pub fn init_project(project_dir: String) -> Result<ProjectInfo, String> {
     if !chapters_index_file(&root).exists() {
         let first = Chapter {
             id: 1,
-            title: "第一章".to_string(),
+            title: i18n::text(project_locale(&root), "chapter.first").to_string(),
             content: "".to_string(),
             summary: "".to_string(),
         };
         save_chapter(root.to_string_lossy().to_string(), &first)?;
-        atomic_write_json(&chapters_index_file(&root), &vec![ChapterIndexItem { id: 1, title: first.title }])?;
+        let index = vec![ChapterIndexItem {
+            id: 1,
+            title: first.title,
+            volume: None,
+        }];
+        atomic_write_json(&chapters_index_file(&root), &index)?;
     }
 
-    Ok(ProjectInfo {
+    Ok(project_info(&root))
+}
+
+fn project_info(root: &Path) -> ProjectInfo {
+    ProjectInfo {
         project_dir: root.to_string_lossy().to_string(),
         project_name: root
             .file_name()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| "Project".to_string()),
-    })
+    }
 }
 
-pub fn list_chapters(project_dir: String) -> Result<Vec<ChapterIndexItem>, String> {
+pub fn list_chapters(project_dir: String) -> Result<Vec<ChapterIndexItem>, AppError> {
     let root = p(project_dir);
     let index_file = chapters_index_file(&root);
     if !index_file.exists() {
         return Ok(vec![]);
     }
-    let raw = fs::read_to_string(index_file).map_err(|e| format!("无法读取章节索引: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("章节索引格式错误: {e}"))
+    let raw = read_tracked(&index_file, i18n::t("what.chapterIndex"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.chapterIndex"), e))
 }
 
-pub fn create_chapter(project_dir: String, title: String) -> Result<ChapterIndexItem, String> {
+pub fn create_chapter(project_dir: String, title: String) -> Result<ChapterIndexItem, AppError> {
     let root = p(project_dir.clone());
+    let _write = lock::write_guard(&root);
     let mut index = list_chapters(project_dir.clone())?;
     let next_id = index.iter().map(|c| c.id).max().unwrap_or(0) + 1;
 
@@ -147,14 +405,19 @@ pub fn create_chapter(project_dir: String, title: String) -> Result<ChapterIndex
     };
     save_chapter(project_dir.clone(), &ch)?;
 
-    let item = ChapterIndexItem { id: next_id, title };
+    let item = ChapterIndexItem {
+        id: next_id,
+        title,
+        volume: None,
+    };
     index.push(item.clone());
     atomic_write_json(&chapters_index_file(&root), &index)?;
     Ok(item)
 }
 
-pub fn rename_chapter(project_dir: String, id: u32, title: String) -> Result<(), String> {
+pub fn rename_chapter(project_dir: String, id: u32, title: String) -> Result<(), AppError> {
     let root = p(project_dir.clone());
+    let _write = lock::write_guard(&root);
     let mut index = list_chapters(project_dir.clone())?;
     for item in index.iter_mut() {
         if item.id == id {
@@ -169,34 +432,53 @@ pub fn rename_chapter(project_dir: String, id: u32, title: String) -> Result<(),
     Ok(())
 }
 
-pub fn delete_chapter(project_dir: String, id: u32) -> Result<(), String> {
+pub fn set_chapter_volume(project_dir: String, id: u32, volume: Option<String>) -> Result<(), AppError> {
     let root = p(project_dir.clone());
-    let mut index = list_chapters(project_dir.clone())?;
-    index.retain(|c| c.id != id);
-    atomic_write_json(&chapters_index_file(&root), &index)?;
+    let _write = lock::write_guard(&root);
+    let mut index = list_chapters(project_dir)?;
+    let volume = volume.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    for item in index.iter_mut() {
+        if item.id == id {
+            item.volume = volume.clone();
+        }
+    }
+    atomic_write_json(&chapters_index_file(&root), &index)
+}
 
-    let txt = chapter_txt(&root, id);
-    let meta = chapter_meta(&root, id);
-    let _ = fs::remove_file(txt);
-    let _ = fs::remove_file(meta);
-    Ok(())
+pub fn delete_chapter(project_dir: String, id: u32) -> Result<(), AppError> {
+    let root = p(project_dir.clone());
+    let _write = lock::write_guard(&root);
+    // remove the files first so a failure leaves the chapter listed rather than orphaned
+    for file in [chapter_txt(&root, id), chapter_meta(&root, id)] {
+        match fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(AppError::io("storage.deleteChapterFile", &file, e));
+            }
+            _ => lock::remember_removed(&file),
+        }
+    }
+
+    let mut index = list_chapters(project_dir)?;
+    index.retain(|c| c.id != id);
+    atomic_write_json(&chapters_index_file(&root), &index)
 }
 
-pub fn load_chapter(project_dir: String, id: u32) -> Result<Chapter, String> {
+pub fn load_chapter(project_dir: String, id: u32) -> Result<Chapter, AppError> {
     let root = p(project_dir);
     let txt = chapter_txt(&root, id);
     if !txt.exists() {
-        return Err("章节不存在".to_string());
+        return Err(AppError::new(ErrorKind::NotFound, "storage.chapterNotFound"));
     }
-    let content = fs::read_to_string(txt).map_err(|e| format!("无法读取章节正文: {e}"))?;
+    let content = read_tracked(&txt, i18n::t("what.chapterText"))?;
 
-    let mut title = format!("第{id}章");
+    let mut title = i18n::text_with(project_locale(&root), "chapter.numbered", &[("id", &id)]);
     let mut summary = "".to_string();
 
     let meta = chapter_meta(&root, id);
     if meta.exists() {
-        let raw = fs::read_to_string(meta).map_err(|e| format!("无法读取章节元数据: {e}"))?;
-        let v: serde_json::Value = serde_json::from_str(&raw).map_err(|e| format!("章节元数据格式错误: {e}"))?;
+        let raw = read_tracked(&meta, i18n::t("what.chapterMeta"))?;
+        let v: serde_json::Value =
+            serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.chapterMeta"), e))?;
         if let Some(t) = v.get("title").and_then(|x| x.as_str()) {
             title = t.to_string();
         }
@@ -215,12 +497,13 @@ pub fn load_chapter(project_dir: String, id: u32) -> Result<Chapter, String> {
     Ok(Chapter { id, title, content, summary })
 }
 
-pub fn save_chapter(project_dir: String, chapter: &Chapter) -> Result<(), String> {
+pub fn save_chapter(project_dir: String, chapter: &Chapter) -> Result<(), AppError> {
     let root = p(project_dir.clone());
+    let _write = lock::write_guard(&root);
     ensure_dir(&chapters_dir(&root))?;
 
-    fs::write(chapter_txt(&root, chapter.id), &chapter.content)
-        .map_err(|e| format!("保存章节正文失败: {e}"))?;
+    let txt = chapter_txt(&root, chapter.id);
+    write_tracked(&txt, chapter.content.as_bytes())?;
 
     let meta = json!({
       "id": chapter.id,
@@ -242,85 +525,153 @@ pub fn save_chapter(project_dir: String, chapter: &Chapter) -> Result<(), String
         index.push(ChapterIndexItem {
             id: chapter.id,
             title: chapter.title.clone(),
+            volume: None,
         });
     }
     atomic_write_json(&chapters_index_file(&root), &index)?;
     Ok(())
 }
 
-pub fn load_summaries(project_dir: String) -> Result<Vec<SummaryRecord>, String> {
+// Saves over changes made outside the app, once the user chose to keep this version.
+pub fn save_chapter_overwriting(project_dir: String, chapter: &Chapter) -> Result<(), AppError> {
+    let root = p(project_dir.clone());
+    lock::forget(&chapter_txt(&root, chapter.id));
+    lock::forget(&chapter_meta(&root, chapter.id));
+    save_chapter(project_dir, chapter)
+}
+
+pub fn load_summaries(project_dir: String) -> Result<Vec<SummaryRecord>, AppError> {
     let root = p(project_dir);
     let file = summaries_file(&root);
     if !file.exists() {
         return Ok(vec![]);
     }
-    let raw = fs::read_to_string(file).map_err(|e| format!("无法读取 summaries.json: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("summaries.json 格式错误: {e}"))
+    let raw = read_tracked(&file, "summaries.json")?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed("summaries.json", e))
 }
 
-pub fn append_summary(project_dir: String, record: SummaryRecord) -> Result<(), String> {
+pub fn append_summary(project_dir: String, record: SummaryRecord) -> Result<(), AppError> {
     let root = p(project_dir);
+    let _write = lock::write_guard(&root);
     let mut all = load_summaries(root.to_string_lossy().to_string())?;
     all.push(record);
     atomic_write_json(&summaries_file(&root), &all)?;
     Ok(())
 }
 
-pub fn load_preset(project_dir: String) -> Result<Preset, String> {
+pub fn load_preset(project_dir: String) -> Result<Preset, AppError> {
     let root = p(project_dir);
     let file = config_file(&root);
     if !file.exists() {
-        return Ok(Preset::default_zh());
+        return Ok(Preset::default_for(project_locale(&root)));
     }
-    let raw = fs::read_to_string(file).map_err(|e| format!("无法读取 config.json: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("config.json 格式错误: {e}"))
+    let raw = read_tracked(&file, "config.json")?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed("config.json", e))
 }
 
-pub fn save_preset(project_dir: String, preset: &Preset) -> Result<(), String> {
+pub fn save_preset(project_dir: String, preset: &Preset) -> Result<(), AppError> {
     let root = p(project_dir);
+    let _write = lock::write_guard(&root);
     atomic_write_json(&config_file(&root), preset)
 }
 
-pub fn export_preset(file_path: String, preset: &Preset) -> Result<(), String> {
+pub fn export_preset(file_path: String, preset: &Preset) -> Result<(), AppError> {
     atomic_write_json(Path::new(&file_path), preset)
 }
 
-pub fn import_preset(file_path: String) -> Result<Preset, String> {
-    let raw = fs::read_to_string(&file_path).map_err(|e| format!("无法读取预设文件: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("预设文件格式错误: {e}"))
+pub fn import_preset(file_path: String) -> Result<Preset, AppError> {
+    let raw = fs::read_to_string(&file_path).map_err(|e| AppError::read(i18n::t("what.presetFile"), e))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.presetFile"), e))
 }
 
-pub fn load_llm_config(project_dir: String) -> Result<LlmConfig, String> {
+pub fn load_llm_config(project_dir: String) -> Result<LlmConfig, AppError> {
     let root = p(project_dir);
     let file = llm_config_file(&root);
+    let mut cfg = if file.exists() {
+        let raw = read_tracked(&file, "llm_config.json")?;
+        serde_json::from_str::<LlmConfig>(&raw).map_err(|e| AppError::malformed("llm_config.json", e))?
+    } else {
+        LlmConfig::default()
+    };
+    // projects from before task profiles, or from before a task was added
+    cfg.fill_default_profiles();
+    Ok(cfg)
+}
+
+pub fn save_llm_config(project_dir: String, cfg: &LlmConfig) -> Result<(), AppError> {
+    let root = p(project_dir);
+    let _write = lock::write_guard(&root);
+    atomic_write_json(&llm_config_file(&root), cfg)
+}
+
+// Only the templates the project overrides.
+pub fn load_prompt_templates(project_dir: String) -> Result<BTreeMap<PromptTemplateName, String>, AppError> {
+    let file = prompts_file(&p(project_dir));
     if !file.exists() {
-        return Ok(LlmConfig::default());
+        return Ok(BTreeMap::new());
     }
-    let raw = fs::read_to_string(file).map_err(|e| format!("无法读取 llm_config.json: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("llm_config.json 格式错误: {e}"))
+    let raw = read_tracked(&file, "prompts.json")?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed("prompts.json", e))
 }
 
-pub fn save_llm_config(project_dir: String, cfg: &LlmConfig) -> Result<(), String> {
+pub fn save_prompt_templates(
+    project_dir: String,
+    templates: &BTreeMap<PromptTemplateName, String>,
+) -> Result<(), AppError> {
     let root = p(project_dir);
-    atomic_write_json(&llm_config_file(&root), cfg)
+    let _write = lock::write_guard(&root);
+    atomic_write_json(&prompts_file(&root), templates)
+}
+
+pub fn load_backup_settings(project_dir: String) -> Result<BackupSettings, AppError> {
+    let root = p(project_dir);
+    let file = backup_settings_file(&root);
+    if !file.exists() {
+        return Ok(BackupSettings::default());
+    }
+    let raw = read_tracked(&file, i18n::t("what.backupSettings"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.backupSettings"), e))
+}
+
+pub fn save_backup_settings(project_dir: String, settings: &BackupSettings) -> Result<(), AppError> {
+    let root = p(project_dir);
+    let _write = lock::write_guard(&root);
+    atomic_write_json(&backup_settings_file(&root), settings)
 }
 
-pub fn list_chat_sessions(project_dir: String) -> Result<Vec<ChatSessionIndexItem>, String> {
+pub fn load_versioning_settings(project_dir: String) -> Result<VersioningSettings, AppError> {
+    let root = p(project_dir);
+    let file = versioning_settings_file(&root);
+    if !file.exists() {
+        return Ok(VersioningSettings::default());
+    }
+    let raw = read_tracked(&file, i18n::t("what.versioningSettings"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.versioningSettings"), e))
+}
+
+pub fn save_versioning_settings(project_dir: String, settings: &VersioningSettings) -> Result<(), AppError> {
+    let root = p(project_dir);
+    let _write = lock::write_guard(&root);
+    atomic_write_json(&versioning_settings_file(&root), settings)
+}
+
+pub fn list_chat_sessions(project_dir: String) -> Result<Vec<ChatSessionIndexItem>, AppError> {
     let root = p(project_dir);
     let idx = sessions_index_file(&root);
     if !idx.exists() {
         return Ok(vec![]);
     }
-    let raw = fs::read_to_string(idx).map_err(|e| format!("无法读取会话索引: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("会话索引格式错误: {e}"))
+    let raw = read_tracked(&idx, i18n::t("what.sessionIndex"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.sessionIndex"), e))
 }
 
-pub fn create_chat_session(project_dir: String, title: Option<String>) -> Result<ChatSessionIndexItem, String> {
+pub fn create_chat_session(project_dir: String, title: Option<String>) -> Result<ChatSessionIndexItem, AppError> {
     let root = p(project_dir.clone());
+    let _write = lock::write_guard(&root);
     ensure_dir(&chat_sessions_dir(&root))?;
 
     let id = Uuid::new_v4().to_string();
-    let title = title.unwrap_or_else(|| "新对话".to_string());
+    let title = title.unwrap_or_else(|| i18n::text(project_locale(&root), "session.defaultTitle").to_string());
     let session = ChatSession {
         id: id.clone(),
         title: title.clone(),
@@ -330,22 +681,23 @@ pub fn create_chat_session(project_dir: String, title: Option<String>) -> Result
     Ok(ChatSessionIndexItem { id, title })
 }
 
-pub fn load_chat_session(project_dir: String, session_id: String) -> Result<ChatSession, String> {
+pub fn load_chat_session(project_dir: String, session_id: String) -> Result<ChatSession, AppError> {
     let root = p(project_dir);
     let file = session_file(&root, &session_id);
     if !file.exists() {
         return Ok(ChatSession {
             id: session_id,
-            title: "新对话".to_string(),
+            title: i18n::text(project_locale(&root), "session.defaultTitle").to_string(),
             messages: vec![],
         });
     }
-    let raw = fs::read_to_string(file).map_err(|e| format!("无法读取会话: {e}"))?;
-    serde_json::from_str(&raw).map_err(|e| format!("会话格式错误: {e}"))
+    let raw = read_tracked(&file, i18n::t("what.session"))?;
+    serde_json::from_str(&raw).map_err(|e| AppError::malformed(i18n::t("what.session"), e))
 }
 
-pub fn save_chat_session(project_dir: String, session: &ChatSession) -> Result<(), String> {
+pub fn save_chat_session(project_dir: String, session: &ChatSession) -> Result<(), AppError> {
     let root = p(project_dir);
+    let _write = lock::write_guard(&root);
     ensure_dir(&chat_sessions_dir(&root))?;
     atomic_write_json(&session_file(&root, &session.id), session)?;
 
@@ -367,10 +719,12 @@ pub fn save_chat_session(project_dir: String, session: &ChatSession) -> Result<(
     Ok(())
 }
 
-pub fn delete_chat_session(project_dir: String, session_id: String) -> Result<(), String> {
+pub fn delete_chat_session(project_dir: String, session_id: String) -> Result<(), AppError> {
     let root = p(project_dir);
+    let _write = lock::write_guard(&root);
     let file = session_file(&root, &session_id);
-    let _ = fs::remove_file(file);
+    let _ = fs::remove_file(&file);
+    lock::remember_removed(&file);
 
     let mut index = list_chat_sessions(root.to_string_lossy().to_string())?;
     index.retain(|x| x.id != session_id);
@@ -383,23 +737,37 @@ mod tests {
     use super::*;
 
     #[test]
-    fn init_and_create_chapter() {
+    fn migrates_legacy_project() {
         let dir = tempfile::tempdir().unwrap();
         let root = dir.path().to_string_lossy().to_string();
-        let info = init_project(root.clone()).unwrap();
-        assert!(info.project_dir.contains(dir.path().to_string_lossy().as_ref()));
-        let chapters = list_chapters(root.clone()).unwrap();
-        assert_eq!(chapters.len(), 1);
-
-        let c2 = create_chapter(root.clone(), "第二章".to_string()).unwrap();
-        assert_eq!(c2.id, 2);
-        let chapters = list_chapters(root.clone()).unwrap();
-        assert_eq!(chapters.len(), 2);
-
-        let mut ch = load_chapter(root.clone(), 2).unwrap();
-        ch.content = "hello".to_string();
-        save_chapter(root.clone(), &ch).unwrap();
-        let ch2 = load_chapter(root.clone(), 2).unwrap();
-        assert_eq!(ch2.content, "hello");
+        init_project(root.clone()).unwrap();
+        assert_eq!(load_manifest(root.clone()).unwrap().unwrap().schema_version, SCHEMA_VERSION);
+
+        // a project from before project.json, with a chapter that only has its text
+        fs::remove_file(manifest_file(dir.path())).unwrap();
+        fs::remove_file(chapter_meta(dir.path(), 1)).unwrap();
+        init_project(root.clone()).unwrap();
+        let manifest = load_manifest(root.clone()).unwrap().unwrap();
+        assert_eq!(manifest.schema_version, SCHEMA_VERSION);
+        assert!(manifest.migrated_at.is_some());
+        assert!(chapter_meta(dir.path(), 1).exists());
+        assert_eq!(fs::read_dir(backups_dir(dir.path())).unwrap().count(), 1);
+        assert_eq!(load_chapter(root.clone(), 99).unwrap_err().kind, ErrorKind::NotFound);
+
+        assert_eq!(manifest.locale, Locale::ZhCn);
+        write_manifest(dir.path(), SCHEMA_VERSION + 1, false, Locale::ZhCn).unwrap();
+        assert!(init_project(root).is_err());
+    }
+
+    #[test]
+    fn english_project_gets_english_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        write_manifest(dir.path(), SCHEMA_VERSION, false, Locale::En).unwrap();
+        init_project(root.clone()).unwrap();
+
+        assert_eq!(load_preset(root.clone()).unwrap().pov, Preset::default_en().pov);
+        assert_eq!(list_chapters(root.clone()).unwrap()[0].title, "Chapter 1");
+        assert_eq!(create_chat_session(root, None).unwrap().title, "New chat");
     }
 }
@@ -0,0 +1,108 @@
+use super::ProjectStore;
+use crate::error::AppError;
+use crate::{lock, storage, types::*};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The original layout: chapters/index.json plus one .txt/.json pair per chapter,
+// chat_sessions/ and summaries.json, all handled by the functions in storage.rs.
+pub struct FileStore {
+    dir: String,
+}
+
+impl FileStore {
+    pub fn new(root: PathBuf) -> Self {
+        FileStore {
+            dir: root.to_string_lossy().to_string(),
+        }
+    }
+
+    // Deletes the contents this backend owns; presets and settings are left alone.
+    pub(crate) fn remove(root: &Path) -> Result<(), AppError> {
+        for dir in [storage::chapters_dir(root), storage::chat_sessions_dir(root)] {
+            match fs::remove_dir_all(&dir) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(AppError::io("io.removeDir", &dir, e));
+                }
+                _ => {}
+            }
+        }
+        let summaries = storage::summaries_file(root);
+        if summaries.exists() {
+            fs::remove_file(&summaries).map_err(|e| AppError::io("io.remove", &summaries, e))?;
+        }
+        lock::forget_project(root);
+        Ok(())
+    }
+}
+
+impl ProjectStore for FileStore {
+    fn list_chapters(&self) -> Result<Vec<ChapterIndexItem>, AppError> {
+        storage::list_chapters(self.dir.clone())
+    }
+
+    fn create_chapter(&self, title: String) -> Result<ChapterIndexItem, AppError> {
+        storage::create_chapter(self.dir.clone(), title)
+    }
+
+    fn rename_chapter(&self, id: u32, title: String) -> Result<(), AppError> {
+        storage::rename_chapter(self.dir.clone(), id, title)
+    }
+
+    fn set_chapter_volume(&self, id: u32, volume: Option<String>) -> Result<(), AppError> {
+        storage::set_chapter_volume(self.dir.clone(), id, volume)
+    }
+
+    fn delete_chapter(&self, id: u32) -> Result<(), AppError> {
+        storage::delete_chapter(self.dir.clone(), id)
+    }
+
+    fn load_chapter(&self, id: u32) -> Result<Chapter, AppError> {
+        storage::load_chapter(self.dir.clone(), id)
+    }
+
+    fn save_chapter(&self, chapter: &Chapter) -> Result<(), AppError> {
+        storage::save_chapter(self.dir.clone(), chapter)
+    }
+
+    fn save_chapter_overwriting(&self, chapter: &Chapter) -> Result<(), AppError> {
+        storage::save_chapter_overwriting(self.dir.clone(), chapter)
+    }
+
+    fn insert_chapter(&self, item: &ChapterIndexItem, chapter: &Chapter) -> Result<(), AppError> {
+        // save_chapter appends chapters missing from the index
+        storage::save_chapter(self.dir.clone(), chapter)?;
+        if item.volume.is_some() {
+            storage::set_chapter_volume(self.dir.clone(), item.id, item.volume.clone())?;
+        }
+        Ok(())
+    }
+
+    fn load_summaries(&self) -> Result<Vec<SummaryRecord>, AppError> {
+        storage::load_summaries(self.dir.clone())
+    }
+
+    fn append_summary(&self, record: SummaryRecord) -> Result<(), AppError> {
+        storage::append_summary(self.dir.clone(), record)
+    }
+
+    fn list_chat_sessions(&self) -> Result<Vec<ChatSessionIndexItem>, AppError> {
+        storage::list_chat_sessions(self.dir.clone())
+    }
+
+    fn create_chat_session(&self, title: Option<String>) -> Result<ChatSessionIndexItem, AppError> {
+        storage::create_chat_session(self.dir.clone(), title)
+    }
+
+    fn load_chat_session(&self, session_id: &str) -> Result<ChatSession, AppError> {
+        storage::load_chat_session(self.dir.clone(), session_id.to_string())
+    }
+
+    fn save_chat_session(&self, session: &ChatSession) -> Result<(), AppError> {
+        storage::save_chat_session(self.dir.clone(), session)
+    }
+
+    fn delete_chat_session(&self, session_id: &str) -> Result<(), AppError> {
+        storage::delete_chat_session(self.dir.clone(), session_id.to_string())
+    }
+}
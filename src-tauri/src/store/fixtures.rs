@@ -0,0 +1,81 @@
+use super::{MemoryStore, ProjectStore};
+use crate::types::*;
+
+const SAMPLE_CHAPTERS: &[(&str, &str, &str)] = &[
+    (
+        "第一章 雾港",
+        "雾从海面漫上来的时候，林舟正站在码头尽头。\n\n灯塔已经熄了三年，可今晚它又亮了。",
+        "林舟在雾港码头看见熄灭三年的灯塔重新亮起。",
+    ),
+    (
+        "第二章 守塔人",
+        "塔门没有锁。旋梯上积着薄灰，只有一行新鲜的脚印向上延伸。\n\n* * *\n\n顶层的灯室里坐着一个陌生的老人。",
+        "林舟登上灯塔，遇见自称守塔人的陌生老人。",
+    ),
+    (
+        "第三章 潮汐表",
+        "老人递给他一张泛黄的潮汐表，日期停在十年前的那个秋天。",
+        "",
+    ),
+];
+
+pub fn sample_preset() -> Preset {
+    Preset {
+        style: "冷峻、克制、带一点悬疑".to_string(),
+        pov: "第三人称限定视角".to_string(),
+        rules: vec!["不要替角色解释动机".to_string(), "每章结尾留一个悬念".to_string()],
+    }
+}
+
+// A short mystery in two volumes: three chapters (the last without a summary yet), the
+// matching summary records and one discussion session.
+pub fn sample_novel() -> MemoryStore {
+    let store = MemoryStore::new();
+    store.delete_chapter(1).expect("in-memory store never fails");
+    for (i, (title, content, summary)) in SAMPLE_CHAPTERS.iter().enumerate() {
+        let item = store
+            .create_chapter(title.to_string())
+            .expect("in-memory store never fails");
+        let chapter = Chapter {
+            id: item.id,
+            title: title.to_string(),
+            content: content.to_string(),
+            summary: summary.to_string(),
+        };
+        store.save_chapter(&chapter).expect("in-memory store never fails");
+        let volume = if i < 2 { "上卷" } else { "下卷" };
+        store
+            .set_chapter_volume(item.id, Some(volume.to_string()))
+            .expect("in-memory store never fails");
+        if !summary.is_empty() {
+            store
+                .append_summary(SummaryRecord {
+                    id: format!("summary-{}", item.id),
+                    chapter_id: item.id,
+                    chapter_title: title.to_string(),
+                    summary: summary.to_string(),
+                    created_at: "2024-01-01T00:00:00Z".to_string(),
+                })
+                .expect("in-memory store never fails");
+        }
+    }
+    store
+        .save_chat_session(&ChatSession {
+            id: "sample-session".to_string(),
+            title: "老人的身份".to_string(),
+            messages: vec![
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: "守塔人应该和林舟有什么关系？".to_string(),
+                    created_at: "2024-01-01T00:00:00Z".to_string(),
+                },
+                ChatMessage {
+                    role: "assistant".to_string(),
+                    content: "可以让他是林舟失踪父亲的旧友，潮汐表就是线索。".to_string(),
+                    created_at: "2024-01-01T00:00:05Z".to_string(),
+                },
+            ],
+        })
+        .expect("in-memory store never fails");
+    store
+}
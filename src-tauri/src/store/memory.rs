@@ -0,0 +1,183 @@
+use super::ProjectStore;
+use crate::error::{AppError, ErrorKind};
+use crate::types::*;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+#[derive(Default)]
+struct Contents {
+    index: Vec<ChapterIndexItem>,
+    chapters: BTreeMap<u32, Chapter>,
+    summaries: Vec<SummaryRecord>,
+    // in index order
+    sessions: Vec<ChatSession>,
+}
+
+// Keeps everything in memory and behaves like a freshly initialised project on disk,
+// so code written against ProjectStore can be exercised without a temp dir.
+pub struct MemoryStore {
+    contents: Mutex<Contents>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let store = MemoryStore {
+            contents: Mutex::new(Contents::default()),
+        };
+        store
+            .create_chapter("第一章".to_string())
+            .expect("in-memory store never fails");
+        store
+    }
+
+    fn contents(&self) -> MutexGuard<'_, Contents> {
+        self.contents.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProjectStore for MemoryStore {
+    fn list_chapters(&self) -> Result<Vec<ChapterIndexItem>, AppError> {
+        Ok(self.contents().index.clone())
+    }
+
+    fn create_chapter(&self, title: String) -> Result<ChapterIndexItem, AppError> {
+        // one lock for picking the id and inserting, so concurrent creates never share an id
+        let mut contents = self.contents();
+        let id = contents.index.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        let item = ChapterIndexItem {
+            id,
+            title,
+            volume: None,
+        };
+        let chapter = Chapter {
+            id,
+            title: item.title.clone(),
+            content: String::new(),
+            summary: String::new(),
+        };
+        contents.chapters.insert(id, chapter);
+        contents.index.push(item.clone());
+        Ok(item)
+    }
+
+    fn rename_chapter(&self, id: u32, title: String) -> Result<(), AppError> {
+        let mut contents = self.contents();
+        let chapter = contents
+            .chapters
+            .get_mut(&id)
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, "storage.chapterNotFound"))?;
+        chapter.title = title.clone();
+        for item in contents.index.iter_mut().filter(|c| c.id == id) {
+            item.title = title.clone();
+        }
+        Ok(())
+    }
+
+    fn set_chapter_volume(&self, id: u32, volume: Option<String>) -> Result<(), AppError> {
+        let volume = volume.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        for item in self.contents().index.iter_mut().filter(|c| c.id == id) {
+            item.volume = volume.clone();
+        }
+        Ok(())
+    }
+
+    fn delete_chapter(&self, id: u32) -> Result<(), AppError> {
+        let mut contents = self.contents();
+        contents.chapters.remove(&id);
+        contents.index.retain(|c| c.id != id);
+        Ok(())
+    }
+
+    fn load_chapter(&self, id: u32) -> Result<Chapter, AppError> {
+        self.contents()
+            .chapters
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, "storage.chapterNotFound"))
+    }
+
+    fn save_chapter(&self, chapter: &Chapter) -> Result<(), AppError> {
+        let mut contents = self.contents();
+        contents.chapters.insert(chapter.id, chapter.clone());
+        match contents.index.iter_mut().find(|c| c.id == chapter.id) {
+            Some(item) => item.title = chapter.title.clone(),
+            None => contents.index.push(ChapterIndexItem {
+                id: chapter.id,
+                title: chapter.title.clone(),
+                volume: None,
+            }),
+        }
+        Ok(())
+    }
+
+    fn insert_chapter(&self, item: &ChapterIndexItem, chapter: &Chapter) -> Result<(), AppError> {
+        let mut contents = self.contents();
+        contents.chapters.insert(item.id, chapter.clone());
+        contents.index.push(item.clone());
+        Ok(())
+    }
+
+    fn load_summaries(&self) -> Result<Vec<SummaryRecord>, AppError> {
+        Ok(self.contents().summaries.clone())
+    }
+
+    fn append_summary(&self, record: SummaryRecord) -> Result<(), AppError> {
+        self.contents().summaries.push(record);
+        Ok(())
+    }
+
+    fn list_chat_sessions(&self) -> Result<Vec<ChatSessionIndexItem>, AppError> {
+        Ok(self
+            .contents()
+            .sessions
+            .iter()
+            .map(|s| ChatSessionIndexItem {
+                id: s.id.clone(),
+                title: s.title.clone(),
+            })
+            .collect())
+    }
+
+    fn create_chat_session(&self, title: Option<String>) -> Result<ChatSessionIndexItem, AppError> {
+        let session = ChatSession {
+            id: Uuid::new_v4().to_string(),
+            title: title.unwrap_or_else(|| "新对话".to_string()),
+            messages: vec![],
+        };
+        self.save_chat_session(&session)?;
+        Ok(ChatSessionIndexItem {
+            id: session.id,
+            title: session.title,
+        })
+    }
+
+    fn load_chat_session(&self, session_id: &str) -> Result<ChatSession, AppError> {
+        let found = self.contents().sessions.iter().find(|s| s.id == session_id).cloned();
+        Ok(found.unwrap_or_else(|| ChatSession {
+            id: session_id.to_string(),
+            title: "新对话".to_string(),
+            messages: vec![],
+        }))
+    }
+
+    fn save_chat_session(&self, session: &ChatSession) -> Result<(), AppError> {
+        let mut contents = self.contents();
+        match contents.sessions.iter_mut().find(|s| s.id == session.id) {
+            Some(existing) => *existing = session.clone(),
+            None => contents.sessions.push(session.clone()),
+        }
+        Ok(())
+    }
+
+    fn delete_chat_session(&self, session_id: &str) -> Result<(), AppError> {
+        self.contents().sessions.retain(|s| s.id != session_id);
+        Ok(())
+    }
+}
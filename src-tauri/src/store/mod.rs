@@ -0,0 +1,219 @@
+mod files;
+pub mod fixtures;
+mod memory;
+mod sqlite;
+
+pub use files::FileStore;
+pub use memory::MemoryStore;
+pub(crate) use sqlite::db_file;
+pub use sqlite::SqliteStore;
+
+use crate::error::{AppError, ErrorKind};
+use crate::{backup, crypto, lock, storage, types::*};
+use std::path::PathBuf;
+
+// Chapters, summaries and chat sessions of one project. Presets, model settings and the
+// other small .creatorai settings stay JSON files whatever the backend.
+pub trait ProjectStore {
+    fn list_chapters(&self) -> Result<Vec<ChapterIndexItem>, AppError>;
+    fn create_chapter(&self, title: String) -> Result<ChapterIndexItem, AppError>;
+    fn rename_chapter(&self, id: u32, title: String) -> Result<(), AppError>;
+    fn set_chapter_volume(&self, id: u32, volume: Option<String>) -> Result<(), AppError>;
+    fn delete_chapter(&self, id: u32) -> Result<(), AppError>;
+    fn load_chapter(&self, id: u32) -> Result<Chapter, AppError>;
+    fn save_chapter(&self, chapter: &Chapter) -> Result<(), AppError>;
+    // Saves even if the chapter was changed outside the app since it was loaded.
+    fn save_chapter_overwriting(&self, chapter: &Chapter) -> Result<(), AppError> {
+        self.save_chapter(chapter)
+    }
+    // Appends a chapter with a given id at the end of the index; used when converting.
+    fn insert_chapter(&self, item: &ChapterIndexItem, chapter: &Chapter) -> Result<(), AppError>;
+
+    fn load_summaries(&self) -> Result<Vec<SummaryRecord>, AppError>;
+    fn append_summary(&self, record: SummaryRecord) -> Result<(), AppError>;
+
+    fn list_chat_sessions(&self) -> Result<Vec<ChatSessionIndexItem>, AppError>;
+    fn create_chat_session(&self, title: Option<String>) -> Result<ChatSessionIndexItem, AppError>;
+    fn load_chat_session(&self, session_id: &str) -> Result<ChatSession, AppError>;
+    fn save_chat_session(&self, session: &ChatSession) -> Result<(), AppError>;
+    fn delete_chat_session(&self, session_id: &str) -> Result<(), AppError>;
+
+    // Chapters whose title or text contains `query`, best matches first. Without an index
+    // this reads every chapter, which is what the SQLite backend is for.
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, AppError> {
+        let mut hits = vec![];
+        for item in self.list_chapters()? {
+            if hits.len() >= limit {
+                break;
+            }
+            let ch = self.load_chapter(item.id)?;
+            if let Some(snippet) = snippet(&ch.content, query).or_else(|| snippet(&ch.title, query)) {
+                hits.push(SearchHit {
+                    chapter_id: ch.id,
+                    chapter_title: ch.title,
+                    snippet,
+                });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+// The store of the backend recorded in the project's manifest.
+pub fn open(project_dir: &str) -> Result<Box<dyn ProjectStore>, AppError> {
+    let root = PathBuf::from(project_dir);
+    Ok(match storage::project_backend(&root) {
+        StorageBackend::Files => Box::new(FileStore::new(root)),
+        StorageBackend::Sqlite => Box::new(SqliteStore::open(&root)?),
+    })
+}
+
+pub fn backend(project_dir: String) -> StorageBackend {
+    storage::project_backend(&PathBuf::from(project_dir))
+}
+
+// Text around the first case-insensitive match of `query`, or None without a match.
+pub(crate) fn snippet(text: &str, query: &str) -> Option<String> {
+    const CONTEXT: usize = 20;
+    let chars = text.chars().collect::<Vec<_>>();
+    let lower = chars.iter().flat_map(|c| c.to_lowercase()).collect::<Vec<_>>();
+    let needle = query.chars().flat_map(|c| c.to_lowercase()).collect::<Vec<_>>();
+    // lowercasing can change the length (e.g. "İ"); fall back to an exact match then
+    let (hay, needle) = if lower.len() == chars.len() {
+        (lower, needle)
+    } else {
+        (chars.clone(), query.chars().collect())
+    };
+    if needle.is_empty() {
+        return None;
+    }
+    let start = hay.windows(needle.len()).position(|w| w == needle.as_slice())?;
+    let end = start + needle.len();
+    let from = start.saturating_sub(CONTEXT);
+    let to = (end + CONTEXT).min(chars.len());
+    let piece = |a: usize, b: usize| chars[a..b].iter().collect::<String>().replace('\n', " ");
+    Some(format!(
+        "{}{}【{}】{}{}",
+        if from > 0 { "…" } else { "" },
+        piece(from, start),
+        piece(start, end),
+        piece(end, to),
+        if to < chars.len() { "…" } else { "" },
+    ))
+}
+
+fn copy_contents(from: &dyn ProjectStore, to: &dyn ProjectStore) -> Result<(), AppError> {
+    for item in from.list_chapters()? {
+        to.insert_chapter(&item, &from.load_chapter(item.id)?)?;
+    }
+    for record in from.load_summaries()? {
+        to.append_summary(record)?;
+    }
+    for item in from.list_chat_sessions()? {
+        to.save_chat_session(&from.load_chat_session(&item.id)?)?;
+    }
+    Ok(())
+}
+
+// Moves the project's contents to another backend. A backup is taken first; the manifest is
+// switched only once everything is copied, and the old copy removed after that, so an
+// interrupted conversion leaves the project usable on the old backend.
+pub fn convert(project_dir: String, target: StorageBackend) -> Result<(), AppError> {
+    let root = PathBuf::from(&project_dir);
+    let _write = lock::write_guard(&root);
+    if storage::project_backend(&root) == target {
+        return Ok(());
+    }
+    if crypto::status(project_dir.clone())?.enabled {
+        return Err(AppError::new(ErrorKind::Invalid, "store.convertEncrypted"));
+    }
+    backup::backup_project(project_dir, None)?;
+
+    match target {
+        StorageBackend::Sqlite => {
+            SqliteStore::remove(&root)?;
+            copy_contents(&FileStore::new(root.clone()), &SqliteStore::open(&root)?)?;
+            storage::set_project_backend(&root, target)?;
+            FileStore::remove(&root)?;
+        }
+        StorageBackend::Files => {
+            FileStore::remove(&root)?;
+            copy_contents(&SqliteStore::open(&root)?, &FileStore::new(root.clone()))?;
+            storage::set_project_backend(&root, target)?;
+            SqliteStore::remove(&root)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `store` is a freshly initialised project
+    fn create_chapter_in(store: &dyn ProjectStore) {
+        let chapters = store.list_chapters().unwrap();
+        assert_eq!(chapters.len(), 1);
+
+        let c2 = store.create_chapter("第二章".to_string()).unwrap();
+        assert_eq!(c2.id, 2);
+        let chapters = store.list_chapters().unwrap();
+        assert_eq!(chapters.len(), 2);
+
+        let mut ch = store.load_chapter(2).unwrap();
+        ch.content = "hello".to_string();
+        store.save_chapter(&ch).unwrap();
+        let ch2 = store.load_chapter(2).unwrap();
+        assert_eq!(ch2.content, "hello");
+    }
+
+    #[test]
+    fn init_and_create_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let info = storage::init_project(root.clone()).unwrap();
+        assert!(info.project_dir.contains(dir.path().to_string_lossy().as_ref()));
+        create_chapter_in(&FileStore::new(dir.path().to_path_buf()));
+
+        create_chapter_in(&MemoryStore::new());
+    }
+
+    #[test]
+    fn converts_between_backends() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let files = open(&root).unwrap();
+        let item = files.create_chapter("第二章".to_string()).unwrap();
+        files.set_chapter_volume(item.id, Some("第一卷".to_string())).unwrap();
+        let mut ch = files.load_chapter(item.id).unwrap();
+        ch.content = "雨夜里，灯塔的光扫过海面。".to_string();
+        files.save_chapter(&ch).unwrap();
+        let session = files.create_chat_session(Some("人物设定".to_string())).unwrap();
+
+        convert(root.clone(), StorageBackend::Sqlite).unwrap();
+        assert_eq!(backend(root.clone()), StorageBackend::Sqlite);
+        assert!(!storage::chapters_index_file(dir.path()).exists());
+        let db = open(&root).unwrap();
+        assert_eq!(db.list_chapters().unwrap().len(), 2);
+        assert_eq!(db.list_chapters().unwrap()[1].volume.as_deref(), Some("第一卷"));
+        assert_eq!(db.load_chapter(item.id).unwrap().content, ch.content);
+        assert_eq!(db.list_chat_sessions().unwrap()[0].id, session.id);
+        let hits = db.search("灯塔的光", 10).unwrap();
+        assert_eq!(hits[0].chapter_id, item.id);
+        assert!(hits[0].snippet.contains("【灯塔的光】"));
+        assert_eq!(db.search("灯塔", 10).unwrap().len(), 1);
+        // reopening keeps the layout instead of recreating the default chapter files
+        storage::init_project(root.clone()).unwrap();
+        assert!(!storage::chapters_index_file(dir.path()).exists());
+
+        convert(root.clone(), StorageBackend::Files).unwrap();
+        assert_eq!(backend(root.clone()), StorageBackend::Files);
+        assert_eq!(storage::list_chapters(root.clone()).unwrap().len(), 2);
+        assert_eq!(
+            storage::load_chapter(root.clone(), item.id).unwrap().content,
+            ch.content
+        );
+        assert_eq!(open(&root).unwrap().search("海面", 10).unwrap()[0].chapter_id, item.id);
+    }
+}
@@ -0,0 +1,416 @@
+use super::{snippet, ProjectStore};
+use crate::error::{AppError, ErrorKind};
+use crate::{i18n, storage, types::*};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+// Bump together with a new step in `migrate`.
+const DB_VERSION: i32 = 1;
+
+// Chapter text is indexed with the trigram tokenizer, which finds substrings in Chinese
+// without word segmentation; queries shorter than three characters fall back to LIKE.
+const SCHEMA: &str = "
+CREATE TABLE chapters (
+    id INTEGER PRIMARY KEY,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    volume TEXT,
+    content TEXT NOT NULL DEFAULT '',
+    summary TEXT NOT NULL DEFAULT ''
+);
+CREATE TABLE summaries (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL,
+    chapter_id INTEGER NOT NULL,
+    chapter_title TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    messages TEXT NOT NULL DEFAULT '[]'
+);
+CREATE VIRTUAL TABLE chapters_fts USING fts5(
+    title, content, content = 'chapters', content_rowid = 'id', tokenize = 'trigram'
+);
+CREATE TRIGGER chapters_ai AFTER INSERT ON chapters BEGIN
+    INSERT INTO chapters_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+CREATE TRIGGER chapters_ad AFTER DELETE ON chapters BEGIN
+    INSERT INTO chapters_fts(chapters_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+END;
+CREATE TRIGGER chapters_au AFTER UPDATE ON chapters BEGIN
+    INSERT INTO chapters_fts(chapters_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO chapters_fts(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+";
+
+fn db_err(e: rusqlite::Error) -> AppError {
+    AppError::new(ErrorKind::Io, "store.database").caused_by(e)
+}
+
+pub(crate) fn db_file(root: &Path) -> PathBuf {
+    root.join("project.db")
+}
+
+// Everything in one project.db next to the presets. The default rollback journal is kept
+// (no WAL), so backups and git only ever see a single, consistent file.
+pub struct SqliteStore {
+    conn: Connection,
+    locale: Locale,
+}
+
+impl SqliteStore {
+    pub fn open(root: &Path) -> Result<Self, AppError> {
+        let conn = Connection::open(db_file(root)).map_err(db_err)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5)).map_err(db_err)?;
+        migrate(&conn)?;
+        Ok(SqliteStore {
+            conn,
+            locale: storage::project_locale(root),
+        })
+    }
+
+    pub(crate) fn remove(root: &Path) -> Result<(), AppError> {
+        for file in [db_file(root), root.join("project.db-journal")] {
+            match fs::remove_file(&file) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(AppError::io("io.remove", &file, e));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // What `PRAGMA integrity_check` finds wrong with project.db; empty when the file is sound.
+    // Opened read-only and without migrating, so checking never changes the database.
+    pub(crate) fn integrity_check(root: &Path) -> Result<Vec<String>, AppError> {
+        let conn = Connection::open_with_flags(db_file(root), OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(db_err)?;
+        let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(db_err)?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0)).map_err(db_err)?;
+        let problems = rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?;
+        Ok(problems.into_iter().filter(|p| p != "ok").collect())
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<(), AppError> {
+    let version: i32 = conn
+        .query_row("PRAGMA user_version", [], |r| r.get(0))
+        .map_err(db_err)?;
+    if version > DB_VERSION {
+        return Err(AppError::new(ErrorKind::Invalid, "store.databaseTooNew")
+            .with_param("version", version)
+            .with_param("supported", DB_VERSION));
+    }
+    if version < 1 {
+        conn.execute_batch(&format!("BEGIN; {SCHEMA} PRAGMA user_version = 1; COMMIT;"))
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+fn next_position(conn: &Connection, table: &str) -> Result<i64, AppError> {
+    conn.query_row(
+        &format!("SELECT COALESCE(MAX(position), 0) + 1 FROM {table}"),
+        [],
+        |r| r.get(0),
+    )
+    .map_err(db_err)
+}
+
+impl ProjectStore for SqliteStore {
+    fn list_chapters(&self) -> Result<Vec<ChapterIndexItem>, AppError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, title, volume FROM chapters ORDER BY position")
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(ChapterIndexItem {
+                    id: r.get(0)?,
+                    title: r.get(1)?,
+                    volume: r.get(2)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    fn create_chapter(&self, title: String) -> Result<ChapterIndexItem, AppError> {
+        let id: u32 = self
+            .conn
+            .query_row("SELECT COALESCE(MAX(id), 0) + 1 FROM chapters", [], |r| r.get(0))
+            .map_err(db_err)?;
+        let item = ChapterIndexItem {
+            id,
+            title,
+            volume: None,
+        };
+        let chapter = Chapter {
+            id,
+            title: item.title.clone(),
+            content: String::new(),
+            summary: String::new(),
+        };
+        self.insert_chapter(&item, &chapter)?;
+        Ok(item)
+    }
+
+    fn rename_chapter(&self, id: u32, title: String) -> Result<(), AppError> {
+        self.conn
+            .execute("UPDATE chapters SET title = ?2 WHERE id = ?1", params![id, title])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn set_chapter_volume(&self, id: u32, volume: Option<String>) -> Result<(), AppError> {
+        let volume = volume.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        self.conn
+            .execute("UPDATE chapters SET volume = ?2 WHERE id = ?1", params![id, volume])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn delete_chapter(&self, id: u32) -> Result<(), AppError> {
+        self.conn
+            .execute("DELETE FROM chapters WHERE id = ?1", [id])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn load_chapter(&self, id: u32) -> Result<Chapter, AppError> {
+        self.conn
+            .query_row(
+                "SELECT title, content, summary FROM chapters WHERE id = ?1",
+                [id],
+                |r| {
+                    Ok(Chapter {
+                        id,
+                        title: r.get(0)?,
+                        content: r.get(1)?,
+                        summary: r.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_err)?
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, "storage.chapterNotFound"))
+    }
+
+    fn save_chapter(&self, chapter: &Chapter) -> Result<(), AppError> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE chapters SET title = ?2, content = ?3, summary = ?4 WHERE id = ?1",
+                params![chapter.id, chapter.title, chapter.content, chapter.summary],
+            )
+            .map_err(db_err)?;
+        if updated == 0 {
+            let item = ChapterIndexItem {
+                id: chapter.id,
+                title: chapter.title.clone(),
+                volume: None,
+            };
+            self.insert_chapter(&item, chapter)?;
+        }
+        Ok(())
+    }
+
+    fn insert_chapter(&self, item: &ChapterIndexItem, chapter: &Chapter) -> Result<(), AppError> {
+        let position = next_position(&self.conn, "chapters")?;
+        self.conn
+            .execute(
+                "INSERT INTO chapters (id, position, title, volume, content, summary) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    item.id,
+                    position,
+                    chapter.title,
+                    item.volume,
+                    chapter.content,
+                    chapter.summary
+                ],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn load_summaries(&self) -> Result<Vec<SummaryRecord>, AppError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, chapter_id, chapter_title, summary, created_at FROM summaries ORDER BY seq")
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(SummaryRecord {
+                    id: r.get(0)?,
+                    chapter_id: r.get(1)?,
+                    chapter_title: r.get(2)?,
+                    summary: r.get(3)?,
+                    created_at: r.get(4)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    fn append_summary(&self, record: SummaryRecord) -> Result<(), AppError> {
+        self.conn
+            .execute(
+                "INSERT INTO summaries (id, chapter_id, chapter_title, summary, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![record.id, record.chapter_id, record.chapter_title, record.summary, record.created_at],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn list_chat_sessions(&self) -> Result<Vec<ChatSessionIndexItem>, AppError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, title FROM sessions ORDER BY position")
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(ChatSessionIndexItem {
+                    id: r.get(0)?,
+                    title: r.get(1)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)
+    }
+
+    fn create_chat_session(&self, title: Option<String>) -> Result<ChatSessionIndexItem, AppError> {
+        let session = ChatSession {
+            id: Uuid::new_v4().to_string(),
+            title: title.unwrap_or_else(|| i18n::text(self.locale, "session.defaultTitle").to_string()),
+            messages: vec![],
+        };
+        self.save_chat_session(&session)?;
+        Ok(ChatSessionIndexItem {
+            id: session.id,
+            title: session.title,
+        })
+    }
+
+    fn load_chat_session(&self, session_id: &str) -> Result<ChatSession, AppError> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT title, messages FROM sessions WHERE id = ?1",
+                [session_id],
+                |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)),
+            )
+            .optional()
+            .map_err(db_err)?;
+        let Some((title, messages)) = row else {
+            return Ok(ChatSession {
+                id: session_id.to_string(),
+                title: i18n::text(self.locale, "session.defaultTitle").to_string(),
+                messages: vec![],
+            });
+        };
+        Ok(ChatSession {
+            id: session_id.to_string(),
+            title,
+            messages: serde_json::from_str(&messages).map_err(|e| AppError::malformed(i18n::t("what.session"), e))?,
+        })
+    }
+
+    fn save_chat_session(&self, session: &ChatSession) -> Result<(), AppError> {
+        let messages = serde_json::to_string(&session.messages)
+            .map_err(|e| AppError::new(ErrorKind::Other, "io.serialize").caused_by(e))?;
+        let position = next_position(&self.conn, "sessions")?;
+        self.conn
+            .execute(
+                "INSERT INTO sessions (id, position, title, messages) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET title = excluded.title, messages = excluded.messages",
+                params![session.id, position, session.title, messages],
+            )
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn delete_chat_session(&self, session_id: &str) -> Result<(), AppError> {
+        self.conn
+            .execute("DELETE FROM sessions WHERE id = ?1", [session_id])
+            .map_err(db_err)?;
+        Ok(())
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, AppError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+        let (sql, pattern) = if query.chars().count() >= 3 {
+            // a quoted FTS5 string matches the query literally
+            (
+                "SELECT c.id, c.title, c.content FROM chapters_fts f JOIN chapters c ON c.id = f.rowid
+                 WHERE chapters_fts MATCH ?1 ORDER BY f.rank LIMIT ?2",
+                format!("\"{}\"", query.replace('"', "\"\"")),
+            )
+        } else {
+            (
+                "SELECT id, title, content FROM chapters WHERE instr(lower(content), lower(?1)) > 0
+                 OR instr(lower(title), lower(?1)) > 0 ORDER BY position LIMIT ?2",
+                query.to_string(),
+            )
+        };
+        let mut stmt = self.conn.prepare(sql).map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![pattern, limit as i64], |r| {
+                Ok((r.get::<_, u32>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?))
+            })
+            .map_err(db_err)?;
+        let mut hits = vec![];
+        for row in rows {
+            let (chapter_id, chapter_title, content) = row.map_err(db_err)?;
+            let snippet = snippet(&content, query)
+                .or_else(|| snippet(&chapter_title, query))
+                .unwrap_or_default();
+            hits.push(SearchHit {
+                chapter_id,
+                chapter_title,
+                snippet,
+            });
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_index_order_and_search_in_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::open(dir.path()).unwrap();
+        let a = store.create_chapter("开端".to_string()).unwrap();
+        let b = store.create_chapter("转折".to_string()).unwrap();
+        store
+            .save_chapter(&Chapter {
+                id: b.id,
+                title: "转折".to_string(),
+                content: "他终于打开了那扇门。".to_string(),
+                summary: String::new(),
+            })
+            .unwrap();
+        assert_eq!(store.search("那扇门", 5).unwrap()[0].chapter_id, b.id);
+
+        store.rename_chapter(b.id, "真相".to_string()).unwrap();
+        assert_eq!(store.load_chapter(b.id).unwrap().title, "真相");
+        store.delete_chapter(b.id).unwrap();
+        assert!(store.search("那扇门", 5).unwrap().is_empty());
+        assert_eq!(
+            store.list_chapters().unwrap().iter().map(|c| c.id).collect::<Vec<_>>(),
+            [a.id]
+        );
+        assert!(store.load_chapter(b.id).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use crate::i18n;
+use serde_json::Value;
+
+// A small Handlebars-like language for prompt templates:
+//   {{name}}                          a variable
+//   {{#if name}} … {{else}} … {{/if}} text that depends on a variable being non-empty
+//   {{#each name}} … {{/each}}        repeated for each item of a list; {{this}} is the item,
+//                                     and the fields of a record item are variables inside
+//   {{! note }}                       a comment
+// A block tag alone on its line takes the line with it, so templates can be laid out
+// one tag per line without leaving blank lines in the output.
+
+#[derive(Debug, Clone, Copy)]
+pub enum VarKind {
+    Text,
+    // a list of records with these fields, or of plain text when there are none
+    List(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Var {
+    pub name: &'static str,
+    pub kind: VarKind,
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        line: usize,
+    },
+    If {
+        name: String,
+        line: usize,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        line: usize,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Tag {
+    Var(String),
+    If(String),
+    Each(String),
+    Else,
+    EndIf,
+    EndEach,
+    Comment,
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn tag(inner: &str, line: usize) -> Result<Tag, String> {
+    let inner = inner.trim();
+    let bad = || {
+        i18n::tf(
+            "template.badTag",
+            &[("line", &line), ("tag", &format!("{{{{{inner}}}}}"))],
+        )
+    };
+    if inner.starts_with('!') {
+        return Ok(Tag::Comment);
+    }
+    let named = |rest: &str, make: fn(String) -> Tag| {
+        let name = rest.trim();
+        if is_name(name) {
+            Ok(make(name.to_string()))
+        } else {
+            Err(bad())
+        }
+    };
+    match inner {
+        "else" => Ok(Tag::Else),
+        "/if" => Ok(Tag::EndIf),
+        "/each" => Ok(Tag::EndEach),
+        _ if inner.starts_with("#if ") => named(&inner[4..], Tag::If),
+        _ if inner.starts_with("#each ") => named(&inner[6..], Tag::Each),
+        _ => named(inner, Tag::Var),
+    }
+}
+
+// An open block while parsing: the tag's name and line, and what has been read inside it.
+struct Frame {
+    tag: Tag,
+    line: usize,
+    nodes: Vec<Node>,
+    // the {{#if}} branch once {{else}} was seen
+    then: Option<Vec<Node>>,
+}
+
+pub fn parse(source: &str) -> Result<Template, String> {
+    let mut stack = vec![Frame {
+        tag: Tag::Comment,
+        line: 0,
+        nodes: vec![],
+        then: None,
+    }];
+    let mut text = String::new();
+    let mut cursor = 0;
+    while let Some(open) = source[cursor..].find("{{").map(|i| cursor + i) {
+        let line = source[..open].matches('\n').count() + 1;
+        let close = source[open..]
+            .find("}}")
+            .map(|i| open + i)
+            .ok_or_else(|| i18n::tf("template.unclosedTag", &[("line", &line)]))?;
+        let parsed = tag(&source[open + 2..close], line)?;
+        let mut end = close + 2;
+        text.push_str(&source[cursor..open]);
+
+        if !matches!(parsed, Tag::Var(_)) {
+            // a block tag alone on its line: drop the indent before it and the line break after
+            let line_start = source[..open].rfind('\n').map_or(0, |i| i + 1);
+            let rest = &source[end..];
+            let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            // an earlier tag on the same line leaves the cursor past the line's start
+            if line_start >= cursor && source[line_start..open].trim().is_empty() && rest[..line_end].trim().is_empty()
+            {
+                text.truncate(text.len() - (open - line_start));
+                end += line_end;
+            }
+        }
+        if !text.is_empty() {
+            let top = stack.last_mut().expect("root frame");
+            top.nodes.push(Node::Text(std::mem::take(&mut text)));
+        }
+        cursor = end;
+
+        let unexpected = |name: &str| i18n::tf("template.unexpectedClose", &[("line", &line), ("tag", &name)]);
+        match parsed {
+            Tag::Comment => {}
+            Tag::Var(name) => stack
+                .last_mut()
+                .expect("root frame")
+                .nodes
+                .push(Node::Var { name, line }),
+            tag @ (Tag::If(_) | Tag::Each(_)) => stack.push(Frame {
+                tag,
+                line,
+                nodes: vec![],
+                then: None,
+            }),
+            Tag::Else => {
+                let nested = stack.len() > 1;
+                let top = stack.last_mut().expect("root frame");
+                if !nested || !matches!(top.tag, Tag::If(_)) || top.then.is_some() {
+                    return Err(unexpected("{{else}}"));
+                }
+                top.then = Some(std::mem::take(&mut top.nodes));
+            }
+            Tag::EndIf | Tag::EndEach => {
+                let closes_if = matches!(parsed, Tag::EndIf);
+                if stack.len() == 1 {
+                    return Err(unexpected(if closes_if { "{{/if}}" } else { "{{/each}}" }));
+                }
+                let top = stack.pop().expect("open block");
+                let node = match (top.tag, closes_if) {
+                    (Tag::If(name), true) => match top.then {
+                        Some(then) => Node::If {
+                            name,
+                            line: top.line,
+                            then,
+                            otherwise: top.nodes,
+                        },
+                        None => Node::If {
+                            name,
+                            line: top.line,
+                            then: top.nodes,
+                            otherwise: vec![],
+                        },
+                    },
+                    (Tag::Each(name), false) => Node::Each {
+                        name,
+                        line: top.line,
+                        body: top.nodes,
+                    },
+                    _ => return Err(unexpected(if closes_if { "{{/if}}" } else { "{{/each}}" })),
+                };
+                stack.last_mut().expect("root frame").nodes.push(node);
+            }
+        }
+    }
+    text.push_str(&source[cursor..]);
+
+    if stack.len() > 1 {
+        let open = stack.pop().expect("open block");
+        let tag = match open.tag {
+            Tag::If(name) => format!("{{{{#if {name}}}}}"),
+            Tag::Each(name) => format!("{{{{#each {name}}}}}"),
+            _ => String::new(),
+        };
+        return Err(i18n::tf(
+            "template.unclosedBlock",
+            &[("line", &open.line), ("tag", &tag)],
+        ));
+    }
+    let mut root = stack.pop().expect("root frame");
+    if !text.is_empty() {
+        root.nodes.push(Node::Text(text));
+    }
+    Ok(Template { nodes: root.nodes })
+}
+
+// Scopes from the template's variables to the innermost {{#each}} item.
+struct Scope<'a> {
+    vars: &'a [Var],
+    items: Vec<&'static [&'static str]>,
+}
+
+impl Scope<'_> {
+    fn kind(&self, name: &str) -> Option<VarKind> {
+        if name == "this" && !self.items.is_empty() {
+            return Some(VarKind::Text);
+        }
+        if self.items.iter().rev().any(|fields| fields.contains(&name)) {
+            return Some(VarKind::Text);
+        }
+        self.vars.iter().find(|v| v.name == name).map(|v| v.kind)
+    }
+}
+
+fn check_nodes(nodes: &[Node], scope: &mut Scope) -> Result<(), String> {
+    let unknown = |name: &str, line: usize| i18n::tf("template.unknownVariable", &[("line", &line), ("name", &name)]);
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var { name, line } => match scope.kind(name) {
+                Some(VarKind::Text) => {}
+                Some(VarKind::List(_)) => {
+                    return Err(i18n::tf("template.listAsText", &[("line", line), ("name", name)]))
+                }
+                None => return Err(unknown(name, *line)),
+            },
+            Node::If {
+                name,
+                line,
+                then,
+                otherwise,
+            } => {
+                scope.kind(name).ok_or_else(|| unknown(name, *line))?;
+                check_nodes(then, scope)?;
+                check_nodes(otherwise, scope)?;
+            }
+            Node::Each { name, line, body } => match scope.kind(name) {
+                Some(VarKind::List(fields)) => {
+                    scope.items.push(fields);
+                    let checked = check_nodes(body, scope);
+                    scope.items.pop();
+                    checked?;
+                }
+                Some(VarKind::Text) => return Err(i18n::tf("template.notAList", &[("line", line), ("name", name)])),
+                None => return Err(unknown(name, *line)),
+            },
+        }
+    }
+    Ok(())
+}
+
+impl Template {
+    // Fails on the first variable the template uses that `vars` doesn't offer, or uses the
+    // wrong way (a list as text, text in {{#each}}).
+    pub fn check(&self, vars: &[Var]) -> Result<(), String> {
+        check_nodes(&self.nodes, &mut Scope { vars, items: vec![] })
+    }
+
+    // `context` is a JSON object with a string or array per variable; anything missing
+    // renders as empty.
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut vec![context], &mut out);
+        out
+    }
+}
+
+fn lookup<'a>(scopes: &[&'a Value], name: &str) -> Option<&'a Value> {
+    if name == "this" && scopes.len() > 1 {
+        return scopes.last().copied();
+    }
+    scopes.iter().rev().find_map(|s| s.get(name))
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::String(s)) => !s.trim().is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    }
+}
+
+fn render_nodes<'a>(nodes: &'a [Node], scopes: &mut Vec<&'a Value>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { name, .. } => match lookup(scopes, name) {
+                Some(Value::String(s)) => out.push_str(s),
+                Some(Value::Null) | None => {}
+                Some(other) => out.push_str(&other.to_string()),
+            },
+            Node::If {
+                name, then, otherwise, ..
+            } => {
+                let branch = if truthy(lookup(scopes, name)) { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::Each { name, body, .. } => {
+                if let Some(Value::Array(items)) = lookup(scopes, name) {
+                    for item in items {
+                        scopes.push(item);
+                        render_nodes(body, scopes, out);
+                        scopes.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARS: &[Var] = &[
+        Var {
+            name: "chapter",
+            kind: VarKind::Text,
+        },
+        Var {
+            name: "rules",
+            kind: VarKind::List(&[]),
+        },
+        Var {
+            name: "summaries",
+            kind: VarKind::List(&["title", "summary"]),
+        },
+    ];
+
+    #[test]
+    fn renders_blocks_and_rejects_what_it_cannot_fill() {
+        let source = "{{! context }}\n{{#if summaries}}\n## 前文\n  {{#each summaries}}\n【{{title}}】{{summary}}\n  {{/each}}\n{{else}}\n（无）\n{{/if}}\n规则：{{#each rules}}{{this}}；{{/each}}\n{{chapter}}";
+        let template = parse(source).unwrap();
+        template.check(VARS).unwrap();
+        let context = serde_json::json!({
+            "summaries": [{ "title": "第一章", "summary": "起风" }, { "title": "第二章", "summary": "落雨" }],
+            "rules": ["短句", "留白"],
+            "chapter": "{{不是标记}}",
+        });
+        assert_eq!(
+            template.render(&context),
+            "## 前文\n【第一章】起风\n【第二章】落雨\n规则：短句；留白；\n{{不是标记}}"
+        );
+        let empty = serde_json::json!({ "summaries": [], "rules": [], "chapter": "" });
+        assert_eq!(template.render(&empty), "（无）\n规则：\n");
+
+        let rejected = |source: &str| parse(source).and_then(|t| t.check(VARS)).is_err();
+        assert!(rejected("{{#if chapter}}未闭合"));
+        assert!(rejected("{{/each}}"));
+        assert!(rejected("{{#each rules}}{{/if}}"));
+        assert!(rejected("{{chapter"));
+        assert!(rejected("{{ char acter }}"));
+        assert!(rejected("{{codex}}"));
+        assert!(rejected("{{summaries}}"));
+        assert!(rejected("{{#each chapter}}{{/each}}"));
+        assert!(rejected("{{title}}"));
+        assert!(!rejected("{{#each summaries}}{{title}}{{chapter}}{{/each}}"));
+    }
+}
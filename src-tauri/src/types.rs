@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -7,6 +8,38 @@ pub struct AppState {
     pub last_project_dir: Option<String>,
     pub last_session_id: Option<String>,
     pub last_chapter_id: Option<u32>,
+    // interface language; new projects start in it too
+    #[serde(default)]
+    pub locale: Locale,
+    // write prompts and generated text to the log instead of just their length
+    #[serde(default)]
+    pub log_full_text: bool,
+    #[serde(default)]
+    pub budget: BudgetSettings,
+}
+
+// Spending limits across all projects; once one is reached, model requests are refused.
+// Costs are in whatever currency the endpoint prices are entered in.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetSettings {
+    #[serde(default)]
+    pub daily_cost: Option<f64>,
+    #[serde(default)]
+    pub monthly_cost: Option<f64>,
+    #[serde(default)]
+    pub daily_tokens: Option<u64>,
+    #[serde(default)]
+    pub monthly_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,11 +49,48 @@ pub struct ProjectInfo {
     pub project_name: String,
 }
 
+// project.json: identifies the on-disk layout so older projects can be upgraded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectManifest {
+    pub schema_version: u32,
+    // app version that last wrote the manifest
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub migrated_at: Option<String>,
+    #[serde(default)]
+    pub backend: StorageBackend,
+    // language of the prompts and of default titles; projects from before this are Chinese
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+// Where chapters, summaries and chat sessions live: one JSON/text file each, or project.db.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageBackend {
+    #[default]
+    Files,
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub chapter_id: u32,
+    pub chapter_title: String,
+    // text around the first match, with the match wrapped in 【】
+    pub snippet: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChapterIndexItem {
     pub id: u32,
     pub title: String,
+    #[serde(default)]
+    pub volume: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +112,13 @@ pub struct Preset {
 }
 
 impl Preset {
+    pub fn default_for(locale: Locale) -> Self {
+        match locale {
+            Locale::ZhCn => Self::default_zh(),
+            Locale::En => Self::default_en(),
+        }
+    }
+
     pub fn default_zh() -> Self {
         Self {
             style: "细腻、沉浸、画面感强".to_string(),
@@ -54,6 +131,52 @@ impl Preset {
             ],
         }
     }
+
+    pub fn default_en() -> Self {
+        Self {
+            style: "Immersive, vivid and richly visual".to_string(),
+            pov: "Third person limited".to_string(),
+            rules: vec![
+                "Keep the style consistent".to_string(),
+                "Engage the senses".to_string(),
+                "Avoid omniscient narration".to_string(),
+                "Show emotion through action and detail".to_string(),
+            ],
+        }
+    }
+}
+
+// The prompt pieces a project can override; the rest come from the built-in templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PromptTemplateName {
+    ContinueSystem,
+    ContinueUser,
+    DiscussSystem,
+    SummarizeSystem,
+    // sent to pick up a reply cut off by max_tokens
+    Resume,
+}
+
+impl PromptTemplateName {
+    pub const ALL: [PromptTemplateName; 5] = [
+        PromptTemplateName::ContinueSystem,
+        PromptTemplateName::ContinueUser,
+        PromptTemplateName::DiscussSystem,
+        PromptTemplateName::SummarizeSystem,
+        PromptTemplateName::Resume,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub name: PromptTemplateName,
+    pub source: String,
+    // false while the built-in text is in use
+    pub customized: bool,
+    // what the template can use, e.g. "chapter" or "summaries[].title"
+    pub variables: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -63,6 +186,9 @@ pub struct GenerationResponse {
     pub summary: String,
     #[serde(default)]
     pub raw: Option<String>,
+    // still cut off by max_tokens after the automatic follow-ups
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +210,61 @@ pub struct ModelParameters {
     pub top_p: Option<f32>,
     #[serde(default)]
     pub top_k: Option<u32>,
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    // the multiplicative penalty of llama.cpp, vLLM and OpenRouter; 1.0 is off
+    #[serde(default)]
+    pub repetition_penalty: Option<f32>,
+    #[serde(default)]
+    pub stop: Vec<String>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    // merged into the request body last, for anything not covered above
+    #[serde(default)]
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+// The API behind an endpoint, which decides which sampling parameters are sent. `Auto` goes
+// by the base URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Provider {
+    #[default]
+    Auto,
+    OpenAi,
+    OpenRouter,
+    DeepSeek,
+    Gemini,
+    // llama.cpp, vLLM, LM Studio and other servers on this machine or network
+    Local,
+    // any other OpenAI-compatible service
+    Generic,
 }
 
 impl ModelParameters {
@@ -91,8 +272,7 @@ impl ModelParameters {
         Self {
             temperature: 0.8,
             max_tokens: 4000,
-            top_p: None,
-            top_k: None,
+            ..Default::default()
         }
     }
 }
@@ -105,7 +285,20 @@ pub struct EndpointConfig {
     pub base_url: String,
     pub default_model: String,
     #[serde(default)]
+    pub provider: Provider,
+    #[serde(default)]
     pub parameters: ModelParameters,
+    #[serde(default)]
+    pub pricing: Vec<ModelPricing>,
+}
+
+// Price per million tokens for one model of an endpoint, used for cost estimates.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPricing {
+    pub model: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
 }
 
 impl EndpointConfig {
@@ -115,7 +308,9 @@ impl EndpointConfig {
             name,
             base_url,
             default_model,
+            provider: Provider::Auto,
             parameters: ModelParameters::default_for_writing(),
+            pricing: vec![],
         }
     }
 }
@@ -127,6 +322,241 @@ pub struct LlmConfig {
     pub endpoints: Vec<EndpointConfig>,
     pub active_endpoint_id: Option<String>,
     pub active_model: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<LlmTask, TaskProfile>,
+}
+
+impl LlmConfig {
+    pub fn profile(&self, task: LlmTask) -> TaskProfile {
+        self.profiles
+            .get(&task)
+            .cloned()
+            .unwrap_or_else(|| TaskProfile::default_for(task))
+    }
+
+    // Adds the default profile for every task that has none, so the settings show them.
+    pub fn fill_default_profiles(&mut self) {
+        for task in LlmTask::ALL {
+            self.profiles
+                .entry(task)
+                .or_insert_with(|| TaskProfile::default_for(task));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LlmTask {
+    Continue,
+    Discuss,
+    Summarize,
+    Polish,
+    Outline,
+    Analysis,
+}
+
+impl LlmTask {
+    pub const ALL: [LlmTask; 6] = [
+        LlmTask::Continue,
+        LlmTask::Discuss,
+        LlmTask::Summarize,
+        LlmTask::Polish,
+        LlmTask::Outline,
+        LlmTask::Analysis,
+    ];
+
+    // the name calls are logged under
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LlmTask::Continue => "continue",
+            LlmTask::Discuss => "discuss",
+            LlmTask::Summarize => "summarize",
+            LlmTask::Polish => "polish",
+            LlmTask::Outline => "outline",
+            LlmTask::Analysis => "analysis",
+        }
+    }
+}
+
+// Which endpoint, model and parameters a task uses. Unset fields fall back to the active
+// endpoint, its active model and the endpoint's own parameters.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProfile {
+    #[serde(default)]
+    pub endpoint_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<ModelParameters>,
+}
+
+impl TaskProfile {
+    // Writing tasks keep the endpoint's parameters, as before profiles existed; summaries and
+    // analysis run cold and short, polishing a little warmer.
+    pub fn default_for(task: LlmTask) -> Self {
+        let parameters = |temperature: f32, max_tokens: u32| {
+            Some(ModelParameters {
+                temperature,
+                max_tokens,
+                ..Default::default()
+            })
+        };
+        TaskProfile {
+            endpoint_id: None,
+            model: None,
+            parameters: match task {
+                LlmTask::Continue | LlmTask::Discuss => None,
+                LlmTask::Summarize => parameters(0.2, 1000),
+                LlmTask::Polish => parameters(0.5, 4000),
+                LlmTask::Outline => parameters(0.7, 2000),
+                LlmTask::Analysis => parameters(0.3, 2000),
+            },
+        }
+    }
+}
+
+// Token counts as reported in a response's `usage` field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+// One chat completion call, kept in .creatorai/llm_log so bad output can be traced back to
+// exactly what the model was sent. `response` is the raw response body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmAuditEntry {
+    pub id: String,
+    pub created_at: String,
+    // "continue", "discuss", ...
+    pub action: String,
+    pub endpoint_id: String,
+    pub endpoint_name: String,
+    pub model: String,
+    pub parameters: ModelParameters,
+    pub messages: Vec<serde_json::Value>,
+    // the structured output schema sent with the request
+    #[serde(default)]
+    pub response_schema: Option<serde_json::Value>,
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    #[serde(default)]
+    pub response: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    // the entry this call re-ran, for replays
+    #[serde(default)]
+    pub replay_of: Option<String>,
+}
+
+// One line of the app-wide usage ledger (usage.jsonl in the app data dir). `day` is the
+// local date; `cost` is None when the model had no price set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    pub created_at: String,
+    pub day: String,
+    pub project_dir: String,
+    pub endpoint_id: String,
+    pub endpoint_name: String,
+    pub model: String,
+    pub action: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UsageGroupBy {
+    Day,
+    Project,
+    Endpoint,
+    Action,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    pub key: String,
+    pub label: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    // calls whose model had no price, so `cost` understates the total
+    pub unpriced_calls: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub budget: BudgetSettings,
+    pub today: UsageBucket,
+    pub this_month: UsageBucket,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextKind {
+    Preset,
+    Summary,
+    Chapter,
+    History,
+    Instruction,
+}
+
+// One piece of story context in a prompt and roughly what it costs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextPiece {
+    pub kind: ContextKind,
+    // the chapter title for summaries and chapters
+    pub label: String,
+    pub chars: usize,
+    pub estimated_tokens: u64,
+}
+
+// Everything a model request would be, without sending it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun {
+    pub task: LlmTask,
+    pub endpoint_id: String,
+    pub endpoint_name: String,
+    // Auto resolved to what the endpoint's address says
+    pub provider: Provider,
+    pub model: String,
+    pub parameters: ModelParameters,
+    pub messages: Vec<serde_json::Value>,
+    // the body that would be posted, after per-provider filtering
+    pub request_body: serde_json::Value,
+    pub context: Vec<ContextPiece>,
+    pub estimated_prompt_tokens: u64,
+    // with max_tokens of output, if the model has a price
+    pub estimated_max_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmAuditIndexItem {
+    pub id: String,
+    pub created_at: String,
+    pub action: String,
+    pub endpoint_name: String,
+    pub model: String,
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub total_tokens: Option<u64>,
+    pub ok: bool,
+    #[serde(default)]
+    pub replay_of: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -151,3 +581,246 @@ pub struct ChatSession {
     pub title: String,
     pub messages: Vec<ChatMessage>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterRange {
+    #[serde(default)]
+    pub start_id: Option<u32>,
+    #[serde(default)]
+    pub end_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpubExportOptions {
+    pub output_path: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub cover_image_path: Option<String>,
+    #[serde(default)]
+    pub range: Option<ChapterRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocxExportOptions {
+    pub output_path: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub range: Option<ChapterRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownExportOptions {
+    // a single .md file, or a directory when split_chapters is set
+    pub output_path: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub split_chapters: bool,
+    #[serde(default)]
+    pub range: Option<ChapterRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManuscriptImportOptions {
+    pub file_path: String,
+    // encoding label such as "utf-8" or "gbk"; detected automatically when empty
+    #[serde(default)]
+    pub encoding: Option<String>,
+    // regexes matched against trimmed lines; built-in 第X章 / Chapter N / Markdown rules when empty
+    #[serde(default)]
+    pub heading_patterns: Option<Vec<String>>,
+    #[serde(default)]
+    pub volume_patterns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreviewChapter {
+    pub title: String,
+    pub volume: Option<String>,
+    pub char_count: u32,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub encoding: Option<String>,
+    pub chapters: Vec<ImportPreviewChapter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    // number of automatic backups to keep; manual backups are never pruned
+    pub keep: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 30,
+            keep: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    pub created_at: String,
+    pub size: u64,
+    pub automatic: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VersioningSettings {
+    pub enabled: bool,
+    // commit author; falls back to "CreatorAI" when empty
+    #[serde(default)]
+    pub author_name: String,
+    #[serde(default)]
+    pub author_email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VcsCommit {
+    pub id: String,
+    pub short_id: String,
+    pub message: String,
+    pub author: String,
+    pub time: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegrityIssueKind {
+    OrphanedFile,
+    MissingText,
+    MissingMeta,
+    MissingSession,
+    DuplicateId,
+    MalformedJson,
+    LeftoverTemp,
+    // project.db is missing, unreadable or fails SQLite's integrity check
+    DatabaseCorrupt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    // relative to the project directory
+    pub path: String,
+    #[serde(default)]
+    pub chapter_id: Option<u32>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub changes: Vec<String>,
+    // issues that could not be fixed automatically
+    pub remaining: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectLockInfo {
+    pub instance_id: String,
+    pub pid: u32,
+    pub host: String,
+    // unix seconds
+    pub acquired_at: u64,
+    pub heartbeat_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectLockStatus {
+    pub acquired: bool,
+    #[serde(default)]
+    pub holder: Option<ProjectLockInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileChangeKind {
+    Chapter,
+    ChapterIndex,
+    Preset,
+    Session,
+    SessionIndex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectFileChange {
+    pub kind: FileChangeKind,
+    #[serde(default)]
+    pub chapter_id: Option<u32>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    // true when the file no longer exists
+    pub removed: bool,
+}
+
+// Stored in .creatorai/encryption.json; its presence marks the project as encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionSettings {
+    // keychain entry name when the key is remembered on this machine
+    pub key_id: String,
+    pub salt: String,
+    // a known value encrypted with the key, to tell a wrong passphrase from a corrupt file
+    pub check: String,
+    #[serde(default)]
+    pub remember_key: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub remember_key: bool,
+}
+
+// What turning on encryption did about plain-text copies outside the project files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionReport {
+    // backups that held plain text; they are replaced by one fresh encrypted backup
+    pub removed_backups: u32,
+    // the project is kept in git, whose earlier commits still hold plain text
+    pub history_exposed: bool,
+}
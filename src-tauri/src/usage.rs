@@ -0,0 +1,311 @@
+use crate::error::{AppError, ErrorKind};
+use crate::prompt;
+use crate::types::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+// usage.jsonl in the app data dir, set once at startup
+static LEDGER: OnceLock<PathBuf> = OnceLock::new();
+static BUDGET: RwLock<Option<BudgetSettings>> = RwLock::new(None);
+// keeps concurrent calls from interleaving their lines
+static APPEND: Mutex<()> = Mutex::new(());
+
+pub fn init(file: PathBuf) {
+    let _ = LEDGER.set(file);
+}
+
+pub fn set_budget(budget: BudgetSettings) {
+    *BUDGET.write().unwrap_or_else(|e| e.into_inner()) = Some(budget);
+}
+
+fn budget() -> BudgetSettings {
+    BUDGET
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+// Today's date where the user is, so daily budgets reset at local midnight.
+fn local_day() -> String {
+    time::OffsetDateTime::now_local()
+        .unwrap_or_else(|_| time::OffsetDateTime::now_utc())
+        .date()
+        .to_string()
+}
+
+// Estimated cost of a call from the endpoint's price list, if the model has a price.
+pub fn cost(endpoint: &EndpointConfig, model: &str, usage: &TokenUsage) -> Option<f64> {
+    let price = endpoint
+        .pricing
+        .iter()
+        .find(|p| p.model.trim().eq_ignore_ascii_case(model.trim()))?;
+    Some(
+        (usage.prompt_tokens as f64 * price.input_per_million
+            + usage.completion_tokens as f64 * price.output_per_million)
+            / 1_000_000.0,
+    )
+}
+
+fn append_to(file: &Path, record: &UsageRecord) -> Result<(), AppError> {
+    let _append = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+    }
+    let mut line =
+        serde_json::to_string(record).map_err(|e| AppError::new(ErrorKind::Io, "io.serialize").caused_by(e))?;
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .map_err(|e| {
+            AppError::new(ErrorKind::Io, "file.write")
+                .with_param("file", "usage.jsonl")
+                .caused_by(e)
+        })
+}
+
+// Adds a finished call to the ledger.
+pub fn record_call(
+    project_dir: &str,
+    endpoint: &EndpointConfig,
+    model: &str,
+    action: &str,
+    usage: &TokenUsage,
+) -> Result<(), AppError> {
+    let Some(file) = LEDGER.get() else {
+        return Ok(());
+    };
+    let record = UsageRecord {
+        created_at: prompt::now_iso(),
+        day: local_day(),
+        project_dir: project_dir.to_string(),
+        endpoint_id: endpoint.id.clone(),
+        endpoint_name: endpoint.name.clone(),
+        model: model.to_string(),
+        action: action.to_string(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        cost: cost(endpoint, model, usage),
+    };
+    append_to(file, &record)
+}
+
+// A line cut short by a crash is skipped rather than making the whole ledger unreadable.
+fn read_from(file: &Path) -> Result<Vec<UsageRecord>, AppError> {
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let raw = fs::read_to_string(file).map_err(|e| AppError::read("usage.jsonl", e))?;
+    Ok(raw.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+fn records() -> Result<Vec<UsageRecord>, AppError> {
+    match LEDGER.get() {
+        Some(file) => read_from(file),
+        None => Ok(vec![]),
+    }
+}
+
+fn add(bucket: &mut UsageBucket, record: &UsageRecord) {
+    bucket.calls += 1;
+    bucket.prompt_tokens += record.prompt_tokens;
+    bucket.completion_tokens += record.completion_tokens;
+    match record.cost {
+        Some(cost) => bucket.cost += cost,
+        None => bucket.unpriced_calls += 1,
+    }
+}
+
+fn total<'a>(key: &str, records: impl Iterator<Item = &'a UsageRecord>) -> UsageBucket {
+    let mut bucket = UsageBucket {
+        key: key.to_string(),
+        label: key.to_string(),
+        ..Default::default()
+    };
+    records.for_each(|r| add(&mut bucket, r));
+    bucket
+}
+
+// Totals per day (newest first) or per project, endpoint or task (largest first).
+fn group(records: &[UsageRecord], group_by: UsageGroupBy) -> Vec<UsageBucket> {
+    let mut buckets: BTreeMap<String, UsageBucket> = BTreeMap::new();
+    for r in records {
+        let (key, label) = match group_by {
+            UsageGroupBy::Day => (r.day.clone(), r.day.clone()),
+            UsageGroupBy::Project => (
+                r.project_dir.clone(),
+                Path::new(&r.project_dir)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| r.project_dir.clone()),
+            ),
+            UsageGroupBy::Endpoint => (r.endpoint_id.clone(), r.endpoint_name.clone()),
+            UsageGroupBy::Action => (r.action.clone(), r.action.clone()),
+        };
+        let bucket = buckets.entry(key.clone()).or_insert_with(|| UsageBucket {
+            key,
+            ..Default::default()
+        });
+        // the latest name wins for renamed endpoints
+        bucket.label = label;
+        add(bucket, r);
+    }
+    let mut out = buckets.into_values().collect::<Vec<_>>();
+    match group_by {
+        UsageGroupBy::Day => out.reverse(),
+        _ => out.sort_by_key(|b| std::cmp::Reverse(b.prompt_tokens + b.completion_tokens)),
+    }
+    out
+}
+
+// Usage between the `from` and `to` days (inclusive, YYYY-MM-DD), optionally for one project.
+pub fn summary(
+    group_by: UsageGroupBy,
+    from: Option<String>,
+    to: Option<String>,
+    project_dir: Option<String>,
+) -> Result<Vec<UsageBucket>, AppError> {
+    let records = records()?
+        .into_iter()
+        .filter(|r| from.as_ref().is_none_or(|f| &r.day >= f))
+        .filter(|r| to.as_ref().is_none_or(|t| &r.day <= t))
+        .filter(|r| project_dir.as_ref().is_none_or(|p| &r.project_dir == p))
+        .collect::<Vec<_>>();
+    Ok(group(&records, group_by))
+}
+
+fn status_of(records: &[UsageRecord], budget: BudgetSettings, today: &str) -> BudgetStatus {
+    let month = &today[..today.len().min(7)];
+    BudgetStatus {
+        budget,
+        today: total(today, records.iter().filter(|r| r.day == today)),
+        this_month: total(month, records.iter().filter(|r| r.day.starts_with(month))),
+    }
+}
+
+pub fn status() -> Result<BudgetStatus, AppError> {
+    Ok(status_of(&records()?, budget(), &local_day()))
+}
+
+fn check(status: &BudgetStatus) -> Result<(), AppError> {
+    let over = |spent: f64, limit: Option<f64>| limit.is_some_and(|l| spent >= l);
+    let b = &status.budget;
+    let tokens = |u: &UsageBucket| (u.prompt_tokens + u.completion_tokens) as f64;
+    if over(status.today.cost, b.daily_cost) || over(tokens(&status.today), b.daily_tokens.map(|t| t as f64)) {
+        return Err(AppError::new(ErrorKind::Budget, "llm.dailyBudgetExceeded"));
+    }
+    if over(status.this_month.cost, b.monthly_cost)
+        || over(tokens(&status.this_month), b.monthly_tokens.map(|t| t as f64))
+    {
+        return Err(AppError::new(ErrorKind::Budget, "llm.monthlyBudgetExceeded"));
+    }
+    Ok(())
+}
+
+// Refuses a model request once a budget limit has been reached.
+pub fn ensure_within_budget() -> Result<(), AppError> {
+    let budget = budget();
+    if budget.daily_cost.is_none()
+        && budget.monthly_cost.is_none()
+        && budget.daily_tokens.is_none()
+        && budget.monthly_tokens.is_none()
+    {
+        return Ok(());
+    }
+    check(&status_of(&records()?, budget, &local_day()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(day: &str, project: &str, action: &str, tokens: u64, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            created_at: format!("{day}T08:00:00Z"),
+            day: day.to_string(),
+            project_dir: format!("/novels/{project}"),
+            endpoint_id: "ep".to_string(),
+            endpoint_name: "OpenAI".to_string(),
+            model: "gpt-4o".to_string(),
+            action: action.to_string(),
+            prompt_tokens: tokens,
+            completion_tokens: tokens / 2,
+            cost,
+        }
+    }
+
+    #[test]
+    fn prices_groups_and_enforces_budgets() {
+        let mut ep = EndpointConfig::new("OpenAI".into(), "https://api.openai.com/v1".into(), "gpt-4o".into());
+        ep.pricing.push(ModelPricing {
+            model: "GPT-4o".to_string(),
+            input_per_million: 2.5,
+            output_per_million: 10.0,
+        });
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            total_tokens: 1_100_000,
+        };
+        assert_eq!(cost(&ep, "gpt-4o", &usage), Some(3.5));
+        assert_eq!(cost(&ep, "gpt-4o-mini", &usage), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("usage.jsonl");
+        for r in [
+            record("2024-05-30", "雾港", "continue", 1000, Some(0.5)),
+            record("2024-06-01", "雾港", "discuss", 2000, None),
+            record("2024-06-02", "灯塔", "continue", 4000, Some(1.0)),
+        ] {
+            append_to(&file, &r).unwrap();
+        }
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .unwrap()
+            .write_all(b"{\"createdAt\":")
+            .unwrap();
+        let records = read_from(&file).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let days = group(&records, UsageGroupBy::Day);
+        assert_eq!(
+            days.iter().map(|b| b.key.as_str()).collect::<Vec<_>>(),
+            ["2024-06-02", "2024-06-01", "2024-05-30"]
+        );
+        let projects = group(&records, UsageGroupBy::Project);
+        assert_eq!(projects[0].label, "灯塔");
+        assert_eq!(projects[1].calls, 2);
+        assert_eq!(projects[1].cost, 0.5);
+        assert_eq!(projects[1].unpriced_calls, 1);
+
+        let status = status_of(&records, BudgetSettings::default(), "2024-06-02");
+        assert_eq!(status.today.calls, 1);
+        assert_eq!(status.this_month.prompt_tokens, 6000);
+        assert!(check(&status).is_ok());
+
+        let capped = |budget| check(&status_of(&records, budget, "2024-06-02")).map_err(|e| e.key);
+        let monthly = BudgetSettings {
+            monthly_cost: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(capped(monthly).unwrap_err(), "llm.monthlyBudgetExceeded");
+        let daily = BudgetSettings {
+            daily_tokens: Some(5000),
+            ..Default::default()
+        };
+        assert_eq!(capped(daily).unwrap_err(), "llm.dailyBudgetExceeded");
+        let roomy = BudgetSettings {
+            daily_tokens: Some(10_000),
+            monthly_cost: Some(5.0),
+            ..Default::default()
+        };
+        assert!(capped(roomy).is_ok());
+    }
+}
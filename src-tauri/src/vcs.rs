@@ -0,0 +1,458 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{durable, i18n, lock, storage, store, types::*};
+use gix::bstr::ByteSlice;
+use gix::config::tree::gitoxide::Committer;
+use gix::objs::tree::EntryKind;
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+use gix::refs::Target;
+use gix::ObjectId;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const COMMIT_FAILED_EVENT: &str = "vcs-commit-failed";
+const GITIGNORE: &str = ".creatorai/backups/\n.creatorai/llm_log/\n.creatorai/lock.json\nproject.db-journal\n*.tmp\n.DS_Store\n";
+// Kept as-is when checking out an old state, otherwise versioning would switch itself off.
+const SETTINGS_PATH: &str = ".creatorai/versioning.json";
+
+// Chapter saves arrive every few seconds while writing; they are collected per project
+// and committed together once the project has been quiet for a while.
+struct PendingSaves {
+    chapters: BTreeMap<u32, String>,
+    last_save: Instant,
+}
+
+static PENDING: Mutex<BTreeMap<String, PendingSaves>> = Mutex::new(BTreeMap::new());
+
+fn git_err(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Io, "vcs.failed").caused_by(e)
+}
+
+// Commit messages are part of the project, so they follow its language.
+fn message(root: &Path, key: &'static str, params: &[(&str, &dyn std::fmt::Display)]) -> String {
+    i18n::text_with(storage::project_locale(root), key, params)
+}
+
+fn signature(settings: &VersioningSettings) -> gix::actor::Signature {
+    let or = |v: &str, fallback: &str| {
+        let v = v.trim();
+        if v.is_empty() { fallback } else { v }.to_string()
+    };
+    gix::actor::Signature {
+        name: or(&settings.author_name, "CreatorAI").into(),
+        email: or(&settings.author_email, "creatorai@localhost").into(),
+        time: gix::date::Time::now_local_or_utc(),
+    }
+}
+
+// Opens the project's own repository, creating one on first use. Projects that are
+// already kept in git are committed to on their current branch, touching only the app's files.
+fn open_or_init(root: &Path, settings: &VersioningSettings) -> Result<gix::Repository, AppError> {
+    let mut repo = if root.join(".git").exists() {
+        gix::open(root).map_err(git_err)?
+    } else {
+        let repo = gix::init(root).map_err(git_err)?;
+        let ignore = root.join(".gitignore");
+        if !ignore.exists() {
+            durable::write(&ignore, GITIGNORE.as_bytes()).map_err(|e| AppError::io("vcs.writeIgnore", &ignore, e))?;
+        }
+        repo
+    };
+
+    // reflog entries need a committer; only used when git has no user configured
+    let sig = signature(settings);
+    let mut config = repo.config_snapshot_mut();
+    config
+        .set_value(&Committer::NAME_FALLBACK, sig.name.as_bstr())
+        .map_err(git_err)?;
+    config
+        .set_value(&Committer::EMAIL_FALLBACK, sig.email.as_bstr())
+        .map_err(git_err)?;
+    config.commit().map_err(git_err)?;
+    Ok(repo)
+}
+
+// The app's files in the project folder; nothing else is committed, checked out or deleted.
+fn app_files(root: &Path) -> Result<Vec<(String, std::path::PathBuf)>, AppError> {
+    Ok(storage::project_files(root)?
+        .into_iter()
+        .filter(|(rel, _)| storage::is_app_file(rel))
+        .collect())
+}
+
+fn head_tree(repo: &gix::Repository) -> Result<Option<ObjectId>, AppError> {
+    let Ok(head) = repo.head_id() else {
+        return Ok(None);
+    };
+    let commit = repo.find_commit(head).map_err(git_err)?;
+    Ok(Some(commit.tree_id().map_err(git_err)?.detach()))
+}
+
+// Paths of the app's files recorded in `tree`.
+fn app_paths_in(repo: &gix::Repository, tree: ObjectId) -> Result<HashSet<String>, AppError> {
+    let index = repo.index_from_tree(&tree).map_err(git_err)?;
+    Ok(index
+        .entries()
+        .iter()
+        .map(|e| e.path(&index).to_str_lossy().to_string())
+        .filter(|rel| storage::is_app_file(rel))
+        .collect())
+}
+
+// `base` with the app's files replaced by what is on disk now; other entries stay as they are.
+fn write_tree(repo: &gix::Repository, root: &Path, base: ObjectId) -> Result<ObjectId, AppError> {
+    let files = app_files(root)?;
+    let mut editor = repo.edit_tree(base).map_err(git_err)?;
+    let present = files.iter().map(|(rel, _)| rel.as_str()).collect::<HashSet<_>>();
+    let committed = app_paths_in(repo, base)?;
+    for gone in committed.iter().filter(|rel| !present.contains(rel.as_str())) {
+        editor.remove(gone.as_str()).map_err(git_err)?;
+    }
+    for (rel, path) in &files {
+        let data = fs::read(path).map_err(|e| AppError::read(&path.display().to_string(), e))?;
+        let blob = repo.write_blob(&data).map_err(git_err)?;
+        editor.upsert(rel.as_str(), EntryKind::Blob, blob).map_err(git_err)?;
+    }
+    Ok(editor.write().map_err(git_err)?.detach())
+}
+
+// Brings the app's entries in the index in line with `tree`, keeping whatever else the user
+// has staged, so `git status` stays clean for people who also use git directly.
+fn update_index(repo: &gix::Repository, tree: ObjectId) -> Result<(), AppError> {
+    let fresh = repo.index_from_tree(&tree).map_err(git_err)?;
+    let mut index = match repo.open_index() {
+        Ok(index) => index,
+        Err(_) => fresh.clone(),
+    };
+    index.remove_entries(|_, path, _| storage::is_app_file(&path.to_str_lossy()));
+    for entry in fresh.entries() {
+        let path = entry.path(&fresh);
+        if storage::is_app_file(&path.to_str_lossy()) {
+            index.dangerously_push_entry(entry.stat, entry.id, entry.flags, entry.mode, path);
+        }
+    }
+    index.sort_entries();
+    // the cached trees may describe entries that were just replaced
+    let options = gix::index::write::Options {
+        extensions: gix::index::write::Extensions::Given {
+            tree_cache: false,
+            end_of_index_entry: true,
+        },
+        ..Default::default()
+    };
+    index.write(options).map_err(git_err)
+}
+
+fn commit_info(commit: &gix::Commit) -> Result<VcsCommit, AppError> {
+    let id = commit.id;
+    let author = commit.author().map_err(git_err)?;
+    let time = commit
+        .time()
+        .ok()
+        .and_then(|t| time::OffsetDateTime::from_unix_timestamp(t.seconds).ok())
+        .and_then(|t| t.format(&time::format_description::well_known::Rfc3339).ok())
+        .unwrap_or_default();
+    Ok(VcsCommit {
+        id: id.to_string(),
+        short_id: id.to_hex_with_len(7).to_string(),
+        message: commit.message_raw_sloppy().to_str_lossy().trim().to_string(),
+        author: author.name.to_str_lossy().to_string(),
+        time,
+    })
+}
+
+// Snapshots the app's files on top of HEAD; nothing is committed when the tree is unchanged.
+fn commit_all(root: &Path, settings: &VersioningSettings, message: &str) -> Result<Option<VcsCommit>, AppError> {
+    let repo = open_or_init(root, settings)?;
+    let parent = repo.head_id().ok().map(|id| id.detach());
+    let base = head_tree(&repo)?.unwrap_or_else(|| ObjectId::empty_tree(repo.object_hash()));
+    let tree = write_tree(&repo, root, base)?;
+    if parent.is_some() && tree == base {
+        return Ok(None);
+    }
+
+    let sig = signature(settings);
+    let (mut committer_time, mut author_time) = (Default::default(), Default::default());
+    let id = repo
+        .commit_as(
+            sig.to_ref(&mut committer_time),
+            sig.to_ref(&mut author_time),
+            "HEAD",
+            message,
+            tree,
+            parent,
+        )
+        .map_err(git_err)?;
+
+    update_index(&repo, tree)?;
+
+    let commit = repo.find_commit(id).map_err(git_err)?;
+    commit_info(&commit).map(Some)
+}
+
+fn take_pending(project_dir: &str) -> Vec<String> {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending
+        .remove(project_dir)
+        .map(|p| p.chapters.into_values().collect())
+        .unwrap_or_default()
+}
+
+fn chapters_message(root: &Path, titles: &[String]) -> String {
+    const SHOWN: usize = 5;
+    let separator = message(root, "vcs.titleSeparator", &[]);
+    let shown = titles.iter().take(SHOWN).cloned().collect::<Vec<_>>().join(&separator);
+    if titles.len() > SHOWN {
+        let count = titles.len();
+        message(root, "vcs.savedManyChapters", &[("titles", &shown), ("count", &count)])
+    } else {
+        message(root, "vcs.savedChapters", &[("titles", &shown)])
+    }
+}
+
+// Commits the current project state for `message`, folding in chapter saves that are still
+// waiting for their batch. Does nothing when versioning is off for the project.
+pub fn commit_event(project_dir: &str, message: &str) -> Result<Option<VcsCommit>, AppError> {
+    let settings = storage::load_versioning_settings(project_dir.to_string())?;
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let saved = take_pending(project_dir);
+    let message = if saved.is_empty() {
+        message.to_string()
+    } else {
+        format!("{message}\n\n{}", chapters_message(Path::new(project_dir), &saved))
+    };
+    commit_all(Path::new(project_dir), &settings, &message)
+}
+
+pub fn note_chapter_saved(project_dir: &str, chapter: &Chapter) {
+    let enabled = storage::load_versioning_settings(project_dir.to_string()).is_ok_and(|s| s.enabled);
+    if !enabled {
+        return;
+    }
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = pending.entry(project_dir.to_string()).or_insert_with(|| PendingSaves {
+        chapters: BTreeMap::new(),
+        last_save: Instant::now(),
+    });
+    entry.chapters.insert(chapter.id, chapter.title.clone());
+    entry.last_save = Instant::now();
+}
+
+// Called by the scheduler: commits every batch of chapter saves that has been idle for `quiet`.
+// The saves themselves already happened, so a failed commit is only reported through `on_failure`.
+pub fn flush_pending(quiet: Duration, on_failure: impl Fn(AppError)) {
+    let due = {
+        let pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+        pending
+            .iter()
+            .filter(|(_, p)| p.last_save.elapsed() >= quiet)
+            .map(|(dir, _)| dir.clone())
+            .collect::<Vec<_>>()
+    };
+    for dir in due {
+        let saved = take_pending(&dir);
+        if saved.is_empty() {
+            continue;
+        }
+        if let Ok(settings) = storage::load_versioning_settings(dir.clone()) {
+            if settings.enabled {
+                let root = Path::new(&dir);
+                if let Err(e) = commit_all(root, &settings, &chapters_message(root, &saved)) {
+                    tracing::warn!(project_dir = %dir, error = %e, "versioning commit failed");
+                    on_failure(AppError::new(ErrorKind::Io, "vcs.autoCommitFailed").caused_by(e));
+                }
+            }
+        }
+    }
+}
+
+pub fn ai_accepted(project_dir: &str, chapter_id: u32) -> Result<Option<VcsCommit>, AppError> {
+    let chapter = store::open(project_dir)?.load_chapter(chapter_id)?;
+    let text = message(Path::new(project_dir), "vcs.aiAccepted", &[("title", &chapter.title)]);
+    commit_event(project_dir, &text)
+}
+
+pub fn preset_changed(project_dir: &str) -> Result<Option<VcsCommit>, AppError> {
+    commit_event(project_dir, &message(Path::new(project_dir), "vcs.presetUpdated", &[]))
+}
+
+// Turning versioning on records the current state as the first commit.
+pub fn save_settings(project_dir: String, settings: &VersioningSettings) -> Result<(), AppError> {
+    storage::save_versioning_settings(project_dir.clone(), settings)?;
+    if settings.enabled {
+        commit_event(&project_dir, &message(Path::new(&project_dir), "vcs.enabled", &[]))?;
+    }
+    Ok(())
+}
+
+// First-parent history of the current branch, newest first.
+pub fn history(project_dir: String, limit: usize) -> Result<Vec<VcsCommit>, AppError> {
+    let root = Path::new(&project_dir);
+    if !root.join(".git").exists() {
+        return Ok(vec![]);
+    }
+    let repo = gix::open(root).map_err(git_err)?;
+    let mut out = vec![];
+    let mut next = repo.head_id().ok().map(|id| id.detach());
+    while let Some(id) = next {
+        if out.len() >= limit {
+            break;
+        }
+        let commit = repo.find_commit(id).map_err(git_err)?;
+        next = commit.parent_ids().next().map(|p| p.detach());
+        out.push(commit_info(&commit)?);
+    }
+    Ok(out)
+}
+
+// Creates `branch_name` at `commit_id`, switches to it and rewrites the app's files to that
+// state. Unsaved work is committed first so the previous branch keeps it. Only files the app
+// committed are removed; anything else in the folder is left alone.
+pub fn checkout(project_dir: String, commit_id: String, branch_name: String) -> Result<(), AppError> {
+    let root = Path::new(&project_dir);
+    let settings = storage::load_versioning_settings(project_dir.clone())?;
+    if !settings.enabled {
+        return Err(AppError::new(ErrorKind::Config, "vcs.notEnabled"));
+    }
+    let branch_name = branch_name.trim();
+    if branch_name.is_empty() {
+        return Err(AppError::new(ErrorKind::Invalid, "vcs.emptyBranch"));
+    }
+    let branch = format!("refs/heads/{branch_name}");
+    if root.join(".git").exists() {
+        let repo = gix::open(root).map_err(git_err)?;
+        if repo.try_find_reference(branch.as_str()).map_err(git_err)?.is_some() {
+            return Err(AppError::new(ErrorKind::Conflict, "vcs.branchExists").with_param("branch", branch_name));
+        }
+    }
+    let text = message(root, "vcs.beforeCheckout", &[("branch", &branch_name)]);
+    commit_event(&project_dir, &text)?;
+
+    let repo = open_or_init(root, &settings)?;
+    let id = ObjectId::from_hex(commit_id.trim().as_bytes())
+        .map_err(|_| AppError::new(ErrorKind::Invalid, "vcs.badCommitId").with_param("id", &commit_id))?;
+    let tree = repo
+        .find_commit(id)
+        .map_err(|_| AppError::new(ErrorKind::NotFound, "vcs.commitNotFound").with_param("id", &commit_id))?
+        .tree_id()
+        .map_err(git_err)?
+        .detach();
+    // what the app committed on the branch being left, all of which is safe to replace
+    let committed = match head_tree(&repo)? {
+        Some(head) => app_paths_in(&repo, head)?,
+        None => HashSet::new(),
+    };
+
+    repo.reference(
+        branch.as_str(),
+        id,
+        PreviousValue::MustNotExist,
+        format!("branch: Created from {id}"),
+    )
+    .map_err(|e| {
+        AppError::new(ErrorKind::Io, "vcs.createBranch")
+            .with_param("branch", branch_name)
+            .caused_by(e)
+    })?;
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: format!("checkout: moving to {branch_name}").into(),
+            },
+            expected: PreviousValue::Any,
+            new: Target::Symbolic(branch.as_str().try_into().map_err(git_err)?),
+        },
+        name: "HEAD".try_into().map_err(git_err)?,
+        deref: false,
+    })
+    .map_err(git_err)?;
+
+    let _write = lock::write_guard(root);
+    let index = repo.index_from_tree(&tree).map_err(git_err)?;
+    let mut wanted = HashSet::new();
+    for entry in index.entries() {
+        let rel = entry.path(&index).to_str_lossy().to_string();
+        if rel == SETTINGS_PATH || !storage::is_app_file(&rel) {
+            continue;
+        }
+        let blob = repo.find_object(entry.id).map_err(git_err)?;
+        let path = root.join(&rel);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AppError::io("io.createDir", dir, e))?;
+        }
+        durable::write(&path, &blob.data).map_err(|e| {
+            AppError::new(ErrorKind::Io, "file.write")
+                .with_param("file", path.display())
+                .caused_by(e)
+        })?;
+        wanted.insert(rel);
+    }
+    for (rel, path) in app_files(root)? {
+        if rel != SETTINGS_PATH && committed.contains(&rel) && !wanted.contains(&rel) {
+            fs::remove_file(&path).map_err(|e| AppError::io("io.remove", &path, e))?;
+        }
+    }
+    update_index(&repo, tree)?;
+    // every file was just replaced on purpose; don't report them as external edits
+    lock::forget_project(root);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enable(root: &str) {
+        let settings = VersioningSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        save_settings(root.to_string(), &settings).unwrap();
+    }
+
+    #[test]
+    fn batches_saves_and_checks_out_into_new_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("novel").to_string_lossy().to_string();
+        storage::init_project(root.clone()).unwrap();
+        let notes = Path::new(&root).join("notes.md");
+        fs::write(&notes, "自己的笔记").unwrap();
+        enable(&root);
+
+        let mut ch = storage::load_chapter(root.clone(), 1).unwrap();
+        ch.content = "初稿".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+        note_chapter_saved(&root, &ch);
+        flush_pending(Duration::ZERO, |e| panic!("{e}"));
+
+        ch.content = "改稿".to_string();
+        storage::save_chapter(root.clone(), &ch).unwrap();
+        preset_changed(&root).unwrap();
+        // unchanged tree: no empty commit
+        assert!(preset_changed(&root).unwrap().is_none());
+
+        let log = history(root.clone(), 10).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[1].message, "保存章节：第一章");
+        assert_eq!(log[2].message, "开启版本管理");
+        assert_eq!(log[0].message, "更新文风预设");
+        // files the app doesn't own are neither committed nor removed on checkout
+        let repo = gix::open(&root).unwrap();
+        let head = repo.head_id().unwrap().detach();
+        let tree = repo.find_commit(head).unwrap().tree_id().unwrap().detach();
+        let index = repo.index_from_tree(&tree).unwrap();
+        assert!(index.entries().iter().all(|e| e.path(&index) != "notes.md"));
+
+        checkout(root.clone(), log[1].id.clone(), "draft-1".to_string()).unwrap();
+        assert_eq!(storage::load_chapter(root.clone(), 1).unwrap().content, "初稿");
+        assert_eq!(fs::read_to_string(&notes).unwrap(), "自己的笔记");
+        assert!(storage::load_versioning_settings(root.clone()).unwrap().enabled);
+        assert!(checkout(root.clone(), log[1].id.clone(), "draft-1".to_string()).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::error::{AppError, ErrorKind};
+use crate::{lock, types::*};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+pub const EVENT: &str = "project-files-changed";
+// Editors and sync clients touch a file several times per save; wait for them to settle.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+type NotifyEvents = Receiver<notify::Result<notify::Event>>;
+
+fn watchers() -> &'static Mutex<HashMap<PathBuf, RecommendedWatcher>> {
+    static WATCHERS: OnceLock<Mutex<HashMap<PathBuf, RecommendedWatcher>>> = OnceLock::new();
+    WATCHERS.get_or_init(Default::default)
+}
+
+// Maps a path inside the project to what the frontend needs to reload; None for files it
+// doesn't care about (temp files, .creatorai, git metadata, ...).
+fn classify(root: &Path, path: &Path) -> Option<ProjectFileChange> {
+    let rel = path.strip_prefix(root).ok()?;
+    let parts = rel.iter().map(|c| c.to_string_lossy().to_string()).collect::<Vec<_>>();
+    let change = |kind, chapter_id, session_id| ProjectFileChange {
+        kind,
+        chapter_id,
+        session_id,
+        removed: !path.exists(),
+    };
+    match parts.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["config.json"] => Some(change(FileChangeKind::Preset, None, None)),
+        ["chapters", "index.json"] => Some(change(FileChangeKind::ChapterIndex, None, None)),
+        ["chat_sessions", "index.json"] => Some(change(FileChangeKind::SessionIndex, None, None)),
+        ["chapters", name] => {
+            let (id, ext) = name.strip_prefix("chapter_")?.split_once('.')?;
+            if ext != "txt" && ext != "json" {
+                return None;
+            }
+            Some(change(FileChangeKind::Chapter, Some(id.parse().ok()?), None))
+        }
+        ["chat_sessions", name] => {
+            let id = name.strip_prefix("session_")?.strip_suffix(".json")?;
+            Some(change(FileChangeKind::Session, None, Some(id.to_string())))
+        }
+        _ => None,
+    }
+}
+
+// Paths the app wrote itself are dropped, so saving a chapter doesn't bounce back as a change.
+fn external_changes(root: &Path, paths: &BTreeSet<PathBuf>) -> Vec<ProjectFileChange> {
+    paths
+        .iter()
+        .filter(|p| !lock::is_known_state(p))
+        .filter_map(|p| classify(root, p))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn debounce(root: PathBuf, events: NotifyEvents, on_change: impl Fn(Vec<ProjectFileChange>)) {
+    let mut pending = BTreeSet::new();
+    loop {
+        let next = if pending.is_empty() {
+            events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            events.recv_timeout(DEBOUNCE)
+        };
+        match next {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => pending.extend(event.paths),
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                let changes = external_changes(&root, &std::mem::take(&mut pending));
+                if !changes.is_empty() {
+                    on_change(changes);
+                }
+            }
+            // the watcher was dropped
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+// Starts watching `project_dir`, replacing an earlier watcher on the same project.
+pub fn watch(project_dir: String, on_change: impl Fn(Vec<ProjectFileChange>) + Send + 'static) -> Result<(), AppError> {
+    let root = PathBuf::from(&project_dir);
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| AppError::new(ErrorKind::Io, "watcher.start").caused_by(e))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| AppError::new(ErrorKind::Io, "watcher.start").caused_by(e))?;
+    let thread_root = root.clone();
+    std::thread::spawn(move || debounce(thread_root, rx, on_change));
+    watchers()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root, watcher);
+    Ok(())
+}
+
+pub fn unwatch(project_dir: String) {
+    watchers()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&PathBuf::from(project_dir));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn reports_external_edits_but_not_own_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("chapters")).unwrap();
+        let ours = root.join("chapters").join("chapter_001.txt");
+        let theirs = root.join("chapters").join("chapter_002.txt");
+        fs::write(&ours, "ours").unwrap();
+        lock::remember(&ours, b"ours");
+        fs::write(&theirs, "edited in vim").unwrap();
+
+        let paths = [
+            ours,
+            theirs,
+            root.join("chapters").join("index.json.tmp"),
+            root.join("config.json"),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+        let changes = external_changes(&root, &paths);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, FileChangeKind::Chapter);
+        assert_eq!(changes[0].chapter_id, Some(2));
+        assert_eq!(changes[1].kind, FileChangeKind::Preset);
+        assert!(changes[1].removed);
+    }
+}
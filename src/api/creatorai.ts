@@ -1,37 +1,82 @@
 import { invoke } from "@tauri-apps/api/core";
 import type {
+  AppError,
   AppState,
+  BackupInfo,
+  BackupSettings,
+  BudgetStatus,
   Chapter,
   ChapterIndexItem,
   ChatMessage,
   ChatSession,
   ChatSessionIndexItem,
+  DocxExportOptions,
+  DryRun,
+  EncryptionReport,
+  EncryptionStatus,
+  EpubExportOptions,
+  ErrorKind,
   GenerationResponse,
+  ImportPreview,
+  IntegrityReport,
+  LlmAuditEntry,
+  LlmAuditIndexItem,
   LlmConfig,
+  LlmTask,
+  ManuscriptImportOptions,
+  MarkdownExportOptions,
   Preset,
   ProjectInfo,
+  PromptMessage,
+  PromptTemplate,
+  PromptTemplateName,
+  ProjectLockStatus,
+  RepairReport,
+  SearchHit,
+  StorageBackend,
   SummaryRecord,
+  UsageBucket,
+  UsageGroupBy,
+  VcsCommit,
+  VersioningSettings,
 } from "../types";
 
 export const api = {
   appGetState: () => invoke<AppState>("app_get_state"),
   appSetState: (next: AppState) => invoke<void>("app_set_state", { next }),
   appGetDefaultProjectDir: () => invoke<string>("app_get_default_project_dir"),
+  diagnosticsExport: (projectDir: string | null, outputPath: string) =>
+    invoke<void>("diagnostics_export", { projectDir, outputPath }),
 
   storageInitProject: (projectDir: string) =>
     invoke<ProjectInfo>("storage_init_project", { projectDir }),
+  projectAcquireLock: (projectDir: string, force: boolean) =>
+    invoke<ProjectLockStatus>("project_acquire_lock", { projectDir, force }),
+  projectReleaseLock: (projectDir: string) => invoke<void>("project_release_lock", { projectDir }),
+  projectWatch: (projectDir: string) => invoke<void>("project_watch", { projectDir }),
+  projectUnwatch: (projectDir: string) => invoke<void>("project_unwatch", { projectDir }),
   storageListChapters: (projectDir: string) =>
     invoke<ChapterIndexItem[]>("storage_list_chapters", { projectDir }),
   storageCreateChapter: (projectDir: string, title: string) =>
     invoke<ChapterIndexItem>("storage_create_chapter", { projectDir, title }),
   storageRenameChapter: (projectDir: string, id: number, title: string) =>
     invoke<void>("storage_rename_chapter", { projectDir, id, title }),
+  storageSetChapterVolume: (projectDir: string, id: number, volume: string | null) =>
+    invoke<void>("storage_set_chapter_volume", { projectDir, id, volume }),
   storageDeleteChapter: (projectDir: string, id: number) =>
     invoke<void>("storage_delete_chapter", { projectDir, id }),
   storageLoadChapter: (projectDir: string, id: number) =>
     invoke<Chapter>("storage_load_chapter", { projectDir, id }),
-  storageSaveChapter: (projectDir: string, chapter: Chapter) =>
-    invoke<void>("storage_save_chapter", { projectDir, chapter }),
+  storageSaveChapter: (projectDir: string, chapter: Chapter, force?: boolean) =>
+    invoke<void>("storage_save_chapter", { projectDir, chapter, force }),
+  storageVerifyProject: (projectDir: string) =>
+    invoke<IntegrityReport>("storage_verify_project", { projectDir }),
+  storageRepairProject: (projectDir: string) => invoke<RepairReport>("storage_repair_project", { projectDir }),
+  storageSearch: (projectDir: string, query: string, limit?: number) =>
+    invoke<SearchHit[]>("storage_search", { projectDir, query, limit }),
+  storageGetBackend: (projectDir: string) => invoke<StorageBackend>("storage_get_backend", { projectDir }),
+  storageConvertBackend: (projectDir: string, backend: StorageBackend) =>
+    invoke<void>("storage_convert_backend", { projectDir, backend }),
 
   storageLoadSummaries: (projectDir: string) =>
     invoke<SummaryRecord[]>("storage_load_summaries", { projectDir }),
@@ -45,6 +90,51 @@ export const api = {
   presetExport: (filePath: string, preset: Preset) => invoke<void>("preset_export", { filePath, preset }),
   presetImport: (filePath: string) => invoke<Preset>("preset_import", { filePath }),
 
+  exportEpub: (projectDir: string, options: EpubExportOptions) =>
+    invoke<void>("export_epub", { projectDir, options }),
+  exportDocx: (projectDir: string, options: DocxExportOptions) =>
+    invoke<void>("export_docx", { projectDir, options }),
+  exportMarkdown: (projectDir: string, options: MarkdownExportOptions) =>
+    invoke<void>("export_markdown", { projectDir, options }),
+
+  importPreviewManuscript: (options: ManuscriptImportOptions) =>
+    invoke<ImportPreview>("import_preview_manuscript", { options }),
+  importManuscript: (projectDir: string, options: ManuscriptImportOptions) =>
+    invoke<ChapterIndexItem[]>("import_manuscript", { projectDir, options }),
+  importPreviewScrivener: (path: string) => invoke<ImportPreview>("import_preview_scrivener", { path }),
+  importScrivener: (projectDir: string, path: string) =>
+    invoke<ChapterIndexItem[]>("import_scrivener", { projectDir, path }),
+  importPreviewFolder: (dirPath: string) => invoke<ImportPreview>("import_preview_folder", { dirPath }),
+  importFolder: (projectDir: string, dirPath: string) =>
+    invoke<ChapterIndexItem[]>("import_folder", { projectDir, dirPath }),
+
+  projectBackup: (projectDir: string, outputPath?: string) =>
+    invoke<BackupInfo>("project_backup", { projectDir, outputPath }),
+  projectListBackups: (projectDir: string) => invoke<BackupInfo[]>("project_list_backups", { projectDir }),
+  projectRestore: (archivePath: string, targetDir: string) =>
+    invoke<ProjectInfo>("project_restore", { archivePath, targetDir }),
+  projectLoadBackupSettings: (projectDir: string) =>
+    invoke<BackupSettings>("project_load_backup_settings", { projectDir }),
+  projectSaveBackupSettings: (projectDir: string, settings: BackupSettings) =>
+    invoke<void>("project_save_backup_settings", { projectDir, settings }),
+
+  vcsLoadSettings: (projectDir: string) => invoke<VersioningSettings>("vcs_load_settings", { projectDir }),
+  vcsSaveSettings: (projectDir: string, settings: VersioningSettings) =>
+    invoke<void>("vcs_save_settings", { projectDir, settings }),
+  vcsRecordAiAccepted: (projectDir: string, chapterId: number) =>
+    invoke<VcsCommit | null>("vcs_record_ai_accepted", { projectDir, chapterId }),
+  vcsHistory: (projectDir: string, limit?: number) => invoke<VcsCommit[]>("vcs_history", { projectDir, limit }),
+  vcsCheckout: (projectDir: string, commitId: string, branchName: string) =>
+    invoke<void>("vcs_checkout", { projectDir, commitId, branchName }),
+  cryptoStatus: (projectDir: string) => invoke<EncryptionStatus>("crypto_status", { projectDir }),
+  cryptoUnlock: (projectDir: string, passphrase: string, remember: boolean) =>
+    invoke<void>("crypto_unlock", { projectDir, passphrase, remember }),
+  cryptoLock: (projectDir: string) => invoke<void>("crypto_lock", { projectDir }),
+  cryptoEncryptProject: (projectDir: string, passphrase: string, remember: boolean) =>
+    invoke<EncryptionReport>("crypto_encrypt_project", { projectDir, passphrase, remember }),
+  cryptoDecryptProject: (projectDir: string, passphrase: string) =>
+    invoke<void>("crypto_decrypt_project", { projectDir, passphrase }),
+
   storageLoadLlmConfig: (projectDir: string) =>
     invoke<LlmConfig>("storage_load_llm_config", { projectDir }),
   storageSaveLlmConfig: (projectDir: string, config: LlmConfig) =>
@@ -74,4 +164,40 @@ export const api = {
     invoke<GenerationResponse>("llm_continue", { projectDir, chapterId, instruction }),
   llmDiscuss: (projectDir: string, sessionId: string, userMessage: string) =>
     invoke<ChatMessage>("llm_discuss", { projectDir, sessionId, userMessage }),
+  llmDryRun: (
+    projectDir: string,
+    task: LlmTask,
+    chapterId: number | null,
+    sessionId: string | null,
+    instruction: string,
+  ) => invoke<DryRun>("llm_dry_run", { projectDir, task, chapterId, sessionId, instruction }),
+  promptTemplatesList: (projectDir: string) => invoke<PromptTemplate[]>("prompt_templates_list", { projectDir }),
+  promptTemplateSave: (projectDir: string, name: PromptTemplateName, source: string | null) =>
+    invoke<void>("prompt_template_save", { projectDir, name, source }),
+  promptPreview: (
+    projectDir: string,
+    task: LlmTask,
+    chapterId: number | null,
+    sessionId: string | null,
+    instruction: string,
+    drafts: Partial<Record<PromptTemplateName, string>>,
+  ) => invoke<PromptMessage[]>("prompt_preview", { projectDir, task, chapterId, sessionId, instruction, drafts }),
+  llmAuditList: (projectDir: string) => invoke<LlmAuditIndexItem[]>("llm_audit_list", { projectDir }),
+  llmAuditGet: (projectDir: string, id: string) => invoke<LlmAuditEntry>("llm_audit_get", { projectDir, id }),
+  llmAuditReplay: (projectDir: string, id: string, endpointId: string | null, model: string | null) =>
+    invoke<LlmAuditEntry>("llm_audit_replay", { projectDir, id, endpointId, model }),
+  usageSummary: (groupBy: UsageGroupBy, from: string | null, to: string | null, projectDir: string | null) =>
+    invoke<UsageBucket[]>("usage_summary", { groupBy, from, to, projectDir }),
+  usageBudgetStatus: () => invoke<BudgetStatus>("usage_budget_status"),
 };
+
+// Commands reject with an AppError; anything else (e.g. a bridge failure) is shown as-is.
+export function isAppError(e: unknown, kind?: ErrorKind): e is AppError {
+  if (typeof e !== "object" || e === null || !("kind" in e) || !("message" in e)) return false;
+  return kind === undefined || (e as AppError).kind === kind;
+}
+
+export function errorMessage(e: unknown): string {
+  if (!isAppError(e)) return String(e);
+  return [e.message, ...e.sources].join("：");
+}
@@ -1,13 +1,33 @@
 import { Button, Input, List, Segmented, Select, Space, Tabs, Typography, message } from "antd";
-import { SendOutlined, ThunderboltOutlined } from "@ant-design/icons";
+import { EyeOutlined, SendOutlined, ThunderboltOutlined } from "@ant-design/icons";
 import { useMemo, useState } from "react";
+import { api, errorMessage } from "../api/creatorai";
 import { useCreator } from "../store/creatorStore";
+import type { DryRun } from "../types";
+import { DryRunModal } from "./DryRunModal";
 
 const { Text } = Typography;
 
 export function AIPanel() {
-  const { aiMode, instruction, generated, activeSession, sessions, llmConfig, busy, actions } = useCreator();
+  const { project, activeChapter, aiMode, instruction, generated, activeSession, sessions, llmConfig, busy, actions } =
+    useCreator();
   const [chatInput, setChatInput] = useState("");
+  const [dryRun, setDryRun] = useState<DryRun | null>(null);
+
+  const estimate = async () => {
+    if (!project) return;
+    try {
+      if (aiMode === "continue") {
+        if (!activeChapter) return;
+        setDryRun(await api.llmDryRun(project.projectDir, "continue", activeChapter.id, null, instruction));
+      } else {
+        const sessionId = activeSession?.id ?? null;
+        setDryRun(await api.llmDryRun(project.projectDir, "discuss", null, sessionId, chatInput.trim()));
+      }
+    } catch (e) {
+      message.error(errorMessage(e));
+    }
+  };
 
   const historyItems = useMemo(() => {
     return activeSession?.messages ?? [];
@@ -94,17 +114,23 @@ export function AIPanel() {
                             try {
                               await actions.continueGenerate();
                             } catch (e) {
-                              message.error(String(e));
+                              message.error(errorMessage(e));
                             }
                           }}
                         >
                           生成
                         </Button>
+                        <Button icon={<EyeOutlined />} disabled={!activeChapter} onClick={estimate}>
+                          预估
+                        </Button>
                       </Space>
 
                       {generated && (
                         <div className="generationPreview">
                           <Text className="previewTitle">生成结果</Text>
+                          {generated.truncated && (
+                            <Text type="warning">输出达到最大长度限制，自动续接后仍不完整，可调高 max_tokens 后重试</Text>
+                          )}
                           <div className="previewBox">{generated.content}</div>
                           <Text className="previewTitle">摘要</Text>
                           <div className="previewBox">{generated.summary || "（未生成摘要）"}</div>
@@ -139,12 +165,15 @@ export function AIPanel() {
                               setChatInput("");
                               await actions.sendChat(t);
                             } catch (e) {
-                              message.error(String(e));
+                              message.error(errorMessage(e));
                             }
                           }}
                         >
                           发送
                         </Button>
+                        <Button icon={<EyeOutlined />} disabled={!chatInput.trim()} onClick={estimate}>
+                          预估
+                        </Button>
                       </Space>
                     </>
                   )}
@@ -158,6 +187,7 @@ export function AIPanel() {
           ]}
         />
       </div>
+      <DryRunModal run={dryRun} onClose={() => setDryRun(null)} />
     </div>
   );
 }
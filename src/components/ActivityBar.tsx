@@ -1,4 +1,12 @@
-import { BookOutlined, FileTextOutlined, SettingOutlined, ThunderboltOutlined } from "@ant-design/icons";
+import {
+  BarChartOutlined,
+  BookOutlined,
+  CodeOutlined,
+  FileTextOutlined,
+  HistoryOutlined,
+  SettingOutlined,
+  ThunderboltOutlined,
+} from "@ant-design/icons";
 import type { ReactNode } from "react";
 import type { PanelKey } from "../store/creatorStore";
 import { useCreator } from "../store/creatorStore";
@@ -8,6 +16,9 @@ const items: { key: PanelKey; title: string; icon: ReactNode }[] = [
   { key: "summaries", title: "摘要记录", icon: <FileTextOutlined /> },
   { key: "presets", title: "文风预设", icon: <SettingOutlined /> },
   { key: "models", title: "模型设置", icon: <ThunderboltOutlined /> },
+  { key: "prompts", title: "提示词模板", icon: <CodeOutlined /> },
+  { key: "llmLog", title: "调用记录", icon: <HistoryOutlined /> },
+  { key: "usage", title: "用量统计", icon: <BarChartOutlined /> },
 ];
 
 export function ActivityBar() {
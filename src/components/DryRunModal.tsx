@@ -0,0 +1,80 @@
+import { Descriptions, Modal, Space, Table, Tag, Typography } from "antd";
+import type { ContextKind, ContextPiece, DryRun, Provider } from "../types";
+import { taskLabel } from "./panels/taskLabels";
+
+const { Text, Paragraph } = Typography;
+
+const kindLabels: Record<ContextKind, string> = {
+  preset: "文风预设",
+  summary: "前文摘要",
+  chapter: "章节正文",
+  history: "对话历史",
+  instruction: "指令",
+};
+
+const providerLabels: Record<Provider, string> = {
+  auto: "自动识别",
+  openAi: "OpenAI",
+  openRouter: "OpenRouter",
+  deepSeek: "DeepSeek",
+  gemini: "Gemini",
+  local: "本地服务",
+  generic: "OpenAI 兼容服务",
+};
+
+function pieceLabel(p: ContextPiece) {
+  if (p.kind === "history") return `${kindLabels.history}（${p.label} 条）`;
+  return p.label ? `${kindLabels[p.kind]} · ${p.label}` : kindLabels[p.kind];
+}
+
+export function DryRunModal({ run, onClose }: { run: DryRun | null; onClose: () => void }) {
+  return (
+    <Modal title="请求预估（未发送）" open={!!run} onCancel={onClose} footer={null} width={760}>
+      {run && (
+        <Space direction="vertical" size={12} style={{ width: "100%" }}>
+          <Descriptions size="small" column={2} bordered>
+            <Descriptions.Item label="任务">{taskLabel(run.task)}</Descriptions.Item>
+            <Descriptions.Item label="端点">
+              {run.endpointName}（{providerLabels[run.provider]}）
+            </Descriptions.Item>
+            <Descriptions.Item label="模型">{run.model}</Descriptions.Item>
+            <Descriptions.Item label="输入 Tokens（估）">{run.estimatedPromptTokens}</Descriptions.Item>
+            <Descriptions.Item label="最高费用（估）" span={2}>
+              {run.estimatedMaxCost != null
+                ? `${run.estimatedMaxCost.toFixed(4)}（按输出达到 max_tokens ${run.parameters.maxTokens} 计）`
+                : "未设置该模型的单价"}
+            </Descriptions.Item>
+          </Descriptions>
+
+          <Table
+            size="small"
+            pagination={false}
+            dataSource={run.context.map((p, i) => ({ ...p, key: i }))}
+            columns={[
+              { title: "上下文", key: "label", render: (_: unknown, p: ContextPiece) => pieceLabel(p) },
+              { title: "字数", dataIndex: "chars" },
+              { title: "Tokens（估）", dataIndex: "estimatedTokens" },
+            ]}
+          />
+          <Text type="secondary">Token 数为粗略估算，实际以模型返回的用量为准；其余为提示词模板本身。</Text>
+
+          {run.messages.map((m, i) => (
+            <div key={i}>
+              <Tag>{m.role}</Tag>
+              <Paragraph className="summaryText" style={{ whiteSpace: "pre-wrap" }} ellipsis={{ rows: 6, expandable: true }}>
+                {m.content}
+              </Paragraph>
+            </div>
+          ))}
+
+          <div>
+            <Tag>request</Tag>
+            <Paragraph code style={{ whiteSpace: "pre-wrap" }} ellipsis={{ rows: 8, expandable: true }} copyable>
+              {JSON.stringify(run.requestBody, null, 2)}
+            </Paragraph>
+          </div>
+        </Space>
+      )}
+    </Modal>
+  );
+}
@@ -1,9 +1,12 @@
 import { Typography } from "antd";
 import { useCreator } from "../store/creatorStore";
 import { ChaptersPanel } from "./panels/ChaptersPanel";
+import { LlmLogPanel } from "./panels/LlmLogPanel";
 import { ModelsPanel } from "./panels/ModelsPanel";
 import { PresetsPanel } from "./panels/PresetsPanel";
+import { PromptsPanel } from "./panels/PromptsPanel";
 import { SummariesPanel } from "./panels/SummariesPanel";
+import { UsagePanel } from "./panels/UsagePanel";
 
 const { Text } = Typography;
 
@@ -18,6 +21,9 @@ export function Sidebar() {
           {activePanel === "summaries" && "摘要记录"}
           {activePanel === "presets" && "文风预设"}
           {activePanel === "models" && "模型设置"}
+          {activePanel === "prompts" && "提示词模板"}
+          {activePanel === "llmLog" && "调用记录"}
+          {activePanel === "usage" && "用量统计"}
         </Text>
       </div>
       <div className="panelBody">
@@ -25,6 +31,9 @@ export function Sidebar() {
         {activePanel === "summaries" && <SummariesPanel />}
         {activePanel === "presets" && <PresetsPanel />}
         {activePanel === "models" && <ModelsPanel />}
+        {activePanel === "prompts" && <PromptsPanel />}
+        {activePanel === "llmLog" && <LlmLogPanel />}
+        {activePanel === "usage" && <UsagePanel />}
       </div>
     </div>
   );
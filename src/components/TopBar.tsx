@@ -7,7 +7,7 @@ import { useCreator } from "../store/creatorStore";
 const { Text } = Typography;
 
 export function TopBar() {
-  const { project, llmConfig, busy, actions } = useCreator();
+  const { appState, project, llmConfig, busy, actions } = useCreator();
 
   const endpointOptions = useMemo(() => {
     const eps = llmConfig?.endpoints ?? [];
@@ -76,6 +76,17 @@ export function TopBar() {
             </Space>
           )}
 
+          <Select
+            size="small"
+            style={{ width: 96 }}
+            value={appState?.locale ?? "zh-CN"}
+            options={[
+              { label: "中文", value: "zh-CN" },
+              { label: "English", value: "en" },
+            ]}
+            onChange={actions.setLocale}
+          />
+
           {busy.saving && <Text className="savingHint">保存中…</Text>}
         </Space>
       </div>
@@ -0,0 +1,174 @@
+import { Button, Descriptions, Input, List, Modal, Select, Space, Tag, Typography, message } from "antd";
+import { ReloadOutlined } from "@ant-design/icons";
+import { useEffect, useState } from "react";
+import { api, errorMessage } from "../../api/creatorai";
+import { useCreator } from "../../store/creatorStore";
+import type { LlmAuditEntry, LlmAuditIndexItem } from "../../types";
+import { taskLabel } from "./taskLabels";
+
+const { Text, Paragraph } = Typography;
+
+export function LlmLogPanel() {
+  const { project, llmConfig } = useCreator();
+  const [items, setItems] = useState<LlmAuditIndexItem[]>([]);
+  const [loading, setLoading] = useState(false);
+  const [entry, setEntry] = useState<LlmAuditEntry | null>(null);
+  const [replayEndpointId, setReplayEndpointId] = useState<string | null>(null);
+  const [replayModel, setReplayModel] = useState("");
+  const [replaying, setReplaying] = useState(false);
+
+  const refresh = async () => {
+    if (!project) return;
+    setLoading(true);
+    try {
+      setItems(await api.llmAuditList(project.projectDir));
+    } catch (e) {
+      message.error(errorMessage(e));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    refresh();
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [project?.projectDir]);
+
+  const openEntry = async (id: string) => {
+    if (!project) return;
+    try {
+      const e = await api.llmAuditGet(project.projectDir, id);
+      setEntry(e);
+      setReplayEndpointId(e.endpointId);
+      setReplayModel(e.model);
+    } catch (e) {
+      message.error(errorMessage(e));
+    }
+  };
+
+  if (!project) {
+    return (
+      <div className="placeholder">
+        <Text className="placeholderTitle">调用记录</Text>
+        <Text className="placeholderDesc">请先打开项目</Text>
+      </div>
+    );
+  }
+
+  return (
+    <Space direction="vertical" size={12} style={{ width: "100%" }}>
+      <Button size="small" icon={<ReloadOutlined />} loading={loading} onClick={refresh}>
+        刷新
+      </Button>
+      <List
+        size="small"
+        dataSource={items}
+        locale={{ emptyText: "暂无调用记录" }}
+        renderItem={(it) => (
+          <List.Item className="summaryRow" onClick={() => openEntry(it.id)} style={{ cursor: "pointer" }}>
+            <div className="summaryCard">
+              <div className="summaryTop">
+                <Text className="summaryTitle">
+                  {taskLabel(it.action)} · {it.model}
+                </Text>
+                <Tag color={it.ok ? undefined : "red"}>{it.ok ? `${it.latencyMs} ms` : "失败"}</Tag>
+              </div>
+              <Text type="secondary">
+                {it.createdAt} · {it.endpointName}
+                {it.totalTokens != null && ` · ${it.totalTokens} tokens`}
+                {it.replayOf && " · 重放"}
+              </Text>
+            </div>
+          </List.Item>
+        )}
+      />
+
+      <Modal title="调用详情" open={!!entry} onCancel={() => setEntry(null)} footer={null} width={760}>
+        {entry && (
+          <Space direction="vertical" size={12} style={{ width: "100%" }}>
+            <Descriptions size="small" column={2} bordered>
+              <Descriptions.Item label="时间">{entry.createdAt}</Descriptions.Item>
+              <Descriptions.Item label="任务">{taskLabel(entry.action)}</Descriptions.Item>
+              <Descriptions.Item label="端点">{entry.endpointName}</Descriptions.Item>
+              <Descriptions.Item label="模型">{entry.model}</Descriptions.Item>
+              <Descriptions.Item label="耗时">{entry.latencyMs} ms</Descriptions.Item>
+              <Descriptions.Item label="Tokens">
+                {entry.usage
+                  ? `${entry.usage.promptTokens} + ${entry.usage.completionTokens} = ${entry.usage.totalTokens}`
+                  : "—"}
+              </Descriptions.Item>
+              <Descriptions.Item label="参数" span={2}>
+                <Text code>{JSON.stringify(entry.parameters)}</Text>
+              </Descriptions.Item>
+            </Descriptions>
+
+            {entry.messages.map((m, i) => (
+              <div key={i}>
+                <Tag>{m.role}</Tag>
+                <Paragraph className="summaryText" style={{ whiteSpace: "pre-wrap" }} ellipsis={{ rows: 6, expandable: true }}>
+                  {m.content}
+                </Paragraph>
+              </div>
+            ))}
+
+            {entry.error ? (
+              <Text type="danger">{entry.error}</Text>
+            ) : (
+              <div>
+                <Tag>response</Tag>
+                <Paragraph code style={{ whiteSpace: "pre-wrap" }} ellipsis={{ rows: 8, expandable: true }} copyable>
+                  {entry.response ?? ""}
+                </Paragraph>
+              </div>
+            )}
+
+            <Space>
+              <Select
+                size="small"
+                style={{ width: 160 }}
+                value={replayEndpointId ?? undefined}
+                options={(llmConfig?.endpoints ?? []).map((e) => ({ label: e.name, value: e.id }))}
+                onChange={(id) => {
+                  setReplayEndpointId(id);
+                  const ep = llmConfig?.endpoints.find((e) => e.id === id);
+                  setReplayModel(id === entry.endpointId ? entry.model : ep?.defaultModel ?? "");
+                }}
+              />
+              <Input
+                size="small"
+                style={{ width: 210 }}
+                value={replayModel}
+                onChange={(e) => setReplayModel(e.target.value)}
+                placeholder="模型"
+              />
+              <Button
+                size="small"
+                loading={replaying}
+                onClick={async () => {
+                  setReplaying(true);
+                  try {
+                    const next = await api.llmAuditReplay(
+                      project.projectDir,
+                      entry.id,
+                      replayEndpointId,
+                      replayModel.trim() || null,
+                    );
+                    setEntry(next);
+                    message.success("已重放，结果已记入调用记录");
+                  } catch (e) {
+                    message.error(errorMessage(e));
+                  } finally {
+                    setReplaying(false);
+                    refresh();
+                  }
+                }}
+              >
+                用此模型重放
+              </Button>
+            </Space>
+          </Space>
+        )}
+      </Modal>
+    </Space>
+  );
+}
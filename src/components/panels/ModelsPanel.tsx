@@ -1,6 +1,7 @@
 import {
   Button,
   Card,
+  Checkbox,
   Form,
   Input,
   InputNumber,
@@ -12,13 +13,34 @@ import {
   message,
 } from "antd";
 import { DeleteOutlined, PlusOutlined, ReloadOutlined } from "@ant-design/icons";
+import { save } from "@tauri-apps/plugin-dialog";
 import { useEffect, useMemo, useState } from "react";
-import { api } from "../../api/creatorai";
+import { api, errorMessage } from "../../api/creatorai";
 import { useCreator } from "../../store/creatorStore";
-import type { EndpointConfig, LlmConfig, ModelParameters } from "../../types";
+import type {
+  EndpointConfig,
+  LlmConfig,
+  LlmTask,
+  ModelParameters,
+  ModelPricing,
+  Provider,
+  ReasoningEffort,
+  TaskProfile,
+} from "../../types";
+import { taskLabels } from "./taskLabels";
 
 const { Text } = Typography;
 
+const providerOptions: { label: string; value: Provider }[] = [
+  { label: "自动识别", value: "auto" },
+  { label: "OpenAI", value: "openAi" },
+  { label: "OpenRouter", value: "openRouter" },
+  { label: "DeepSeek", value: "deepSeek" },
+  { label: "Gemini", value: "gemini" },
+  { label: "本地服务（llama.cpp / vLLM 等）", value: "local" },
+  { label: "其他 OpenAI 兼容服务", value: "generic" },
+];
+
 function newEndpointDraft(): Pick<EndpointConfig, "name" | "baseUrl" | "defaultModel"> {
   return {
     name: "New Endpoint",
@@ -28,7 +50,7 @@ function newEndpointDraft(): Pick<EndpointConfig, "name" | "baseUrl" | "defaultM
 }
 
 export function ModelsPanel() {
-  const { llmConfig, actions } = useCreator();
+  const { appState, project, llmConfig, actions } = useCreator();
   const [addOpen, setAddOpen] = useState(false);
   const [addDraft, setAddDraft] = useState(newEndpointDraft());
   const [apiKeyModal, setApiKeyModal] = useState<{ open: boolean; endpointId: string | null }>({
@@ -72,6 +94,24 @@ export function ModelsPanel() {
     await updateConfig({ ...config, endpoints: nextEndpoints });
   };
 
+  const currentModel = config.activeModel ?? activeEndpoint?.defaultModel ?? "";
+  const currentPrice = activeEndpoint?.pricing?.find((p) => p.model.toLowerCase() === currentModel.toLowerCase());
+
+  // one price per model; clearing both prices removes it
+  const updateCurrentPrice = async (patch: Partial<ModelPricing>) => {
+    if (!activeEndpoint || !currentModel) return;
+    const next = { model: currentModel, inputPerMillion: 0, outputPerMillion: 0, ...currentPrice, ...patch };
+    const others = (activeEndpoint.pricing ?? []).filter((p) => p !== currentPrice);
+    const pricing = next.inputPerMillion || next.outputPerMillion ? [...others, next] : others;
+    const nextEndpoints = config.endpoints.map((e) => (e.id === activeEndpoint.id ? { ...e, pricing } : e));
+    await updateConfig({ ...config, endpoints: nextEndpoints });
+  };
+
+  const updateProfile = async (task: LlmTask, patch: Partial<TaskProfile>) => {
+    const current = config.profiles?.[task] ?? {};
+    await updateConfig({ ...config, profiles: { ...config.profiles, [task]: { ...current, ...patch } } });
+  };
+
   if (!llmConfig) {
     return (
       <div className="placeholder">
@@ -109,6 +149,20 @@ export function ModelsPanel() {
             onChange={async (id) => updateConfig({ ...config, activeEndpointId: id })}
           />
 
+          {activeEndpoint && (
+            <Select
+              style={{ width: "100%" }}
+              options={providerOptions}
+              value={activeEndpoint.provider ?? "auto"}
+              onChange={async (provider: Provider) =>
+                updateConfig({
+                  ...config,
+                  endpoints: config.endpoints.map((e) => (e.id === activeEndpoint.id ? { ...e, provider } : e)),
+                })
+              }
+            />
+          )}
+
           {activeEndpoint && (
             <Space wrap>
               <Button
@@ -169,7 +223,7 @@ export function ModelsPanel() {
                   setModelsByEndpoint((mm) => ({ ...mm, [activeEndpoint.id]: models }));
                   message.success(`已拉取 ${models.length} 个模型`);
                 } catch (e) {
-                  message.error(String(e));
+                  message.error(errorMessage(e));
                 } finally {
                   setLoadingModels((m) => ({ ...m, [activeEndpoint.id]: false }));
                 }
@@ -246,6 +300,139 @@ export function ModelsPanel() {
                   }
                 />
               </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">min_p</Text>
+                <InputNumber
+                  min={0}
+                  max={1}
+                  step={0.01}
+                  placeholder="不设置"
+                  value={activeEndpoint.parameters.minP ?? null}
+                  onChange={async (v) => updateActiveEndpointParams({ ...activeEndpoint.parameters, minP: v })}
+                />
+              </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">presence_penalty</Text>
+                <InputNumber
+                  min={-2}
+                  max={2}
+                  step={0.1}
+                  placeholder="不设置"
+                  value={activeEndpoint.parameters.presencePenalty ?? null}
+                  onChange={async (v) => updateActiveEndpointParams({ ...activeEndpoint.parameters, presencePenalty: v })}
+                />
+              </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">frequency_penalty</Text>
+                <InputNumber
+                  min={-2}
+                  max={2}
+                  step={0.1}
+                  placeholder="不设置"
+                  value={activeEndpoint.parameters.frequencyPenalty ?? null}
+                  onChange={async (v) => updateActiveEndpointParams({ ...activeEndpoint.parameters, frequencyPenalty: v })}
+                />
+              </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">repetition_penalty</Text>
+                <InputNumber
+                  min={0.5}
+                  max={2}
+                  step={0.05}
+                  placeholder="不设置"
+                  value={activeEndpoint.parameters.repetitionPenalty ?? null}
+                  onChange={async (v) => updateActiveEndpointParams({ ...activeEndpoint.parameters, repetitionPenalty: v })}
+                />
+              </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">seed</Text>
+                <InputNumber
+                  min={0}
+                  step={1}
+                  placeholder="随机"
+                  value={activeEndpoint.parameters.seed ?? null}
+                  onChange={async (v) => updateActiveEndpointParams({ ...activeEndpoint.parameters, seed: v })}
+                />
+              </Space>
+
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">reasoning_effort</Text>
+                <Select
+                  style={{ width: 120 }}
+                  allowClear
+                  placeholder="不设置"
+                  value={activeEndpoint.parameters.reasoningEffort ?? undefined}
+                  options={["minimal", "low", "medium", "high"].map((v) => ({ label: v, value: v }))}
+                  onChange={async (v?: ReasoningEffort) =>
+                    updateActiveEndpointParams({ ...activeEndpoint.parameters, reasoningEffort: v ?? null })
+                  }
+                />
+              </Space>
+            </Space>
+
+            <Space direction="vertical" style={{ width: "100%" }} size={6}>
+              <Text className="fieldLabel">stop</Text>
+              <Select
+                mode="tags"
+                style={{ width: "100%" }}
+                placeholder="停止序列，回车添加"
+                value={activeEndpoint.parameters.stop ?? []}
+                onChange={async (stop: string[]) => updateActiveEndpointParams({ ...activeEndpoint.parameters, stop })}
+              />
+            </Space>
+
+            <Space direction="vertical" style={{ width: "100%" }} size={6}>
+              <Text className="fieldLabel">额外请求参数（JSON）</Text>
+              <Input.TextArea
+                key={activeEndpoint.id}
+                autoSize={{ minRows: 2, maxRows: 6 }}
+                placeholder='{"user": "novel"}'
+                defaultValue={
+                  activeEndpoint.parameters.extraBody && Object.keys(activeEndpoint.parameters.extraBody).length
+                    ? JSON.stringify(activeEndpoint.parameters.extraBody, null, 2)
+                    : ""
+                }
+                onBlur={async (e) => {
+                  const text = e.target.value.trim();
+                  try {
+                    const extraBody = text ? JSON.parse(text) : {};
+                    if (typeof extraBody !== "object" || extraBody === null || Array.isArray(extraBody)) {
+                      throw new Error();
+                    }
+                    await updateActiveEndpointParams({ ...activeEndpoint.parameters, extraBody });
+                  } catch {
+                    message.error("额外请求参数必须是 JSON 对象");
+                  }
+                }}
+              />
+            </Space>
+
+            <Text type="secondary">当前服务不支持的参数不会发送；额外请求参数总会原样附加。</Text>
+
+            <Space wrap size={14}>
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">输入单价（每百万 Token）</Text>
+                <InputNumber
+                  min={0}
+                  step={0.1}
+                  value={currentPrice?.inputPerMillion ?? null}
+                  onChange={async (v) => updateCurrentPrice({ inputPerMillion: Number(v ?? 0) })}
+                />
+              </Space>
+              <Space direction="vertical" size={4}>
+                <Text className="fieldLabel">输出单价（每百万 Token）</Text>
+                <InputNumber
+                  min={0}
+                  step={0.1}
+                  value={currentPrice?.outputPerMillion ?? null}
+                  onChange={async (v) => updateCurrentPrice({ outputPerMillion: Number(v ?? 0) })}
+                />
+              </Space>
             </Space>
 
             <Text type="secondary">
@@ -255,6 +442,95 @@ export function ModelsPanel() {
         </Card>
       )}
 
+      <Card size="small" className="ivoryCard" title="任务配置">
+        <Space direction="vertical" style={{ width: "100%" }} size={12}>
+          {(Object.keys(taskLabels) as LlmTask[]).map((task) => {
+            const profile = config.profiles?.[task] ?? {};
+            const endpoint = config.endpoints.find((e) => e.id === profile.endpointId) ?? activeEndpoint;
+            const params = profile.parameters ?? endpoint?.parameters;
+            return (
+              <Space key={task} direction="vertical" size={4} style={{ width: "100%" }}>
+                <Text className="fieldLabel">{taskLabels[task]}</Text>
+                <Space wrap size={8}>
+                  <Select
+                    size="small"
+                    style={{ width: 150 }}
+                    allowClear
+                    placeholder="跟随当前端点"
+                    value={profile.endpointId ?? undefined}
+                    options={config.endpoints.map((e) => ({ label: e.name, value: e.id }))}
+                    onChange={(id) => updateProfile(task, { endpointId: id ?? null })}
+                  />
+                  <Input
+                    size="small"
+                    style={{ width: 160 }}
+                    placeholder="默认模型"
+                    defaultValue={profile.model ?? ""}
+                    onBlur={(e) => updateProfile(task, { model: e.target.value.trim() || null })}
+                  />
+                  <InputNumber
+                    size="small"
+                    min={0}
+                    max={2}
+                    step={0.05}
+                    addonBefore="T"
+                    value={params?.temperature}
+                    onChange={(v) =>
+                      params && updateProfile(task, { parameters: { ...params, temperature: Number(v ?? params.temperature) } })
+                    }
+                  />
+                  <InputNumber
+                    size="small"
+                    min={64}
+                    max={200000}
+                    step={256}
+                    addonBefore="max"
+                    value={params?.maxTokens}
+                    onChange={(v) =>
+                      params && updateProfile(task, { parameters: { ...params, maxTokens: Number(v ?? params.maxTokens) } })
+                    }
+                  />
+                  {profile.parameters && (
+                    <Button size="small" type="link" onClick={() => updateProfile(task, { parameters: null })}>
+                      使用端点参数
+                    </Button>
+                  )}
+                </Space>
+              </Space>
+            );
+          })}
+          <Text type="secondary">未指定端点或模型的任务使用当前端点和模型；未单独设置参数的任务沿用端点的参数。</Text>
+        </Space>
+      </Card>
+
+      <Card size="small" className="ivoryCard" title="日志与诊断">
+        <Space direction="vertical" size={8} style={{ width: "100%" }}>
+          <Checkbox checked={appState?.logFullText ?? false} onChange={(e) => actions.setLogFullText(e.target.checked)}>
+            在日志中记录完整的提示词和生成内容
+          </Checkbox>
+          <Text type="secondary">默认只记录字数；API Key 始终不会写入日志。</Text>
+          <Button
+            size="small"
+            onClick={async () => {
+              try {
+                const path = await save({
+                  title: "导出诊断包",
+                  defaultPath: "creatorai-diagnostics.zip",
+                  filters: [{ name: "Zip", extensions: ["zip"] }],
+                });
+                if (!path) return;
+                await api.diagnosticsExport(project?.projectDir ?? null, path);
+                message.success("已导出诊断包");
+              } catch (e) {
+                message.error(errorMessage(e));
+              }
+            }}
+          >
+            导出诊断包
+          </Button>
+        </Space>
+      </Card>
+
       <Modal
         title="添加端点"
         open={addOpen}
@@ -280,7 +556,7 @@ export function ModelsPanel() {
             setAddOpen(false);
             message.success("已添加端点");
           } catch (e) {
-            message.error(String(e));
+            message.error(errorMessage(e));
           }
         }}
       >
@@ -312,7 +588,7 @@ export function ModelsPanel() {
             setApiKeyValue("");
             message.success("已保存 API Key");
           } catch (e) {
-            message.error(String(e));
+            message.error(errorMessage(e));
           }
         }}
       >
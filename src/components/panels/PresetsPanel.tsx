@@ -4,7 +4,7 @@ import { useMemo, useState } from "react";
 import { useCreator } from "../../store/creatorStore";
 import type { Preset } from "../../types";
 import { open, save } from "@tauri-apps/plugin-dialog";
-import { api } from "../../api/creatorai";
+import { api, errorMessage } from "../../api/creatorai";
 
 const { Text } = Typography;
 
@@ -39,7 +39,7 @@ export function PresetsPanel() {
               await api.presetExport(path, current as Preset);
               message.success("已导出预设");
             } catch (e) {
-              message.error(String(e));
+              message.error(errorMessage(e));
             }
           }}
         >
@@ -59,7 +59,7 @@ export function PresetsPanel() {
               setDraft(imported);
               message.success("已导入到草稿（点击保存生效）");
             } catch (e) {
-              message.error(String(e));
+              message.error(errorMessage(e));
             }
           }}
         >
@@ -0,0 +1,151 @@
+import { Button, Card, Input, Modal, Select, Space, Tag, Typography, message } from "antd";
+import { useEffect, useState } from "react";
+import { api, errorMessage } from "../../api/creatorai";
+import { useCreator } from "../../store/creatorStore";
+import type { PromptMessage, PromptTemplate, PromptTemplateName } from "../../types";
+
+const { Text, Paragraph } = Typography;
+
+const templateLabels: Record<PromptTemplateName, string> = {
+  continueSystem: "续写 · 系统提示",
+  continueUser: "续写 · 用户提示",
+  discussSystem: "讨论 · 系统提示",
+  summarizeSystem: "补写摘要 · 系统提示",
+  resume: "截断续接",
+};
+
+function previewTask(name: PromptTemplateName) {
+  return name === "discussSystem" ? "discuss" : "continue";
+}
+
+export function PromptsPanel() {
+  const { project, activeChapter, activeSession, instruction } = useCreator();
+  const [templates, setTemplates] = useState<PromptTemplate[]>([]);
+  const [selected, setSelected] = useState<PromptTemplateName>("continueSystem");
+  const [draft, setDraft] = useState("");
+  const [saving, setSaving] = useState(false);
+  const [preview, setPreview] = useState<PromptMessage[] | null>(null);
+
+  const current = templates.find((t) => t.name === selected);
+
+  const refresh = async () => {
+    if (!project) return;
+    try {
+      setTemplates(await api.promptTemplatesList(project.projectDir));
+    } catch (e) {
+      message.error(errorMessage(e));
+    }
+  };
+
+  useEffect(() => {
+    refresh();
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [project?.projectDir]);
+
+  useEffect(() => {
+    setDraft(current?.source ?? "");
+  }, [current?.name, current?.source]);
+
+  if (!project) {
+    return (
+      <div className="placeholder">
+        <Text className="placeholderTitle">提示词模板</Text>
+        <Text className="placeholderDesc">请先打开项目</Text>
+      </div>
+    );
+  }
+
+  const save = async (source: string | null) => {
+    setSaving(true);
+    try {
+      await api.promptTemplateSave(project.projectDir, selected, source);
+      message.success(source === null ? "已恢复默认模板" : "已保存");
+      await refresh();
+    } catch (e) {
+      message.error(errorMessage(e));
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const openPreview = async () => {
+    try {
+      const task = previewTask(selected);
+      setPreview(
+        await api.promptPreview(
+          project.projectDir,
+          task,
+          activeChapter?.id ?? null,
+          task === "discuss" ? activeSession?.id ?? null : null,
+          instruction,
+          { [selected]: draft },
+        ),
+      );
+    } catch (e) {
+      message.error(errorMessage(e));
+    }
+  };
+
+  return (
+    <Space direction="vertical" size={12} style={{ width: "100%" }}>
+      <Select
+        size="small"
+        style={{ width: "100%" }}
+        value={selected}
+        options={templates.map((t) => ({
+          label: t.customized ? `${templateLabels[t.name]}（已修改）` : templateLabels[t.name],
+          value: t.name,
+        }))}
+        onChange={setSelected}
+      />
+
+      <Card size="small" className="ivoryCard">
+        <Space direction="vertical" size={10} style={{ width: "100%" }}>
+          <Input.TextArea
+            value={draft}
+            onChange={(e) => setDraft(e.target.value)}
+            autoSize={{ minRows: 12, maxRows: 28 }}
+            style={{ fontFamily: "monospace" }}
+          />
+          <Space wrap size={4}>
+            <Text type="secondary">可用变量：</Text>
+            {(current?.variables ?? []).map((v) => (
+              <Tag key={v}>{v}</Tag>
+            ))}
+            {current?.variables.length === 0 && <Text type="secondary">无</Text>}
+          </Space>
+          <Text type="secondary">
+            {"用 {{变量}} 插入内容，{{#if 变量}}…{{else}}…{{/if}} 按是否为空取舍，{{#each 列表}}…{{/each}} 逐项展开（{{this}} 为当前项）。"}
+          </Text>
+          <Space>
+            <Button type="primary" size="small" loading={saving} onClick={() => save(draft)}>
+              保存
+            </Button>
+            <Button size="small" disabled={!current?.customized} loading={saving} onClick={() => save(null)}>
+              恢复默认
+            </Button>
+            {selected !== "summarizeSystem" && selected !== "resume" && (
+              <Button size="small" onClick={openPreview}>
+                预览
+              </Button>
+            )}
+          </Space>
+        </Space>
+      </Card>
+
+      <Modal title="提示词预览" open={!!preview} onCancel={() => setPreview(null)} footer={null} width={760}>
+        <Space direction="vertical" size={12} style={{ width: "100%" }}>
+          <Text type="secondary">按当前章节、对话和指令渲染，未调用模型。</Text>
+          {(preview ?? []).map((m, i) => (
+            <div key={i}>
+              <Tag>{m.role}</Tag>
+              <Paragraph className="summaryText" style={{ whiteSpace: "pre-wrap" }} ellipsis={{ rows: 12, expandable: true }}>
+                {m.content}
+              </Paragraph>
+            </div>
+          ))}
+        </Space>
+      </Modal>
+    </Space>
+  );
+}
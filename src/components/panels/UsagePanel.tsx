@@ -0,0 +1,144 @@
+import { Button, Card, InputNumber, Segmented, Space, Table, Typography, message } from "antd";
+import { ReloadOutlined } from "@ant-design/icons";
+import { useEffect, useState } from "react";
+import { api, errorMessage } from "../../api/creatorai";
+import { useCreator } from "../../store/creatorStore";
+import type { BudgetSettings, BudgetStatus, UsageBucket, UsageGroupBy } from "../../types";
+import { taskLabel } from "./taskLabels";
+
+const { Text } = Typography;
+
+const groupOptions: { label: string; value: UsageGroupBy }[] = [
+  { label: "按日", value: "day" },
+  { label: "按项目", value: "project" },
+  { label: "按端点", value: "endpoint" },
+  { label: "按任务", value: "action" },
+];
+
+function formatCost(b: UsageBucket) {
+  const cost = b.cost.toFixed(4);
+  return b.unpricedCalls > 0 ? `${cost}*` : cost;
+}
+
+export function UsagePanel() {
+  const { appState, actions } = useCreator();
+  const [groupBy, setGroupBy] = useState<UsageGroupBy>("day");
+  const [buckets, setBuckets] = useState<UsageBucket[]>([]);
+  const [status, setStatus] = useState<BudgetStatus | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  const budget = appState?.budget ?? {};
+
+  const refresh = async () => {
+    setLoading(true);
+    try {
+      const [rows, st] = await Promise.all([api.usageSummary(groupBy, null, null, null), api.usageBudgetStatus()]);
+      setBuckets(rows);
+      setStatus(st);
+    } catch (e) {
+      message.error(errorMessage(e));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    refresh();
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [groupBy]);
+
+  const updateBudget = async (patch: Partial<BudgetSettings>) => {
+    await actions.setBudget({ ...budget, ...patch });
+    refresh();
+  };
+
+  return (
+    <Space direction="vertical" size={12} style={{ width: "100%" }}>
+      <Card size="small" className="ivoryCard" title="预算">
+        <Space direction="vertical" size={10} style={{ width: "100%" }}>
+          {status && (
+            <Text type="secondary">
+              今日 {status.today.promptTokens + status.today.completionTokens} tokens / {status.today.cost.toFixed(4)}
+              ；本月 {status.thisMonth.promptTokens + status.thisMonth.completionTokens} tokens /{" "}
+              {status.thisMonth.cost.toFixed(4)}
+            </Text>
+          )}
+          <Space wrap size={14}>
+            <Space direction="vertical" size={4}>
+              <Text className="fieldLabel">每日费用上限</Text>
+              <InputNumber
+                min={0}
+                step={1}
+                placeholder="不限"
+                value={budget.dailyCost ?? null}
+                onChange={(v) => updateBudget({ dailyCost: v })}
+              />
+            </Space>
+            <Space direction="vertical" size={4}>
+              <Text className="fieldLabel">每月费用上限</Text>
+              <InputNumber
+                min={0}
+                step={10}
+                placeholder="不限"
+                value={budget.monthlyCost ?? null}
+                onChange={(v) => updateBudget({ monthlyCost: v })}
+              />
+            </Space>
+            <Space direction="vertical" size={4}>
+              <Text className="fieldLabel">每日 Token 上限</Text>
+              <InputNumber
+                min={0}
+                step={10000}
+                placeholder="不限"
+                value={budget.dailyTokens ?? null}
+                onChange={(v) => updateBudget({ dailyTokens: v })}
+              />
+            </Space>
+            <Space direction="vertical" size={4}>
+              <Text className="fieldLabel">每月 Token 上限</Text>
+              <InputNumber
+                min={0}
+                step={100000}
+                placeholder="不限"
+                value={budget.monthlyTokens ?? null}
+                onChange={(v) => updateBudget({ monthlyTokens: v })}
+              />
+            </Space>
+          </Space>
+          <Text type="secondary">达到任一上限后将不再发送模型请求。费用按“模型设置”中填写的单价估算。</Text>
+        </Space>
+      </Card>
+
+      <Card
+        size="small"
+        className="ivoryCard"
+        title="用量"
+        extra={<Button size="small" icon={<ReloadOutlined />} loading={loading} onClick={refresh} />}
+      >
+        <Space direction="vertical" size={10} style={{ width: "100%" }}>
+          <Segmented size="small" options={groupOptions} value={groupBy} onChange={(v) => setGroupBy(v as UsageGroupBy)} />
+          <Table
+            size="small"
+            rowKey="key"
+            loading={loading}
+            dataSource={buckets}
+            pagination={{ pageSize: 10, size: "small" }}
+            locale={{ emptyText: "暂无用量记录" }}
+            columns={[
+              {
+                title: groupOptions.find((o) => o.value === groupBy)?.label.replace("按", ""),
+                dataIndex: "label",
+                render: (label: string) => (groupBy === "action" ? taskLabel(label) : label),
+              },
+              { title: "次数", dataIndex: "calls" },
+              { title: "输入", dataIndex: "promptTokens" },
+              { title: "输出", dataIndex: "completionTokens" },
+              { title: "费用", key: "cost", render: (_: unknown, b: UsageBucket) => formatCost(b) },
+            ]}
+          />
+          <Text type="secondary">* 含未设置单价的模型调用，实际费用可能更高。</Text>
+        </Space>
+      </Card>
+    </Space>
+  );
+}
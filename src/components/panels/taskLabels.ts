@@ -0,0 +1,15 @@
+import type { LlmTask } from "../../types";
+
+export const taskLabels: Record<LlmTask, string> = {
+  continue: "续写",
+  discuss: "讨论",
+  summarize: "摘要",
+  polish: "润色",
+  outline: "大纲",
+  analysis: "分析",
+};
+
+// logged actions are task names; replays keep the original's
+export function taskLabel(action: string) {
+  return taskLabels[action as LlmTask] ?? action;
+}
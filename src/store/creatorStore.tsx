@@ -1,7 +1,10 @@
 import React, { createContext, useContext, useEffect, useMemo, useRef, useState } from "react";
-import { api } from "../api/creatorai";
+import { listen } from "@tauri-apps/api/event";
+import { api, errorMessage, isAppError } from "../api/creatorai";
 import type {
+  AppError,
   AppState,
+  BudgetSettings,
   Chapter,
   ChapterIndexItem,
   ChatMessage,
@@ -9,12 +12,14 @@ import type {
   ChatSessionIndexItem,
   GenerationResponse,
   LlmConfig,
+  Locale,
   Preset,
+  ProjectFileChange,
   ProjectInfo,
   SummaryRecord,
 } from "../types";
 
-export type PanelKey = "chapters" | "summaries" | "presets" | "models";
+export type PanelKey = "chapters" | "summaries" | "presets" | "models" | "prompts" | "llmLog" | "usage";
 export type AiMode = "continue" | "discuss";
 
 type CreatorContextValue = {
@@ -51,6 +56,9 @@ type CreatorContextValue = {
     deleteChapter: (id: number) => Promise<void>;
     savePreset: (preset: Preset) => Promise<void>;
     saveLlmConfig: (config: LlmConfig) => Promise<void>;
+    setLocale: (locale: Locale) => Promise<void>;
+    setLogFullText: (enabled: boolean) => Promise<void>;
+    setBudget: (budget: BudgetSettings) => Promise<void>;
     ensureSession: () => Promise<ChatSession>;
     selectSession: (sessionId: string) => Promise<void>;
     deleteSession: (sessionId: string) => Promise<void>;
@@ -107,8 +115,44 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
 
   const loadAll = async (projectDir: string, nextAppState?: AppState | null) => {
     setBusy((b) => ({ ...b, loading: true }));
+    if (project && project.projectDir !== projectDir) {
+      await api.projectReleaseLock(project.projectDir).catch(() => undefined);
+      await api.projectUnwatch(project.projectDir).catch(() => undefined);
+    }
+    const lock = await api.projectAcquireLock(projectDir, false);
+    if (!lock.acquired) {
+      const holder = lock.holder ? `（${lock.holder.host || "未知设备"}，进程 ${lock.holder.pid}）` : "";
+      const proceed = window.confirm(
+        `该项目正在被另一个 CreatorAI 窗口使用${holder}。同时编辑可能互相覆盖内容，仍要打开吗？`,
+      );
+      if (!proceed) {
+        setBusy((b) => ({ ...b, loading: false }));
+        return;
+      }
+      await api.projectAcquireLock(projectDir, true);
+    }
     const info = await api.storageInitProject(projectDir);
+    const encryption = await api.cryptoStatus(projectDir);
+    if (encryption.enabled && !encryption.unlocked) {
+      // keep asking until the passphrase is right or the user gives up
+      for (;;) {
+        const passphrase = window.prompt("该项目已加密，请输入密码：");
+        if (passphrase === null) {
+          await api.projectReleaseLock(projectDir).catch(() => undefined);
+          setBusy((b) => ({ ...b, loading: false }));
+          return;
+        }
+        try {
+          await api.cryptoUnlock(projectDir, passphrase, false);
+          break;
+        } catch (e) {
+          window.alert(errorMessage(e));
+        }
+      }
+    }
     setProject(info);
+    // reloading is a convenience; editing still works if the watcher can't start
+    await api.projectWatch(projectDir).catch(() => undefined);
 
     const [chs, pr, llm, sums, sessIdx] = await Promise.all([
       api.storageListChapters(projectDir),
@@ -158,6 +202,57 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
+  // files edited outside the app (another editor, sync client, git checkout)
+  const handleExternalChanges = async (changes: ProjectFileChange[]) => {
+    if (!project) return;
+    const dir = project.projectDir;
+    if (changes.some((c) => c.kind === "chapterIndex" || c.kind === "chapter")) {
+      setChapters(await api.storageListChapters(dir));
+    }
+    if (changes.some((c) => c.kind === "preset")) {
+      setPreset(await api.storageLoadPreset(dir));
+    }
+    if (changes.some((c) => c.kind === "sessionIndex" || c.kind === "session")) {
+      setSessions(await api.chatListSessions(dir));
+    }
+    if (activeChapter && changes.some((c) => c.kind === "chapter" && c.chapterId === activeChapter.id && !c.removed)) {
+      const dirty = lastSavedChapterRef.current?.content !== activeChapter.content;
+      const reload =
+        !dirty || window.confirm("当前章节已在其他程序中被修改，是否重新加载？未保存的编辑将丢失。");
+      if (reload) {
+        const ch = await api.storageLoadChapter(dir, activeChapter.id);
+        setActiveChapter(ch);
+        lastSavedChapterRef.current = ch;
+      }
+    }
+    if (activeSession && changes.some((c) => c.kind === "session" && c.sessionId === activeSession.id && !c.removed)) {
+      setActiveSession(await api.chatLoadSession(dir, activeSession.id));
+    }
+  };
+
+  // the listener is registered once, so it goes through a ref to see current state
+  const externalChangesRef = useRef(handleExternalChanges);
+  externalChangesRef.current = handleExternalChanges;
+
+  useEffect(() => {
+    const unlisten = listen<ProjectFileChange[]>("project-files-changed", (event) => {
+      void externalChangesRef.current(event.payload);
+    });
+    return () => {
+      void unlisten.then((f) => f());
+    };
+  }, []);
+
+  // chapter saves are committed in the background; their failures arrive as events
+  useEffect(() => {
+    const unlisten = listen<AppError>("vcs-commit-failed", (event) => {
+      window.alert(errorMessage(event.payload));
+    });
+    return () => {
+      void unlisten.then((f) => f());
+    };
+  }, []);
+
   const openProject = async (projectDir: string) => {
     const st = appState ?? {};
     await persistAppState({ ...st, lastProjectDir: projectDir });
@@ -173,7 +268,14 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
     if (!project || !activeChapter) return;
     setBusy((b) => ({ ...b, saving: true }));
     try {
-      await api.storageSaveChapter(project.projectDir, activeChapter);
+      try {
+        await api.storageSaveChapter(project.projectDir, activeChapter);
+      } catch (e) {
+        // the file changed on disk since it was loaded; only overwrite if the user agrees
+        if (!isAppError(e, "conflict")) throw e;
+        if (!window.confirm(`${e.message}\n\n是否用当前编辑器中的内容覆盖？`)) return;
+        await api.storageSaveChapter(project.projectDir, activeChapter, true);
+      }
       lastSavedChapterRef.current = activeChapter;
       await persistAppState({
         ...(appState ?? {}),
@@ -248,6 +350,19 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
     setLlmConfig(cfg);
   };
 
+  // backend messages switch right away; an open project keeps the language it was created in
+  const setLocale = async (locale: Locale) => {
+    await persistAppState({ ...(appState ?? {}), locale });
+  };
+
+  const setLogFullText = async (enabled: boolean) => {
+    await persistAppState({ ...(appState ?? {}), logFullText: enabled });
+  };
+
+  const setBudget = async (budget: BudgetSettings) => {
+    await persistAppState({ ...(appState ?? {}), budget });
+  };
+
   const ensureSession = async (): Promise<ChatSession> => {
     if (!project) throw new Error("No project");
     if (activeSession) return activeSession;
@@ -309,6 +424,7 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
       await api.storageAppendSummary(project.projectDir, record);
       setSummaries(await api.storageLoadSummaries(project.projectDir));
     }
+    await api.vcsRecordAiAccepted(project.projectDir, nextChapter.id).catch(() => null);
 
     setGenerated(null);
   };
@@ -366,6 +482,9 @@ export function CreatorProvider({ children }: { children: React.ReactNode }) {
         deleteChapter,
         savePreset,
         saveLlmConfig,
+        setLocale,
+        setLogFullText,
+        setBudget,
         ensureSession,
         selectSession,
         deleteSession,
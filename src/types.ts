@@ -1,7 +1,22 @@
+export type Locale = "zh-CN" | "en";
+
 export type AppState = {
   lastProjectDir?: string | null;
   lastSessionId?: string | null;
   lastChapterId?: number | null;
+  // interface language; new projects are created in it too
+  locale?: Locale;
+  // write prompts and generated text to the log files instead of just their length
+  logFullText?: boolean;
+  budget?: BudgetSettings;
+};
+
+// limits across all projects; costs use the currency the endpoint prices are in
+export type BudgetSettings = {
+  dailyCost?: number | null;
+  monthlyCost?: number | null;
+  dailyTokens?: number | null;
+  monthlyTokens?: number | null;
 };
 
 export type ProjectInfo = {
@@ -9,7 +24,7 @@ export type ProjectInfo = {
   projectName: string;
 };
 
-export type ChapterIndexItem = { id: number; title: string };
+export type ChapterIndexItem = { id: number; title: string; volume?: string | null };
 
 export type Chapter = { id: number; title: string; content: string; summary: string };
 
@@ -28,20 +43,95 @@ export type ModelParameters = {
   maxTokens: number;
   topP?: number | null;
   topK?: number | null;
+  minP?: number | null;
+  presencePenalty?: number | null;
+  frequencyPenalty?: number | null;
+  repetitionPenalty?: number | null;
+  stop?: string[];
+  seed?: number | null;
+  reasoningEffort?: ReasoningEffort | null;
+  // merged into the request body as is
+  extraBody?: Record<string, unknown>;
 };
 
+export type ReasoningEffort = "minimal" | "low" | "medium" | "high";
+
+// decides which parameters are sent; "auto" goes by the base URL
+export type Provider = "auto" | "openAi" | "openRouter" | "deepSeek" | "gemini" | "local" | "generic";
+
 export type EndpointConfig = {
   id: string;
   name: string;
   baseUrl: string;
   defaultModel: string;
+  provider?: Provider;
   parameters: ModelParameters;
+  pricing?: ModelPricing[];
+};
+
+// price per million tokens
+export type ModelPricing = { model: string; inputPerMillion: number; outputPerMillion: number };
+
+export type UsageGroupBy = "day" | "project" | "endpoint" | "action";
+
+export type UsageBucket = {
+  key: string;
+  label: string;
+  calls: number;
+  promptTokens: number;
+  completionTokens: number;
+  cost: number;
+  unpricedCalls: number;
 };
 
+export type BudgetStatus = { budget: BudgetSettings; today: UsageBucket; thisMonth: UsageBucket };
+
 export type LlmConfig = {
   endpoints: EndpointConfig[];
   activeEndpointId?: string | null;
   activeModel?: string | null;
+  profiles?: Partial<Record<LlmTask, TaskProfile>>;
+};
+
+export type LlmTask = "continue" | "discuss" | "summarize" | "polish" | "outline" | "analysis";
+
+// unset fields follow the active endpoint, its current model and the endpoint's parameters
+export type TaskProfile = {
+  endpointId?: string | null;
+  model?: string | null;
+  parameters?: ModelParameters | null;
+};
+
+export type TokenUsage = { promptTokens: number; completionTokens: number; totalTokens: number };
+
+// one model call from the project's log; `response` is the raw response body
+export type LlmAuditEntry = {
+  id: string;
+  createdAt: string;
+  action: string;
+  endpointId: string;
+  endpointName: string;
+  model: string;
+  parameters: ModelParameters;
+  messages: { role: string; content: string }[];
+  responseSchema?: unknown;
+  latencyMs: number;
+  usage?: TokenUsage | null;
+  response?: string | null;
+  error?: string | null;
+  replayOf?: string | null;
+};
+
+export type LlmAuditIndexItem = {
+  id: string;
+  createdAt: string;
+  action: string;
+  endpointName: string;
+  model: string;
+  latencyMs: number;
+  totalTokens?: number | null;
+  ok: boolean;
+  replayOf?: string | null;
 };
 
 export type ChatMessage = {
@@ -54,5 +144,168 @@ export type ChatSessionIndexItem = { id: string; title: string };
 
 export type ChatSession = { id: string; title: string; messages: ChatMessage[] };
 
-export type GenerationResponse = { content: string; summary: string; raw?: string | null };
+export type PromptTemplateName = "continueSystem" | "continueUser" | "discussSystem" | "summarizeSystem" | "resume";
+
+export type PromptTemplate = {
+  name: PromptTemplateName;
+  source: string;
+  customized: boolean;
+  variables: string[];
+};
+
+export type PromptMessage = { role: string; content: string };
+
+export type ContextKind = "preset" | "summary" | "chapter" | "history" | "instruction";
+
+export type ContextPiece = { kind: ContextKind; label: string; chars: number; estimatedTokens: number };
+
+export type DryRun = {
+  task: LlmTask;
+  endpointId: string;
+  endpointName: string;
+  provider: Provider;
+  model: string;
+  parameters: ModelParameters;
+  messages: PromptMessage[];
+  requestBody: unknown;
+  context: ContextPiece[];
+  estimatedPromptTokens: number;
+  estimatedMaxCost?: number | null;
+};
+
+export type GenerationResponse = { content: string; summary: string; raw?: string | null; truncated?: boolean };
+
+
+export type ChapterRange = { startId?: number | null; endId?: number | null };
+
+export type EpubExportOptions = {
+  outputPath: string;
+  title?: string | null;
+  author?: string | null;
+  language?: string | null;
+  coverImagePath?: string | null;
+  range?: ChapterRange | null;
+};
+
+export type DocxExportOptions = {
+  outputPath: string;
+  title?: string | null;
+  author?: string | null;
+  range?: ChapterRange | null;
+};
+
+export type MarkdownExportOptions = {
+  outputPath: string;
+  title?: string | null;
+  author?: string | null;
+  splitChapters?: boolean;
+  range?: ChapterRange | null;
+};
+
+export type ManuscriptImportOptions = {
+  filePath: string;
+  encoding?: string | null;
+  headingPatterns?: string[] | null;
+  volumePatterns?: string[] | null;
+};
+
+export type ImportPreviewChapter = {
+  title: string;
+  volume?: string | null;
+  charCount: number;
+  excerpt: string;
+};
+
+export type ImportPreview = { encoding?: string | null; chapters: ImportPreviewChapter[] };
+
+export type BackupSettings = { enabled: boolean; intervalMinutes: number; keep: number };
+
+export type BackupInfo = {
+  path: string;
+  fileName: string;
+  createdAt: string;
+  size: number;
+  automatic: boolean;
+};
+
+export type VersioningSettings = { enabled: boolean; authorName?: string; authorEmail?: string };
+
+export type VcsCommit = {
+  id: string;
+  shortId: string;
+  message: string;
+  author: string;
+  time: string;
+};
 
+export type IntegrityIssueKind =
+  | "orphanedFile"
+  | "missingText"
+  | "missingMeta"
+  | "missingSession"
+  | "duplicateId"
+  | "malformedJson"
+  | "leftoverTemp"
+  | "databaseCorrupt";
+
+export type IntegrityIssue = {
+  kind: IntegrityIssueKind;
+  path: string;
+  chapterId?: number | null;
+  detail: string;
+};
+
+export type IntegrityReport = { issues: IntegrityIssue[] };
+
+export type RepairReport = { changes: string[]; remaining: IntegrityIssue[] };
+
+export type StorageBackend = "files" | "sqlite";
+
+export type SearchHit = { chapterId: number; chapterTitle: string; snippet: string };
+
+export type ProjectLockInfo = {
+  instanceId: string;
+  pid: number;
+  host: string;
+  acquiredAt: number;
+  heartbeatAt: number;
+};
+
+export type ProjectLockStatus = { acquired: boolean; holder?: ProjectLockInfo | null };
+
+export type EncryptionStatus = { enabled: boolean; unlocked: boolean; rememberKey: boolean };
+
+export type EncryptionReport = { removedBackups: number; historyExposed: boolean };
+
+export type FileChangeKind = "chapter" | "chapterIndex" | "preset" | "session" | "sessionIndex";
+
+export type ProjectFileChange = {
+  kind: FileChangeKind;
+  chapterId?: number | null;
+  sessionId?: string | null;
+  removed: boolean;
+};
+
+export type ErrorKind =
+  | "notFound"
+  | "conflict"
+  | "locked"
+  | "io"
+  | "config"
+  | "auth"
+  | "quota"
+  | "rateLimit"
+  | "contextLength"
+  | "server"
+  | "network"
+  | "budget"
+  | "invalid"
+  | "other";
+
+export type AppError = {
+  kind: ErrorKind;
+  key: string;
+  params: Record<string, string>;
+  message: string;
+  sources: string[];
+};